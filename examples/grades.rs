@@ -26,6 +26,7 @@ use rand::thread_rng;
 
 use rust_neuralnet::activation::Sigmoid;
 use rust_neuralnet::builder::NeuralNetworkBuilder;
+use rust_neuralnet::metrics::r_squared;
 use rust_neuralnet::training::{prepare_dataset, Sample};
 
 fn main() {
@@ -47,5 +48,8 @@ fn main() {
 
     println!("expected outputs = {:?}", expected_outputs);
 
+    let predictions = neural_network.predict(inputs.view()).expect("prediction error");
+    println!("R² = {}", r_squared(&predictions.view(), &expected_outputs.view()));
+
     neural_network.backward_propagation(inputs.view(), expected_outputs.view());
 }
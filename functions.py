@@ -9,9 +9,25 @@ import math
 sigmoid = lambda x: 1 / (1 + math.exp(-x))
 sigmoid_dx = lambda x: sigmoid(x) * (1 - sigmoid(x))
 
+softplus = lambda x: math.log1p(math.exp(x))
+softplus_dx = sigmoid
+
+swish = lambda x: x * sigmoid(x)
+swish_dx = lambda x: sigmoid(x) + x * sigmoid(x) * (1 - sigmoid(x))
+
 tanh = lambda x: math.tanh(x)
 tanh_dx = lambda x: 1 - tanh(x) ** 2
 
+gelu_c, gelu_k = math.sqrt(2 / math.pi), 0.044715
+gelu_t = lambda x: math.tanh(gelu_c * (x + gelu_k * x ** 3))
+gelu = lambda x: 0.5 * x * (1 + gelu_t(x))
+gelu_dx = lambda x: 0.5 * (1 + gelu_t(x)) + \
+    0.5 * x * (1 - gelu_t(x) ** 2) * gelu_c * (1 + 3 * gelu_k * x ** 2)
+
+selu_lambda, selu_alpha = 1.0507009873554805, 1.6732632423543772
+selu = lambda x: selu_lambda * (x if x > 0 else selu_alpha * (math.exp(x) - 1))
+selu_dx = lambda x: selu_lambda * (1 if x > 0 else selu_alpha * math.exp(x))
+
 # TO MODIFY
 inputs = [-2.0, -1.0, 0.0, 1.0, 2.0]
 inputs = [0.5 * v for v in inputs]
@@ -1,8 +1,15 @@
-use ndarray::{Array, Dimension, NdFloat};
+use ndarray::{Array, Array2, Dimension, Ix2, NdFloat, Zip};
+
+use super::{Float, NetworkError, NetworkResult};
+use init::WeightInit;
 
 /// An activation function in a Neural Network defines whether a neuron will
 /// send a signal to its outputs or not.
-pub trait Activation<F, D>
+///
+/// Activation functions must be ```Sync``` (and ```Send```), so that layers
+/// can be moved across threads and evaluated over several samples at once,
+/// and ```Clone```, so that layers can be copied.
+pub trait Activation<F, D>: ActivationClone<F, D> + Send + Sync
 where
     F: NdFloat,
     D: Dimension,
@@ -12,9 +19,230 @@ where
 
     #[inline]
     fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D>;
+
+    /// Same as ```compute_derivative```, given both `x` and the already
+    /// computed outputs `y` = compute(`x`).
+    ///
+    /// Functions whose derivative is cheaper to get from their outputs (such
+    /// as ```Sigmoid```) override this fast path.
+    fn compute_derivative_from_output(&self, x: &Array<F, D>, _y: &Array<F, D>) -> Array<F, D> {
+        self.compute_derivative(x)
+    }
+
+    /// Backpropagate `error`, the partial derivative of the cost with
+    /// respect to the outputs `y` = compute(`x`), into its partial derivative
+    /// with respect to `x`: the product of `error` by the Jacobian of the
+    /// function.
+    ///
+    /// For element-wise functions, this is `error` .* derivative(`x`). Other
+    /// functions (such as ```Softmax```) override it.
+    fn backpropagate(&self, x: &Array<F, D>, y: &Array<F, D>, error: &Array<F, D>) -> Array<F, D> {
+        error * &self.compute_derivative_from_output(x, y)
+    }
+
+    /// The learnable parameters of the function, if any.
+    fn parameters(&self) -> Vec<F> {
+        Vec::new()
+    }
+
+    /// Overwrite the learnable parameters of the function, in the same order
+    /// as returned by ```parameters```.
+    fn set_parameters(&mut self, parameters: &[F]) {
+        debug_assert!(parameters.is_empty());
+    }
+
+    /// Reset the learnable parameters of the function to their default
+    /// values, as when reinitializing a ```layer::Layer```.
+    fn reset_parameters(&mut self) {}
+
+    /// Compute, for each learnable parameter, the partial derivative of the
+    /// function with respect to that parameter evaluated at `x`.
+    fn compute_parameters_derivative(&self, _x: &Array<F, D>) -> Vec<Array<F, D>> {
+        Vec::new()
+    }
+
+    /// The ```WeightInit``` best suited to the function, used by the
+    /// ```builder::NeuralNetworkBuilder``` in auto initialization mode.
+    ///
+    /// Defaults to Xavier, suited to the functions symmetric around 0 (such
+    /// as ```TanH```) or saturating (such as ```Sigmoid```), while the
+    /// rectifiers recommend He.
+    fn recommended_init(&self) -> WeightInit {
+        WeightInit::XavierUniform
+    }
+
+    /// The serializable identity of the function, if it has one.
+    fn tag(&self) -> Option<ActivationTag> {
+        None
+    }
+
+    /// The name of the function, used to describe the layers.
+    ///
+    /// Defaults to the name of its ```tag```, or "Custom" without one.
+    fn name(&self) -> &'static str {
+        self.tag().map_or("Custom", |tag| tag.name())
+    }
+}
+
+/// Copies a boxed ```Activation```, implemented for every ```Clone```
+/// activation function.
+pub trait ActivationClone<F, D> {
+    fn clone_box(&self) -> Box<Activation<F, D>>;
+}
+
+impl<F, D, A> ActivationClone<F, D> for A
+where
+    F: NdFloat,
+    D: Dimension,
+    A: 'static + Activation<F, D> + Clone,
+{
+    fn clone_box(&self) -> Box<Activation<F, D>> {
+        Box::new(self.clone())
+    }
+}
+
+impl<F: NdFloat, D: Dimension> Clone for Box<Activation<F, D>> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Identifies a built-in activation function along with its parameters, so
+/// that it can be serialized and later reconstructed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ActivationTag {
+    Identity,
+    Sigmoid,
+    Swish,
+    Softplus,
+    TanH,
+    Gelu,
+    HardSigmoid,
+    HardTanH,
+    Rectifier {
+        negative_slope: Float,
+        max_value: Option<Float>,
+    },
+    LeakyReLU {
+        slope: Float,
+    },
+    Softmax,
+    Selu,
+    PReLU {
+        alpha: Float,
+    },
+}
+
+impl ActivationTag {
+    /// Reconstruct the tagged activation function.
+    pub fn activation(&self) -> Box<Activation<Float, Ix2>> {
+        match *self {
+            ActivationTag::Identity => Box::new(Identity),
+            ActivationTag::Sigmoid => Box::new(Sigmoid),
+            ActivationTag::Swish => Box::new(Swish),
+            ActivationTag::Softplus => Box::new(Softplus),
+            ActivationTag::TanH => Box::new(TanH),
+            ActivationTag::Gelu => Box::new(Gelu),
+            ActivationTag::HardSigmoid => Box::new(HardSigmoid),
+            ActivationTag::HardTanH => Box::new(HardTanH),
+            ActivationTag::Rectifier {
+                negative_slope,
+                max_value,
+            } => Box::new(Rectifier {
+                negative_slope,
+                max_value,
+            }),
+            ActivationTag::LeakyReLU { slope } => Box::new(LeakyReLU { slope }),
+            ActivationTag::Softmax => Box::new(Softmax),
+            ActivationTag::Selu => Box::new(Selu),
+            ActivationTag::PReLU { alpha } => Box::new(PReLU { alpha }),
+        }
+    }
+
+    /// Get the tag of the activation function named `name` (as returned by
+    /// ```name```, ignoring the case), the parametric functions having their
+    /// default parameters. Returns ```None``` for an unknown name.
+    pub fn from_name(name: &str) -> Option<ActivationTag> {
+        builtin_tags()
+            .into_iter()
+            .find(|tag| tag.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Get the name of the tagged activation function.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ActivationTag::Identity => "Identity",
+            ActivationTag::Sigmoid => "Sigmoid",
+            ActivationTag::Swish => "Swish",
+            ActivationTag::Softplus => "Softplus",
+            ActivationTag::TanH => "TanH",
+            ActivationTag::Gelu => "Gelu",
+            ActivationTag::HardSigmoid => "HardSigmoid",
+            ActivationTag::HardTanH => "HardTanH",
+            ActivationTag::Rectifier { .. } => "Rectifier",
+            ActivationTag::LeakyReLU { .. } => "LeakyReLU",
+            ActivationTag::Softmax => "Softmax",
+            ActivationTag::Selu => "Selu",
+            ActivationTag::PReLU { .. } => "PReLU",
+        }
+    }
+}
+
+/// Tags of the built-in activation functions, the parametric ones having
+/// their default parameters.
+fn builtin_tags() -> Vec<ActivationTag> {
+    vec![
+        ActivationTag::Identity,
+        ActivationTag::Sigmoid,
+        ActivationTag::Swish,
+        ActivationTag::Softplus,
+        ActivationTag::TanH,
+        ActivationTag::Gelu,
+        ActivationTag::HardSigmoid,
+        ActivationTag::HardTanH,
+        default_tag::<Rectifier<Float>>(),
+        default_tag::<LeakyReLU<Float>>(),
+        ActivationTag::Softmax,
+        ActivationTag::Selu,
+        default_tag::<PReLU<Float>>(),
+    ]
+}
+
+/// Construct the built-in activation function named `name`, for instance
+/// from a configuration file or the command line.
+///
+/// The names are case-insensitive (e.g. "sigmoid", "tanh" or "identity"),
+/// "relu" standing for ```Rectifier```, and the parametric functions have
+/// their default parameters.
+pub fn parse_activation(name: &str) -> NetworkResult<Box<Activation<Float, Ix2>>> {
+    let lowercase_name = name.to_lowercase();
+    let known_name = match lowercase_name.as_str() {
+        "relu" => "rectifier",
+        lowercase_name => lowercase_name,
+    };
+    ActivationTag::from_name(known_name)
+        .map(|tag| tag.activation())
+        .ok_or_else(|| NetworkError::UnknownActivation {
+            name: name.to_owned(),
+        })
+}
+
+/// Get the tag of a built-in activation function with its default
+/// parameters.
+fn default_tag<A: Activation<Float, Ix2> + Default>() -> ActivationTag {
+    A::default()
+        .tag()
+        .expect("the built-in activation functions are all tagged")
+}
+
+/// Convert a parameter of an activation function to another precision, for
+/// instance to the ```Float``` of its ```ActivationTag```.
+fn cast<F: NdFloat, T: NdFloat>(value: F) -> T {
+    T::from(value).unwrap()
 }
 
 /// The Identity function.
+#[derive(Clone)]
 pub struct Identity;
 impl<F: NdFloat, D: Dimension> Activation<F, D> for Identity {
     #[inline]
@@ -26,27 +254,102 @@ impl<F: NdFloat, D: Dimension> Activation<F, D> for Identity {
         let one = F::one();
         x.map(|_| one)
     }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Identity)
+    }
+}
+
+/// Compute the sigmoid of a scalar value.
+///
+/// For negative values, the equivalent exp(v) / (1 + exp(v)) form is used so
+/// that exp never overflows.
+#[inline]
+fn sigmoid<F: NdFloat>(v: F) -> F {
+    let one = F::one();
+    if v < F::zero() {
+        let e = v.exp();
+        e / (one + e)
+    } else {
+        one / (one + (-v).exp())
+    }
 }
 
 /// The Sigmoid function squashes a real value into the ]0, 1[ range.
+#[derive(Clone)]
 pub struct Sigmoid;
 impl<F: NdFloat, D: Dimension> Activation<F, D> for Sigmoid {
     #[inline]
     fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
-        let one = F::one();
-        x.map(|v: &F| one / (one + (-*v).exp()))
+        x.map(|v: &F| sigmoid(*v))
     }
     #[inline]
     fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
         let one = F::one();
         x.map(|v: &F| {
-            let y = one / (one + (-*v).exp());
+            let y = sigmoid(*v);
             y * (one - y)
         })
     }
+    #[inline]
+    fn compute_derivative_from_output(&self, _x: &Array<F, D>, y: &Array<F, D>) -> Array<F, D> {
+        let one = F::one();
+        y.map(|y| *y * (one - *y))
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Sigmoid)
+    }
+}
+
+/// The Swish function (also known as the Sigmoid Linear Unit) is a smooth,
+/// non-monotonic alternative to ```Rectifier```: f(x) = x * sigmoid(x).
+#[derive(Clone)]
+pub struct Swish;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for Swish {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        x.map(|v: &F| *v * sigmoid(*v))
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let one = F::one();
+        x.map(|v: &F| {
+            let y = sigmoid(*v);
+            y + *v * y * (one - y)
+        })
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Swish)
+    }
+}
+
+/// The Softplus function is a smooth approximation of ```Rectifier```:
+/// f(x) = ln(1 + exp(x)), whose derivative is the sigmoid function.
+#[derive(Clone)]
+pub struct Softplus;
+/// Above this value, ln(1 + exp(x)) is equal to x within floating point
+/// precision and computing exp(x) could overflow.
+const SOFTPLUS_THRESHOLD: f64 = 40.0;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for Softplus {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let threshold = F::from(SOFTPLUS_THRESHOLD).unwrap();
+        x.map(|v| if *v > threshold { *v } else { v.exp().ln_1p() })
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        x.map(|v| sigmoid(*v))
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Softplus)
+    }
 }
 
 /// The Hyperbolic tangent squashes a real value into the ]-1, 1[ range.
+#[derive(Clone)]
 pub struct TanH;
 impl<F: NdFloat, D: Dimension> Activation<F, D> for TanH {
     #[inline]
@@ -58,29 +361,362 @@ impl<F: NdFloat, D: Dimension> Activation<F, D> for TanH {
         let one = F::one();
         x.map(|v| one - v.tanh().powi(2))
     }
+    #[inline]
+    fn compute_derivative_from_output(&self, _x: &Array<F, D>, y: &Array<F, D>) -> Array<F, D> {
+        let one = F::one();
+        y.map(|y| one - y.powi(2))
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::TanH)
+    }
+}
+
+/// The Gaussian Error Linear Unit, using the usual tanh approximation:
+///
+/// f(x) = 0.5 * x * (1 + tanh(sqrt(2/pi) * (x + 0.044715 * x^3)))
+#[derive(Clone)]
+pub struct Gelu;
+const GELU_COEFFICIENT: f64 = 0.044715;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for Gelu {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (half, one) = (F::from(0.5).unwrap(), F::one());
+        let c = F::from((2.0 / ::std::f64::consts::PI).sqrt()).unwrap();
+        let k = F::from(GELU_COEFFICIENT).unwrap();
+        x.map(|v| half * *v * (one + (c * (*v + k * v.powi(3))).tanh()))
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (half, one, three) = (F::from(0.5).unwrap(), F::one(), F::from(3.0).unwrap());
+        let c = F::from((2.0 / ::std::f64::consts::PI).sqrt()).unwrap();
+        let k = F::from(GELU_COEFFICIENT).unwrap();
+        x.map(|v| {
+            let t = (c * (*v + k * v.powi(3))).tanh();
+            half * (one + t) + half * *v * (one - t * t) * c * (one + three * k * v.powi(2))
+        })
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Gelu)
+    }
+}
+
+/// The Hard Sigmoid is a piecewise linear approximation of ```Sigmoid```,
+/// clamping 0.2 * x + 0.5 into the [0, 1] range. It avoids any call to exp.
+#[derive(Clone)]
+pub struct HardSigmoid;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for HardSigmoid {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one) = (F::zero(), F::one());
+        let (slope, offset) = (F::from(0.2).unwrap(), F::from(0.5).unwrap());
+        x.map(|v| (slope * *v + offset).max(zero).min(one))
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one) = (F::zero(), F::one());
+        let (slope, offset) = (F::from(0.2).unwrap(), F::from(0.5).unwrap());
+        x.map(|v| {
+            let y = slope * *v + offset;
+            if y < zero || y > one {
+                zero
+            } else {
+                slope
+            }
+        })
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::HardSigmoid)
+    }
+}
+
+/// The Hard Hyperbolic tangent is a piecewise linear approximation of
+/// ```TanH```, clamping x into the [-1, 1] range. It avoids any call to tanh.
+#[derive(Clone)]
+pub struct HardTanH;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for HardTanH {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let one = F::one();
+        x.map(|v| v.max(-one).min(one))
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one) = (F::zero(), F::one());
+        x.map(|v| if *v < -one || *v > one { zero } else { one })
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::HardTanH)
+    }
 }
 
 /// The Rectified Linear Unit (ReLU) functions replaces negative values with 0.
-pub struct Rectifier;
-impl<F: NdFloat, D: Dimension> Activation<F, D> for Rectifier {
+///
+/// Negative values can instead be scaled by `negative_slope` (leaky behavior),
+/// and positive values can be capped at `max_value` (e.g. 6 for ReLU6).
+/// The default configuration is the plain ReLU.
+#[derive(Clone)]
+pub struct Rectifier<F: NdFloat> {
+    pub negative_slope: F,
+    pub max_value: Option<F>,
+}
+impl<F: NdFloat> Default for Rectifier<F> {
+    fn default() -> Self {
+        Rectifier {
+            negative_slope: F::zero(),
+            max_value: None,
+        }
+    }
+}
+impl<F: NdFloat, D: Dimension> Activation<F, D> for Rectifier<F> {
     #[inline]
     fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
-        let zero = F::zero();
-        x.map(|v| if *v < zero { zero } else { *v })
+        let (zero, slope) = (F::zero(), self.negative_slope);
+        let max = self.max_value.unwrap_or(F::infinity());
+        x.map(|v| if *v < zero { slope * *v } else { v.min(max) })
     }
     #[inline]
     fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one, slope) = (F::zero(), F::one(), self.negative_slope);
+        let max = self.max_value.unwrap_or(F::infinity());
+        x.map(|v| {
+            if *v < zero {
+                slope
+            } else if *v > max {
+                zero
+            } else {
+                one
+            }
+        })
+    }
+
+    fn recommended_init(&self) -> WeightInit {
+        WeightInit::HeUniform
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Rectifier {
+            negative_slope: cast(self.negative_slope),
+            max_value: self.max_value.map(cast),
+        })
+    }
+}
+
+/// The Leaky Rectified Linear Unit scales negative values by a small slope
+/// instead of zeroing them, which keeps a gradient flowing for inactive neurons.
+#[derive(Clone)]
+pub struct LeakyReLU<F: NdFloat> {
+    pub slope: F,
+}
+impl<F: NdFloat> Default for LeakyReLU<F> {
+    fn default() -> Self {
+        LeakyReLU {
+            slope: F::from(0.01).unwrap(),
+        }
+    }
+}
+impl<F: NdFloat, D: Dimension> Activation<F, D> for LeakyReLU<F> {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, slope) = (F::zero(), self.slope);
+        x.map(|v| if *v < zero { slope * *v } else { *v })
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one, slope) = (F::zero(), F::one(), self.slope);
+        x.map(|v| if *v < zero { slope } else { one })
+    }
+
+    fn recommended_init(&self) -> WeightInit {
+        WeightInit::HeUniform
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::LeakyReLU {
+            slope: cast(self.slope),
+        })
+    }
+}
+
+/// The Softmax function turns each row (i.e. sample) of a matrix into a
+/// probability distribution over its columns, making it suited to the output
+/// layer of a classifier.
+///
+/// Unlike the other activations it is not element-wise and is thus only
+/// defined for 2-dimensional ([samples] * [neurons]) arrays.
+#[derive(Clone)]
+pub struct Softmax;
+impl<F: NdFloat> Activation<F, Ix2> for Softmax {
+    /// The maximum of each row is subtracted before exponentiating, which
+    /// leaves the result unchanged but avoids overflows.
+    #[inline]
+    fn compute(&self, x: &Array2<F>) -> Array2<F> {
+        let mut y = x.clone();
+        for mut row in y.outer_iter_mut() {
+            let max = row.iter().fold(F::neg_infinity(), |m, v| m.max(*v));
+            row.mapv_inplace(|v| (v - max).exp());
+            let sum = row.scalar_sum();
+            row.mapv_inplace(|v| v / sum);
+        }
+        y
+    }
+    /// Only the diagonal of the Jacobian is computed (s * (1 - s)): see
+    /// ```backpropagate``` for the complete one.
+    #[inline]
+    fn compute_derivative(&self, x: &Array2<F>) -> Array2<F> {
+        let one = F::one();
+        self.compute(x).map(|s| *s * (one - *s))
+    }
+
+    /// Since each output depends on the whole row, the complete Jacobian of
+    /// each sample is applied:
+    ///
+    /// d(cost)/d(x_i) = s_i * (error_i - sum_j(error_j * s_j))
+    fn backpropagate(&self, _x: &Array2<F>, y: &Array2<F>, error: &Array2<F>) -> Array2<F> {
+        let mut x_error = error * y;
+        for (mut row, s) in x_error.outer_iter_mut().zip(y.outer_iter()) {
+            let weighted_sum = row.scalar_sum();
+            Zip::from(&mut row)
+                .and(&s)
+                .apply(|d, &s| *d = *d - s * weighted_sum);
+        }
+        x_error
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Softmax)
+    }
+}
+
+/// Scale constant of the ```Selu``` function.
+pub const SELU_LAMBDA: f64 = 1.0507009873554805;
+/// Negative saturation constant of the ```Selu``` function.
+pub const SELU_ALPHA: f64 = 1.6732632423543772;
+
+/// The Scaled Exponential Linear Unit makes a network self-normalizing: with
+/// standardized inputs, its outputs keep a mean close to 0 and a variance
+/// close to 1.
+#[derive(Clone)]
+pub struct Selu;
+impl<F: NdFloat, D: Dimension> Activation<F, D> for Selu {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
         let (zero, one) = (F::zero(), F::one());
-        x.map(|v| if *v < zero { zero } else { one })
+        let lambda = F::from(SELU_LAMBDA).unwrap();
+        let alpha = F::from(SELU_ALPHA).unwrap();
+        x.map(|v| if *v > zero { lambda * *v } else { lambda * alpha * (v.exp() - one) })
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let zero = F::zero();
+        let lambda = F::from(SELU_LAMBDA).unwrap();
+        let alpha = F::from(SELU_ALPHA).unwrap();
+        x.map(|v| if *v > zero { lambda } else { lambda * alpha * v.exp() })
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::Selu)
+    }
+}
+
+/// The Parametric Rectified Linear Unit behaves like ```LeakyReLU``` but its
+/// negative slope `alpha` is learned during training.
+///
+/// f(x) = x if x >= 0, alpha * x otherwise
+///
+/// df/dx = 1 if x >= 0, alpha otherwise
+///
+/// df/dalpha = 0 if x >= 0, x otherwise
+#[derive(Clone)]
+pub struct PReLU<F: NdFloat> {
+    pub alpha: F,
+}
+impl<F: NdFloat> Default for PReLU<F> {
+    fn default() -> Self {
+        PReLU {
+            alpha: F::from(0.25).unwrap(),
+        }
+    }
+}
+impl<F: NdFloat> PReLU<F> {
+    /// Compute the partial derivative of the function with respect to `alpha`
+    /// at each pre-activation value of `x`.
+    pub fn compute_alpha_derivative<D: Dimension>(&self, x: &Array<F, D>) -> Array<F, D> {
+        let zero = F::zero();
+        x.map(|v| if *v < zero { *v } else { zero })
+    }
+}
+impl<F: NdFloat, D: Dimension> Activation<F, D> for PReLU<F> {
+    #[inline]
+    fn compute(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, alpha) = (F::zero(), self.alpha);
+        x.map(|v| if *v < zero { alpha * *v } else { *v })
+    }
+    #[inline]
+    fn compute_derivative(&self, x: &Array<F, D>) -> Array<F, D> {
+        let (zero, one, alpha) = (F::zero(), F::one(), self.alpha);
+        x.map(|v| if *v < zero { alpha } else { one })
+    }
+
+    fn parameters(&self) -> Vec<F> {
+        vec![self.alpha]
+    }
+
+    fn set_parameters(&mut self, parameters: &[F]) {
+        assert_eq!(parameters.len(), 1, "PReLU has a single parameter.");
+        self.alpha = parameters[0];
+    }
+
+    fn reset_parameters(&mut self) {
+        *self = PReLU::default();
+    }
+
+    fn compute_parameters_derivative(&self, x: &Array<F, D>) -> Vec<Array<F, D>> {
+        vec![self.compute_alpha_derivative(x)]
+    }
+
+    fn recommended_init(&self) -> WeightInit {
+        WeightInit::HeUniform
+    }
+
+    fn tag(&self) -> Option<ActivationTag> {
+        Some(ActivationTag::PReLU {
+            alpha: cast(self.alpha),
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use ndarray::{Array1, Ix1};
+    use ndarray::{arr2, Array1, Array2, Ix1, Ix2};
     use super::super::Float;
     use super::*;
 
+    #[test]
+    fn parse_activation_names() {
+        let name = |name: &str| parse_activation(name).map(|activation| activation.name());
+        assert_eq!(name("sigmoid"), Ok("Sigmoid"));
+        assert_eq!(name("Sigmoid"), Ok("Sigmoid"));
+        assert_eq!(name("tanh"), Ok("TanH"));
+        assert_eq!(name("TANH"), Ok("TanH"));
+        assert_eq!(name("relu"), Ok("Rectifier"));
+        assert_eq!(name("ReLU"), Ok("Rectifier"));
+        assert_eq!(name("identity"), Ok("Identity"));
+        for tag in builtin_tags() {
+            assert_eq!(name(tag.name()), Ok(tag.name()));
+            assert_eq!(ActivationTag::from_name(tag.name()), Some(tag));
+        }
+        assert_eq!(
+            name("sigmoïd"),
+            Err(NetworkError::UnknownActivation {
+                name: "sigmoïd".to_owned(),
+            })
+        );
+    }
+
     fn test_numerical_function<A, V>(
         function: A,
         inputs: V,
@@ -95,10 +731,54 @@ mod tests {
         assert_eq!(inputs_array.len(), derivatives.len());
         let computed_values = function.compute(&inputs_array);
         let computed_derivatives = function.compute_derivative(&inputs_array);
+        let fast_derivatives =
+            function.compute_derivative_from_output(&inputs_array, &computed_values);
+        // up to the machine precision of Float, be it f64 or f32
+        let precision = Float::EPSILON;
         for i in 0..inputs_array.len() {
-            assert_relative_eq!(computed_values[i], values[i]); // 16 digits precision by default
-            assert_relative_eq!(computed_derivatives[i], derivatives[i]);
+            assert_relative_eq!(
+                computed_values[i],
+                values[i],
+                epsilon = precision,
+                max_relative = precision
+            );
+            assert_relative_eq!(
+                computed_derivatives[i],
+                derivatives[i],
+                epsilon = precision,
+                max_relative = precision
+            );
+            assert_relative_eq!(
+                fast_derivatives[i],
+                derivatives[i],
+                epsilon = precision,
+                max_relative = precision
+            );
+        }
+    }
+
+    #[test]
+    fn derivative_from_output() {
+        fn assert_fast_path_matches<A: Activation<Float, Ix2>>(function: A) {
+            let x = Array2::from_shape_fn((20, 5), |(i, j)| (i as Float - 10.0) * 0.7 + j as Float);
+            let y = function.compute(&x);
+            let derivative = function.compute_derivative(&x);
+            let fast_derivative = function.compute_derivative_from_output(&x, &y);
+            // up to a few roundings of Float, be it f64 or f32
+            let precision = 4.0 * Float::EPSILON;
+            for (fast, expected) in fast_derivative.iter().zip(derivative.iter()) {
+                assert_relative_eq!(
+                    *fast,
+                    *expected,
+                    epsilon = precision,
+                    max_relative = precision
+                );
+            }
         }
+        assert_fast_path_matches(Sigmoid);
+        assert_fast_path_matches(TanH);
+        assert_fast_path_matches(Identity);
+        assert_fast_path_matches(Selu);
     }
 
     #[test]
@@ -129,6 +809,73 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sigmoid_extreme_values() {
+        let inputs: Array1<Float> = vec![-1e6, -800.0, -745.0, 745.0, 800.0, 1e6].into();
+        let values = Sigmoid.compute(&inputs);
+        let derivatives = Sigmoid.compute_derivative(&inputs);
+        for i in 0..inputs.len() {
+            assert!(values[i].is_finite() && values[i] >= 0.0 && values[i] <= 1.0);
+            assert!(derivatives[i].is_finite() && derivatives[i] >= 0.0);
+        }
+        assert_relative_eq!(values[0], 0.0);
+        assert_relative_eq!(values[5], 1.0);
+        assert_relative_eq!(derivatives[0], 0.0);
+        assert_relative_eq!(derivatives[5], 0.0);
+    }
+
+    #[test]
+    fn swish() {
+        test_numerical_function(
+            Swish,
+            vec![-1000.0, -2.0, -1.0, 0.0, 1.0, 2.0, 1000.0],
+            vec![
+                0.0,
+                -0.2384058440442351,
+                -0.2689414213699951,
+                0.0,
+                0.7310585786300049,
+                1.7615941559557646,
+                1000.0,
+            ],
+            vec![
+                0.0,
+                -0.0907842487848955,
+                0.0723294881285132,
+                0.5,
+                0.9276705118714867,
+                1.0907842487848955,
+                1.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn softplus() {
+        test_numerical_function(
+            Softplus,
+            vec![-50.0, -1.0, 0.0, 1.0, 10.0, 700.0],
+            vec![
+                1.9287498479639178e-22,
+                0.3132616875182229,
+                0.6931471805599453,
+                1.3132616875182228,
+                10.000045398899218,
+                700.0,
+            ],
+            vec![
+                1.928749847963918e-22,
+                0.2689414213699951,
+                0.5,
+                0.7310585786300049,
+                0.9999546021312976,
+                1.0,
+            ],
+        );
+        let values = Softplus.compute(&Array1::from_vec(vec![700.0, 1e6]));
+        assert!(values.iter().all(|v: &Float| v.is_finite()));
+    }
+
     #[test]
     fn tanh() {
         test_numerical_function(
@@ -151,13 +898,177 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gelu() {
+        test_numerical_function(
+            Gelu,
+            vec![-3.0, -1.0, -0.5, 0.0, 0.5, 1.0, 3.0],
+            vec![
+                -0.0036373920817730,
+                -0.1588080093917232,
+                -0.1542859901748561,
+                0.0,
+                0.3457140098251439,
+                0.8411919906082768,
+                2.9963626079182270,
+            ],
+            vec![
+                -0.0115841666309695,
+                -0.0829640838457826,
+                0.1326300964653576,
+                0.5,
+                0.8673699035346424,
+                1.0829640838457826,
+                1.0115841666309695,
+            ],
+        );
+    }
+
+    #[test]
+    fn hard_sigmoid() {
+        test_numerical_function(
+            HardSigmoid,
+            vec![-10.0, -2.6, -2.5, 0.0, 1.0, 2.5, 2.6, 10.0],
+            vec![0.0, 0.0, 0.0, 0.5, 0.7, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 0.2, 0.2, 0.2, 0.2, 0.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn hard_tanh() {
+        test_numerical_function(
+            HardTanH,
+            vec![-10.0, -1.1, -1.0, 0.0, 0.5, 1.0, 1.1, 10.0],
+            vec![-1.0, -1.0, -1.0, 0.0, 0.5, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0],
+        );
+    }
+
     #[test]
     fn relu() {
         test_numerical_function(
-            Rectifier,
+            Rectifier::default(),
             vec![-150.0, -7.0, 0.0, 3.0, 10.0],
             vec![0.0, 0.0, 0.0, 3.0, 10.0],
             vec![0.0, 0.0, 1.0, 1.0, 1.0],
         );
     }
+
+    #[test]
+    fn relu_configurable() {
+        test_numerical_function(
+            Rectifier {
+                negative_slope: 0.0,
+                max_value: Some(6.0),
+            },
+            vec![-7.0, 0.0, 3.0, 6.0, 10.0],
+            vec![0.0, 0.0, 3.0, 6.0, 6.0],
+            vec![0.0, 1.0, 1.0, 1.0, 0.0],
+        );
+        test_numerical_function(
+            Rectifier {
+                negative_slope: 0.1,
+                max_value: Some(6.0),
+            },
+            vec![-7.0, 0.0, 3.0, 6.0, 10.0],
+            vec![-0.7, 0.0, 3.0, 6.0, 6.0],
+            vec![0.1, 1.0, 1.0, 1.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn leaky_relu() {
+        test_numerical_function(
+            LeakyReLU::default(),
+            vec![-150.0, -7.0, 0.0, 3.0, 10.0],
+            vec![-1.5, -0.07, 0.0, 3.0, 10.0],
+            vec![0.01, 0.01, 1.0, 1.0, 1.0],
+        );
+        test_numerical_function(
+            LeakyReLU { slope: 0.2 },
+            vec![-5.0, -0.5, 0.0, 0.5, 5.0],
+            vec![-1.0, -0.1, 0.0, 0.5, 5.0],
+            vec![0.2, 0.2, 1.0, 1.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn prelu() {
+        let inputs = vec![-8.0, -2.0, 0.0, 2.0, 8.0];
+        test_numerical_function(
+            PReLU::default(),
+            inputs.clone(),
+            vec![-2.0, -0.5, 0.0, 2.0, 8.0],
+            vec![0.25, 0.25, 1.0, 1.0, 1.0],
+        );
+
+        let mut prelu = PReLU::default();
+        let inputs_array: Array1<Float> = inputs.into();
+        let derivatives = prelu.compute_parameters_derivative(&inputs_array);
+        assert_eq!(derivatives.len(), 1);
+        assert_eq!(derivatives[0], Array1::from_vec(vec![-8.0, -2.0, 0.0, 0.0, 0.0]));
+
+        Activation::<Float, Ix1>::set_parameters(&mut prelu, &[0.5]);
+        assert_eq!(Activation::<Float, Ix1>::parameters(&prelu), vec![0.5]);
+        assert_relative_eq!(prelu.compute(&inputs_array)[1], -1.0);
+    }
+
+    #[test]
+    fn softmax() {
+        let inputs = arr2(&[[1.0, 2.0, 3.0], [1000.0, 1000.0, 1000.0], [-5.0, 0.0, 5.0]]);
+        let values = Softmax.compute(&inputs);
+        let expected = arr2(&[
+            [0.0900305731703805, 0.2447284710547976, 0.6652409557748218],
+            [1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0],
+            [0.0000450940412364, 0.0066925491165893, 0.9932623568421745],
+        ]);
+        for (value, expected) in values.iter().zip(expected.iter()) {
+            assert_relative_eq!(value, expected, epsilon = 1e-12);
+        }
+        for row in values.outer_iter() {
+            assert_relative_eq!(row.scalar_sum(), 1.0, epsilon = 1e-12);
+        }
+
+        let derivatives = Softmax.compute_derivative(&inputs);
+        for (derivative, value) in derivatives.iter().zip(values.iter()) {
+            assert_relative_eq!(*derivative, value * (1.0 - value));
+        }
+    }
+
+    #[test]
+    fn selu() {
+        test_numerical_function(
+            Selu,
+            vec![-2.0, -1.0, 0.0, 1.0, 2.0],
+            vec![
+                -1.5201664685956948,
+                -1.1113307378125625,
+                0.0,
+                1.0507009873554805,
+                2.1014019747109610,
+            ],
+            vec![
+                0.2379328722516818,
+                0.6467686030348140,
+                1.7580993408473766,
+                1.0507009873554805,
+                1.0507009873554805,
+            ],
+        );
+    }
+
+    #[test]
+    fn selu_fixed_point() {
+        use rand::distributions::{IndependentSample, Normal};
+        use rand::{SeedableRng, StdRng};
+
+        let mut rng = StdRng::from_seed(&[42usize][..]);
+        let normal = Normal::new(0.0, 1.0);
+        let inputs = Array1::from_shape_fn(100_000, |_| normal.ind_sample(&mut rng) as Float);
+        let outputs = Selu.compute(&inputs);
+        let mean = outputs.scalar_sum() / outputs.len() as Float;
+        let variance = outputs.map(|v| (v - mean).powi(2)).scalar_sum() / outputs.len() as Float;
+        assert!(mean.abs() < 0.02, "mean = {}", mean);
+        assert!((variance - 1.0).abs() < 0.02, "variance = {}", variance);
+    }
 }
@@ -1,45 +1,141 @@
 //! Facilities meant to make the definition of common Artificial Neural Network
 //! topologies easier.
 
-use rand::Rng;
+use rand::{Rng, StdRng};
 use ndarray::Ix2;
 
-use super::Float;
+use super::{Float, NetworkError, NetworkResult};
 use layer::Layer;
 use network::NeuralNetwork;
-use activation::Activation;
+use activation::{Activation, Softmax};
+use cost::SoftmaxCrossEntropy;
+use init::WeightInit;
+use utils::{seeded_rng, INIT_STREAM};
 
+/// Builds a ```NeuralNetwork``` layer by layer.
+///
+/// The topology is only validated by ```build``` (or ```try_output```), so
+/// that a network described by a user configuration fails with an error
+/// rather than a panic.
 pub struct NeuralNetworkBuilder {
+    /// Number of inputs of the Neural Network.
+    inputs: usize,
     /// Number of outputs of the current last layer.
     last_layer_outputs: usize,
+    /// Weight initialization of the next layers, ```None``` for the one
+    /// recommended by their activation function.
+    init: Option<WeightInit>,
     layers: Vec<Layer<Float>>,
 }
 
 impl NeuralNetworkBuilder {
     pub fn with_inputs(inputs: usize) -> Self {
-        assert!(inputs > 0, "An ANN requires at least 1 input.");
         NeuralNetworkBuilder {
+            inputs,
             last_layer_outputs: inputs,
+            init: Some(WeightInit::default()),
             layers: Vec::new(),
         }
     }
 
+    /// Start a builder owning an RNG seeded with `seed`, so that its layers
+    /// need no RNG argument and two builders with the same seed and topology
+    /// yield identical weights.
+    ///
+    /// The dropout masks of the Neural Network built are also seeded by
+    /// `seed` (see ```NeuralNetwork::seed```).
+    pub fn with_seed(inputs: usize, seed: u64) -> SeededNeuralNetworkBuilder {
+        SeededNeuralNetworkBuilder {
+            builder: NeuralNetworkBuilder::with_inputs(inputs),
+            seed,
+            rng: seeded_rng(seed, INIT_STREAM),
+        }
+    }
+
+    /// Set the weight initialization used by the layers added afterwards.
+    pub fn init(mut self, init: WeightInit) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    /// Initialize the weights of the layers added afterwards as recommended
+    /// by their activation function (see ```Activation::recommended_init```):
+    /// He for the rectifiers, Xavier otherwise.
+    pub fn auto_init(mut self) -> Self {
+        self.init = None;
+        self
+    }
+
+    fn weight_init<A>(&self, activation: &A) -> WeightInit
+    where
+        A: Activation<Float, Ix2>,
+    {
+        self.init.unwrap_or_else(|| activation.recommended_init())
+    }
+
     /// Add a hidden layer with the specified topology and activation function.
     pub fn layer<A: 'static, R>(mut self, neurons: usize, activation: A, rng: &mut R) -> Self
     where
         A: Activation<Float, Ix2>,
         R: Rng,
     {
-        debug_assert!(self.last_layer_outputs > 0);
-        let layer =
-            Layer::with_random_weights(activation, self.last_layer_outputs, neurons, neurons, rng);
+        let init = self.weight_init(&activation);
+        let layer = Layer::with_weight_init(
+            activation,
+            init,
+            self.last_layer_outputs,
+            neurons,
+            neurons,
+            rng,
+        );
         self.layers.push(layer);
         self.last_layer_outputs = neurons;
         self
     }
 
+    /// Add a layer built beforehand, for instance with known weights, whose
+    /// inputs must match the outputs of the current last layer.
+    pub fn add_layer(mut self, layer: Layer<Float>) -> NetworkResult<Self> {
+        if layer.dim_inputs() != self.last_layer_outputs {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.last_layer_outputs,
+                got: layer.dim_inputs(),
+            });
+        }
+        self.last_layer_outputs = layer.dim_outputs();
+        self.layers.push(layer);
+        Ok(self)
+    }
+
+    /// Build the Neural Network from the layers added so far, the last one
+    /// being its output layer.
+    ///
+    /// Fails if the Neural Network has no inputs, no layers or a layer
+    /// without neurons or outputs.
+    pub fn build(self) -> NetworkResult<NeuralNetwork> {
+        if self.inputs == 0 {
+            return Err(NetworkError::NoInputs);
+        }
+        if self.layers.is_empty() {
+            return Err(NetworkError::NoLayers);
+        }
+        let empty_layer = self.layers
+            .iter()
+            .position(|layer| layer.dim_neurons() == 0 || layer.dim_outputs() == 0);
+        if let Some(layer_index) = empty_layer {
+            return Err(NetworkError::EmptyLayer { layer_index });
+        }
+        Ok(NeuralNetwork::new(self.layers))
+    }
+
+    /// Add the output layer and build the Neural Network.
+    ///
+    /// For a classifier, prefer ```softmax_output```.
+    ///
+    /// Panics if the topology is invalid: see ```try_output``` for the
+    /// fallible version.
     pub fn output<A: 'static, R>(
-        mut self,
+        self,
         neurons: usize,
         outputs: usize,
         activation: A,
@@ -49,11 +145,322 @@ impl NeuralNetworkBuilder {
         A: Activation<Float, Ix2>,
         R: Rng,
     {
-        debug_assert!(self.last_layer_outputs > 0);
-        let last_layer =
-            Layer::with_random_weights(activation, self.last_layer_outputs, neurons, outputs, rng);
+        match self.try_output(neurons, outputs, activation, rng) {
+            Ok(neural_network) => neural_network,
+            Err(why) => panic!("NeuralNetworkBuilder.output : {}", why),
+        }
+    }
+
+    /// Add the output layer of a classifier among `classes` classes and build
+    /// the Neural Network, or fail as ```build``` does if the topology is
+    /// invalid.
+    ///
+    /// The layer uses the ```activation::Softmax``` activation, so that each
+    /// sample's outputs form a probability distribution, along with the
+    /// ```cost::SoftmaxCrossEntropy``` cost function.
+    pub fn softmax_output<R: Rng>(
+        mut self,
+        neurons: usize,
+        classes: usize,
+        rng: &mut R,
+    ) -> NetworkResult<NeuralNetwork> {
+        let init = self.weight_init(&Softmax);
+        let last_layer = Layer::with_weight_init(
+            Softmax,
+            init,
+            self.last_layer_outputs,
+            neurons,
+            classes,
+            rng,
+        ).with_cost(SoftmaxCrossEntropy);
+        self.layers.push(last_layer);
+        self.last_layer_outputs = classes;
+        self.build()
+    }
+
+    /// Add the output layer and build the Neural Network, or fail as
+    /// ```build``` does if the topology is invalid.
+    pub fn try_output<A: 'static, R>(
+        mut self,
+        neurons: usize,
+        outputs: usize,
+        activation: A,
+        rng: &mut R,
+    ) -> NetworkResult<NeuralNetwork>
+    where
+        A: Activation<Float, Ix2>,
+        R: Rng,
+    {
+        let init = self.weight_init(&activation);
+        let last_layer = Layer::with_weight_init(
+            activation,
+            init,
+            self.last_layer_outputs,
+            neurons,
+            outputs,
+            rng,
+        );
         self.layers.push(last_layer);
         self.last_layer_outputs = outputs;
-        NeuralNetwork::new(self.layers)
+        self.build()
+    }
+}
+
+/// The topology of a Neural Network, as described by a user configuration
+/// (for instance deserialized from JSON), built by
+/// ```NeuralNetwork::from_config```.
+///
+/// The hidden layers have as many outputs as neurons, the last layer having
+/// `output_dim` outputs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    pub input_dim: usize,
+    pub layers: Vec<LayerSpec>,
+    pub output_dim: usize,
+}
+
+/// The description of a layer within a ```NetworkConfig```.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerSpec {
+    pub neurons: usize,
+    /// Name of the activation function (see
+    /// ```activation::parse_activation```).
+    pub activation: String,
+    /// Name of the weight initialization (see ```WeightInit::from_name```),
+    /// or "Auto" for the one recommended by the activation function.
+    pub init: String,
+}
+
+/// A ```NeuralNetworkBuilder``` drawing the random weights of its layers
+/// from its own seeded RNG.
+pub struct SeededNeuralNetworkBuilder {
+    builder: NeuralNetworkBuilder,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededNeuralNetworkBuilder {
+    /// Set the weight initialization used by the layers added afterwards.
+    pub fn init(mut self, init: WeightInit) -> Self {
+        self.builder = self.builder.init(init);
+        self
+    }
+
+    /// Initialize the weights of the layers added afterwards as recommended
+    /// by their activation function: see ```NeuralNetworkBuilder::auto_init```.
+    pub fn auto_init(mut self) -> Self {
+        self.builder = self.builder.auto_init();
+        self
+    }
+
+    /// Add a hidden layer with the specified topology and activation function.
+    pub fn layer<A: 'static>(mut self, neurons: usize, activation: A) -> Self
+    where
+        A: Activation<Float, Ix2>,
+    {
+        self.builder = self.builder.layer(neurons, activation, &mut self.rng);
+        self
+    }
+
+    /// Add a layer built beforehand: see ```NeuralNetworkBuilder::add_layer```.
+    pub fn add_layer(mut self, layer: Layer<Float>) -> NetworkResult<Self> {
+        self.builder = self.builder.add_layer(layer)?;
+        Ok(self)
+    }
+
+    /// Build the Neural Network: see ```NeuralNetworkBuilder::build```.
+    pub fn build(self) -> NetworkResult<NeuralNetwork> {
+        let seed = self.seed;
+        self.builder.build().map(|network| network.seeded(seed))
+    }
+
+    /// Add the output layer and build the Neural Network.
+    ///
+    /// Panics if the topology is invalid: see ```try_output``` for the
+    /// fallible version.
+    pub fn output<A: 'static>(
+        mut self,
+        neurons: usize,
+        outputs: usize,
+        activation: A,
+    ) -> NeuralNetwork
+    where
+        A: Activation<Float, Ix2>,
+    {
+        self.builder
+            .output(neurons, outputs, activation, &mut self.rng)
+            .seeded(self.seed)
+    }
+
+    /// Add the output layer of a classifier and build the Neural Network: see
+    /// ```NeuralNetworkBuilder::softmax_output```.
+    pub fn softmax_output(
+        mut self,
+        neurons: usize,
+        classes: usize,
+    ) -> NetworkResult<NeuralNetwork> {
+        let seed = self.seed;
+        self.builder
+            .softmax_output(neurons, classes, &mut self.rng)
+            .map(|network| network.seeded(seed))
+    }
+
+    /// Add the output layer and build the Neural Network, or fail if the
+    /// topology is invalid.
+    pub fn try_output<A: 'static>(
+        mut self,
+        neurons: usize,
+        outputs: usize,
+        activation: A,
+    ) -> NetworkResult<NeuralNetwork>
+    where
+        A: Activation<Float, Ix2>,
+    {
+        let seed = self.seed;
+        self.builder
+            .try_output(neurons, outputs, activation, &mut self.rng)
+            .map(|network| network.seeded(seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr2, ArrayView2};
+    use rand::{SeedableRng, StdRng};
+
+    use activation::{Identity, Rectifier, Sigmoid};
+    use super::*;
+
+    #[test]
+    fn add_layer() {
+        let hidden_layer = Layer::new(
+            Identity,
+            arr2(&[[1.0, 0.0], [0.0, 1.0]]),
+            arr2(&[[1.0, 0.0, 2.0], [0.0, 1.0, 0.0]]),
+        );
+        let output_layer = Layer::new(Identity, arr2(&[[1.0], [1.0], [1.0]]), arr2(&[[2.0]]));
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .add_layer(hidden_layer)
+            .and_then(|builder| builder.add_layer(output_layer))
+            .and_then(|builder| builder.build())
+            .unwrap();
+        let outputs = neural_network.predict(arr2(&[[1.0, 2.0]]).view()).unwrap();
+        // hidden outputs: [1, 2, 2]
+        assert_eq!(outputs, arr2(&[[10.0]]));
+
+        // mixed with random layers
+        let mut rng = StdRng::from_seed(&[65usize][..]);
+        let neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .add_layer(Layer::new(Identity, arr2(&[[1.0], [1.0]]), arr2(&[[1.0, 1.0, 1.0]])))
+            .unwrap()
+            .layer(4, Sigmoid, &mut rng)
+            .output(2, 1, Sigmoid, &mut rng);
+        assert_eq!(neural_network.input_dim(), 2);
+        assert_eq!(neural_network.output_dim(), 1);
+    }
+
+    #[test]
+    fn topology_errors() {
+        let mut rng = StdRng::from_seed(&[66usize][..]);
+        let error = NeuralNetworkBuilder::with_inputs(0)
+            .try_output(3, 1, Sigmoid, &mut rng)
+            .err();
+        assert_eq!(error, Some(NetworkError::NoInputs));
+
+        let error = NeuralNetworkBuilder::with_inputs(2)
+            .layer(0, Sigmoid, &mut rng)
+            .try_output(3, 1, Sigmoid, &mut rng)
+            .err();
+        assert_eq!(error, Some(NetworkError::EmptyLayer { layer_index: 0 }));
+
+        let error = NeuralNetworkBuilder::with_inputs(2)
+            .layer(4, Sigmoid, &mut rng)
+            .try_output(3, 0, Sigmoid, &mut rng)
+            .err();
+        assert_eq!(error, Some(NetworkError::EmptyLayer { layer_index: 1 }));
+
+        // a single output layer is a valid topology
+        let neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .try_output(3, 1, Sigmoid, &mut rng)
+            .unwrap();
+        assert_eq!(neural_network.output_dim(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn output_panics_on_invalid_topology() {
+        let mut rng = StdRng::from_seed(&[66usize][..]);
+        NeuralNetworkBuilder::with_inputs(0).output(3, 1, Sigmoid, &mut rng);
+    }
+
+    #[test]
+    fn add_layer_errors() {
+        let layer = Layer::new(Identity, arr2(&[[1.0], [1.0], [1.0]]), arr2(&[[2.0]]));
+        let error = NeuralNetworkBuilder::with_inputs(2).add_layer(layer).err();
+        assert_eq!(
+            error,
+            Some(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 3,
+            })
+        );
+        let error = NeuralNetworkBuilder::with_inputs(2).build().err();
+        assert_eq!(error, Some(NetworkError::NoLayers));
+    }
+
+    #[test]
+    fn seeded_builds_are_reproducible() {
+        let build = |seed| {
+            NeuralNetworkBuilder::with_seed(2, seed)
+                .layer(4, Sigmoid)
+                .output(3, 1, Sigmoid)
+        };
+        let (first, second, other) = (build(67), build(67), build(68));
+        let weights = |neural_network: &NeuralNetwork| {
+            neural_network
+                .layers()
+                .iter()
+                .flat_map(|layer| {
+                    layer
+                        .inputs_weights()
+                        .iter()
+                        .chain(layer.outputs_weights().iter())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<Float>>()
+        };
+        assert_eq!(weights(&first), weights(&second));
+        assert!(weights(&first) != weights(&other));
+    }
+
+    #[test]
+    fn auto_init() {
+        let variance = |weights: ArrayView2<Float>| {
+            weights.mapv(|w| w * w).scalar_sum() / weights.len() as Float
+        };
+        let neural_network = NeuralNetworkBuilder::with_seed(200, 80)
+            .auto_init()
+            .layer(100, Rectifier::default())
+            .output(100, 1, Sigmoid);
+        let relu_layer = &neural_network.layers()[0];
+        let sigmoid_layer = &neural_network.layers()[1];
+        // He: 2 / fan_in
+        assert_relative_eq!(variance(relu_layer.inputs_weights()), 0.01, max_relative = 0.05);
+        let limit = (6.0 as Float / 200.0).sqrt();
+        assert!(relu_layer.inputs_weights().iter().all(|w| w.abs() < limit));
+        // Xavier: 2 / (fan_in + fan_out)
+        assert_relative_eq!(variance(sigmoid_layer.inputs_weights()), 0.01, max_relative = 0.05);
+        assert_relative_eq!(
+            variance(sigmoid_layer.outputs_weights()),
+            2.0 / 101.0,
+            max_relative = 0.3
+        );
+
+        let neural_network = NeuralNetworkBuilder::with_seed(200, 80)
+            .auto_init()
+            .output(100, 1, Sigmoid);
+        let inputs_weights = neural_network.layers()[0].inputs_weights();
+        assert_relative_eq!(variance(inputs_weights), 2.0 / 300.0, max_relative = 0.05);
     }
 }
@@ -0,0 +1,509 @@
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, NdFloat};
+
+use utils::stable_column_sums;
+
+/// A cost (or loss) function measures how far the outputs estimated by an
+/// Artificial Neural Network are from the expected outputs.
+///
+/// Both `predicted` and `expected` are ([samples] * [outputs]) matrices.
+pub trait Cost<F>: CostClone<F>
+where
+    F: NdFloat,
+{
+    /// Compute the cost for each output.
+    ///
+    /// costs : (1 * [outputs])
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F>;
+
+    /// Compute the partial derivative of the cost with respect to each
+    /// predicted value.
+    ///
+    /// cost_derivative : ([samples] * [outputs])
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F>;
+
+    /// Compute the cost for each output, the cost of each sample being
+    /// scaled by its weight (e.g. the weight of its class).
+    ///
+    /// sample_weights : ([samples])
+    ///
+    /// The default implementation evaluates ```cost``` sample by sample,
+    /// which suits the costs summed over the samples.
+    fn weighted_cost(
+        &self,
+        predicted: &ArrayView2<F>,
+        expected: &ArrayView2<F>,
+        sample_weights: &ArrayView1<F>,
+    ) -> Array1<F> {
+        let mut sample_costs = Array2::zeros(predicted.dim());
+        let samples = predicted
+            .axis_chunks_iter(Axis(0), 1)
+            .zip(expected.axis_chunks_iter(Axis(0), 1))
+            .zip(sample_weights)
+            .zip(sample_costs.genrows_mut());
+        for (((predicted, expected), &weight), mut costs) in samples {
+            costs.assign(&(self.cost(&predicted, &expected) * weight));
+        }
+        stable_column_sums(&sample_costs)
+    }
+
+    /// Does ```cost_derivative``` already include the derivative of the
+    /// output activation function, i.e. is it the partial derivative of the
+    /// cost with respect to the weighted sums of the outputs?
+    ///
+    /// Such a cost is specific to an activation function (see
+    /// ```SoftmaxCrossEntropy```) and must only be used by an output layer.
+    fn fused_with_activation(&self) -> bool {
+        false
+    }
+}
+
+/// Copies a boxed ```Cost```, implemented for every ```Clone``` cost
+/// function.
+pub trait CostClone<F> {
+    fn clone_box(&self) -> Box<Cost<F>>;
+}
+
+impl<F, C> CostClone<F> for C
+where
+    F: NdFloat,
+    C: 'static + Cost<F> + Clone,
+{
+    fn clone_box(&self) -> Box<Cost<F>> {
+        Box::new(self.clone())
+    }
+}
+
+impl<F: NdFloat> Clone for Box<Cost<F>> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The Mean Squared Error cost function.
+///
+/// cost = 1/2 * sum((expected - predicted) ^ 2)
+///
+/// d(cost)/d(predicted) = predicted - expected
+#[derive(Clone)]
+pub struct MeanSquaredError;
+impl<F: NdFloat> Cost<F> for MeanSquaredError {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        let half = F::from(0.5).unwrap();
+        let squared_diffs = (expected - predicted).mapv(|d| d.powi(2));
+        stable_column_sums(&squared_diffs) * half
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        predicted - expected
+    }
+}
+
+/// The Mean Squared Error cost function, the squared error of each output
+/// being scaled by its weight, so that some outputs of a multi-output
+/// regression matter more than others.
+///
+/// cost = 1/2 * sum((expected - predicted) ^ 2) .* output_weights
+///
+/// d(cost)/d(predicted) = (predicted - expected) .* output_weights
+///
+/// The weights must hold one value per output: see
+/// ```layer::Layer::with_output_weights```, which checks it.
+#[derive(Clone)]
+pub struct WeightedMeanSquaredError<F: NdFloat> {
+    output_weights: Array1<F>,
+}
+
+impl<F: NdFloat> WeightedMeanSquaredError<F> {
+    /// Returns ```None``` if a weight is negative or not finite.
+    pub fn new(output_weights: Array1<F>) -> Option<Self> {
+        if output_weights
+            .iter()
+            .any(|&weight| !weight.is_finite() || weight < F::zero())
+        {
+            return None;
+        }
+        Some(WeightedMeanSquaredError { output_weights })
+    }
+
+    pub fn output_weights(&self) -> ArrayView1<F> {
+        self.output_weights.view()
+    }
+}
+
+impl<F: NdFloat> Cost<F> for WeightedMeanSquaredError<F> {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        MeanSquaredError.cost(predicted, expected) * &self.output_weights
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        (predicted - expected) * &self.output_weights
+    }
+}
+
+/// The Absolute Error (L1) cost function, less sensitive to outliers than
+/// ```MeanSquaredError```.
+///
+/// cost = sum(|expected - predicted|)
+///
+/// d(cost)/d(predicted) = sign(predicted - expected), defined as 0 when
+/// predicted == expected
+///
+/// Like the other costs, it is summed over the samples: averaged over them,
+/// it is the mean absolute error.
+#[derive(Clone)]
+pub struct AbsoluteError;
+impl<F: NdFloat> Cost<F> for AbsoluteError {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        stable_column_sums(&(expected - predicted).mapv(|d| d.abs()))
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        let (zero, one) = (F::zero(), F::one());
+        (predicted - expected).mapv(|d| {
+            if d > zero {
+                one
+            } else if d < zero {
+                -one
+            } else {
+                zero
+            }
+        })
+    }
+}
+
+/// The Huber cost function is quadratic for residuals within `delta` and
+/// linear outside, combining the smoothness of ```MeanSquaredError``` near
+/// zero with the robustness to outliers of ```AbsoluteError```.
+///
+/// With r = predicted - expected:
+///
+/// cost = sum(1/2 * r ^ 2) if |r| <= delta, sum(delta * (|r| - 1/2 * delta)) otherwise
+///
+/// d(cost)/d(predicted) = r if |r| <= delta, delta * sign(r) otherwise
+#[derive(Clone)]
+pub struct Huber<F: NdFloat> {
+    pub delta: F,
+}
+impl<F: NdFloat> Default for Huber<F> {
+    fn default() -> Self {
+        Huber { delta: F::one() }
+    }
+}
+impl<F: NdFloat> Cost<F> for Huber<F> {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        let (half, delta) = (F::from(0.5).unwrap(), self.delta);
+        let costs = (predicted - expected).mapv(|r| {
+            if r.abs() <= delta {
+                half * r * r
+            } else {
+                delta * (r.abs() - half * delta)
+            }
+        });
+        stable_column_sums(&costs)
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        let delta = self.delta;
+        (predicted - expected).mapv(|r| if r.abs() <= delta { r } else { delta * r.signum() })
+    }
+}
+
+/// The Log-Cosh cost function behaves like ```MeanSquaredError``` for small
+/// residuals and like ```AbsoluteError``` for large ones, while being
+/// twice differentiable everywhere.
+///
+/// With r = predicted - expected:
+///
+/// cost = sum(ln(cosh(r)))
+///
+/// d(cost)/d(predicted) = tanh(r)
+///
+/// ln(cosh(r)) is evaluated as |r| + ln(1 + exp(-2|r|)) - ln(2), so that
+/// cosh never overflows: it tends to |r| - ln(2) for large residuals.
+#[derive(Clone)]
+pub struct LogCosh;
+impl<F: NdFloat> Cost<F> for LogCosh {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        let (two, ln_2) = (F::from(2.0).unwrap(), F::from(::std::f64::consts::LN_2).unwrap());
+        let costs = (predicted - expected).mapv(|r| {
+            let r = r.abs();
+            r + (-two * r).exp().ln_1p() - ln_2
+        });
+        stable_column_sums(&costs)
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        (predicted - expected).mapv(|r| r.tanh())
+    }
+}
+
+/// The Cross-Entropy cost function, meant for classifiers whose expected
+/// outputs are probability distributions (e.g. one-hot vectors), typically
+/// paired with a ```activation::Softmax``` output.
+///
+/// cost = - sum(expected * ln(predicted))
+///
+/// d(cost)/d(predicted) = - expected / predicted
+///
+/// The predicted values are clamped into [epsilon, 1 - epsilon] so that
+/// ln(0) is never evaluated.
+#[derive(Clone)]
+pub struct CrossEntropy;
+const CROSS_ENTROPY_EPSILON: f64 = 1e-12;
+impl CrossEntropy {
+    fn clamp<F: NdFloat>(predicted: &ArrayView2<F>) -> Array2<F> {
+        let epsilon = F::from(CROSS_ENTROPY_EPSILON).unwrap();
+        let max = F::one() - epsilon;
+        predicted.mapv(|p| p.max(epsilon).min(max))
+    }
+}
+impl<F: NdFloat> Cost<F> for CrossEntropy {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        let log_predicted = CrossEntropy::clamp(predicted).mapv(|p| p.ln());
+        -stable_column_sums(&(expected * &log_predicted))
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        -(expected / &CrossEntropy::clamp(predicted))
+    }
+}
+
+/// The Cross-Entropy cost function fused with a ```activation::Softmax```
+/// output layer, whose derivative with respect to the weighted sums of the
+/// outputs simplifies into the numerically stable:
+///
+/// d(cost)/d(outputs_sum) = predicted - expected
+///
+/// instead of the product of the cross-entropy derivative with the Jacobian
+/// of the softmax, computed separately.
+///
+/// The cost itself is the one of ```CrossEntropy```.
+#[derive(Clone)]
+pub struct SoftmaxCrossEntropy;
+impl<F: NdFloat> Cost<F> for SoftmaxCrossEntropy {
+    fn cost(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array1<F> {
+        CrossEntropy.cost(predicted, expected)
+    }
+
+    fn cost_derivative(&self, predicted: &ArrayView2<F>, expected: &ArrayView2<F>) -> Array2<F> {
+        predicted - expected
+    }
+
+    fn fused_with_activation(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2};
+    use super::super::Float;
+    use activation::TanH;
+    use builder::NeuralNetworkBuilder;
+    use init::WeightInit;
+    use testing::{gradient_check, gradient_check_tolerance};
+    use training::one_hot;
+    use super::*;
+
+    #[test]
+    fn mean_squared_error() {
+        let predicted = arr2(&[[0.5, 1.0], [2.0, -1.0], [0.0, 0.0]]);
+        let expected = arr2(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        let costs: Array1<Float> = MeanSquaredError.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 0.5 * (0.25 + 1.0 + 1.0));
+        assert_relative_eq!(costs[1], 0.5 * (0.0 + 4.0 + 1.0));
+
+        let derivatives = MeanSquaredError.cost_derivative(&predicted.view(), &expected.view());
+        assert_eq!(derivatives, arr2(&[[-0.5, 0.0], [1.0, -2.0], [-1.0, -1.0]]));
+    }
+
+    #[test]
+    fn mean_squared_error_of_tiny_residuals() {
+        // a residual of 1 followed by many residuals whose squared halves
+        // (Float::EPSILON / 8) are below half the ulp of 0.5, which a naive
+        // summation drops
+        let samples = 100_000;
+        let residual = Float::EPSILON.sqrt() / 2.0;
+        let predicted: Array2<Float> = Array2::from_shape_fn((samples + 1, 1), |(i, _)| {
+            if i == 0 {
+                1.0
+            } else {
+                residual
+            }
+        });
+        let expected = Array2::zeros((samples + 1, 1));
+        let costs = MeanSquaredError.cost(&predicted.view(), &expected.view());
+        let reference = samples as Float * Float::EPSILON / 8.0;
+        assert_relative_eq!(costs[0] - 0.5, reference, max_relative = 1e-3);
+    }
+
+    #[test]
+    fn weighted_costs() {
+        let predicted = arr2(&[[0.5, 3.0], [2.0, 1.0], [-4.0, 0.0]]);
+        let expected = arr2(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        let unit_weights = Array1::from_elem(3, 1.0);
+        let weights = arr1(&[2.0, 0.0, 1.0]);
+        let costs: &[&Cost<Float>] = &[
+            &MeanSquaredError,
+            &AbsoluteError,
+            &Huber::default(),
+            &LogCosh,
+        ];
+        for cost in costs {
+            let (predicted, expected) = (predicted.view(), expected.view());
+            assert_eq!(
+                cost.weighted_cost(&predicted, &expected, &unit_weights.view()),
+                cost.cost(&predicted, &expected)
+            );
+        }
+        let costs = MeanSquaredError.weighted_cost(
+            &predicted.view(),
+            &expected.view(),
+            &weights.view(),
+        );
+        assert_relative_eq!(costs[0], 0.5 * (2.0 * 0.25 + 25.0));
+        let costs = AbsoluteError.weighted_cost(
+            &predicted.view(),
+            &expected.view(),
+            &weights.view(),
+        );
+        assert_relative_eq!(costs[1], 2.0 * 2.0 + 1.0);
+    }
+
+    #[test]
+    fn absolute_error() {
+        let predicted = arr2(&[[0.5, 3.0], [2.0, 1.0], [-4.0, 0.0]]);
+        let expected = arr2(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        let costs: Array1<Float> = AbsoluteError.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 0.5 + 1.0 + 5.0);
+        assert_relative_eq!(costs[1], 2.0 + 0.0 + 1.0);
+        // an empty batch costs nothing
+        let empty = Array2::<Float>::zeros((0, 2));
+        assert_eq!(AbsoluteError.cost(&empty.view(), &empty.view()), arr1(&[0.0, 0.0]));
+
+        let derivatives = AbsoluteError.cost_derivative(&predicted.view(), &expected.view());
+        assert_eq!(derivatives, arr2(&[[-1.0, 1.0], [1.0, 0.0], [-1.0, -1.0]]));
+    }
+
+    #[test]
+    fn weighted_mean_squared_error() {
+        let predicted = arr2(&[[0.5, 1.0], [2.0, -1.0], [0.0, 0.0]]);
+        let expected = arr2(&[[1.0, 1.0], [1.0, 1.0], [1.0, 1.0]]);
+        let cost = WeightedMeanSquaredError::new(arr1(&[2.0, 0.0])).unwrap();
+        assert_eq!(cost.output_weights(), arr1(&[2.0, 0.0]));
+        let costs: Array1<Float> = cost.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 2.0 * 0.5 * (0.25 + 1.0 + 1.0));
+        assert_eq!(costs[1], 0.0);
+        let derivatives = cost.cost_derivative(&predicted.view(), &expected.view());
+        assert_eq!(derivatives, arr2(&[[-1.0, 0.0], [2.0, 0.0], [-2.0, 0.0]]));
+        for &invalid in &[-1.0, Float::NAN, Float::INFINITY] {
+            assert!(WeightedMeanSquaredError::new(arr1(&[1.0, invalid])).is_none());
+        }
+    }
+
+    #[test]
+    fn huber() {
+        let huber = Huber { delta: 2.0 };
+        let predicted = arr2(&[[1.0], [5.0], [-3.0]]);
+        let expected = arr2(&[[0.0], [0.0], [0.0]]);
+        let costs: Array1<Float> = huber.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 0.5 + 2.0 * (5.0 - 1.0) + 2.0 * (3.0 - 1.0));
+        let derivatives = huber.cost_derivative(&predicted.view(), &expected.view());
+        assert_eq!(derivatives, arr2(&[[1.0], [2.0], [-2.0]]));
+    }
+
+    #[test]
+    fn huber_continuity() {
+        let huber: Huber<Float> = Huber::default();
+        let epsilon = 1e-9;
+        let expected = arr2(&[[0.0]]);
+        for &boundary in &[-huber.delta, huber.delta] {
+            let inside = arr2(&[[boundary * (1.0 - epsilon)]]);
+            let outside = arr2(&[[boundary * (1.0 + epsilon)]]);
+            let cost_inside = huber.cost(&inside.view(), &expected.view())[0];
+            let cost_outside = huber.cost(&outside.view(), &expected.view())[0];
+            assert_relative_eq!(cost_inside, cost_outside, epsilon = 1e-8);
+            let derivative_inside = huber.cost_derivative(&inside.view(), &expected.view());
+            let derivative_outside = huber.cost_derivative(&outside.view(), &expected.view());
+            assert_relative_eq!(
+                derivative_inside[[0, 0]],
+                derivative_outside[[0, 0]],
+                epsilon = 1e-8
+            );
+        }
+    }
+
+    #[test]
+    fn log_cosh() {
+        let predicted = arr2(&[[1.0, 0.0], [-0.5, 2.0]]);
+        let expected = arr2(&[[0.0, 0.0], [0.0, 2.0]]);
+        let costs: Array1<Float> = LogCosh.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 0.4337808304830271 + 0.12011450695827745, epsilon = 1e-6);
+        assert_relative_eq!(costs[1], 0.0);
+        let derivatives = LogCosh.cost_derivative(&predicted.view(), &expected.view());
+        assert_relative_eq!(derivatives[[0, 0]], 0.7615941559557649, epsilon = 1e-6);
+        assert_relative_eq!(derivatives[[1, 0]], -0.46211715726000974, epsilon = 1e-6);
+
+        // cosh(1000) overflows, its logarithm does not
+        let large = arr2(&[[1000.0], [-1000.0]]);
+        let zeros = arr2(&[[0.0], [0.0]]);
+        let costs: Array1<Float> = LogCosh.cost(&large.view(), &zeros.view());
+        assert!(costs[0].is_finite());
+        assert_relative_eq!(costs[0], 2.0 * (1000.0 - ::std::f64::consts::LN_2) as Float);
+        let derivatives = LogCosh.cost_derivative(&large.view(), &zeros.view());
+        assert_eq!(derivatives, arr2(&[[1.0], [-1.0]]));
+    }
+
+    #[test]
+    fn cross_entropy() {
+        let predicted = arr2(&[[0.7, 0.2, 0.1], [0.1, 0.8, 0.1], [0.25, 0.25, 0.5]]);
+        let expected = arr2(&[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        let costs: Array1<Float> = CrossEntropy.cost(&predicted.view(), &expected.view());
+        assert_relative_eq!(costs[0], 0.3566749439387324);
+        assert_relative_eq!(costs[1], 0.2231435513142097);
+        assert_relative_eq!(costs[2], 0.6931471805599453);
+
+        let derivatives = CrossEntropy.cost_derivative(&predicted.view(), &expected.view());
+        let expected_derivatives =
+            arr2(&[[-1.0 / 0.7, 0.0, 0.0], [0.0, -1.25, 0.0], [0.0, 0.0, -2.0]]);
+        for (d, expected_d) in derivatives.iter().zip(expected_derivatives.iter()) {
+            assert_relative_eq!(d, expected_d);
+        }
+    }
+
+    #[test]
+    fn cross_entropy_clamping() {
+        let predicted = arr2(&[[0.0, 1.0]]);
+        let expected = arr2(&[[1.0, 0.0]]);
+        let costs: Array1<Float> = CrossEntropy.cost(&predicted.view(), &expected.view());
+        let derivatives = CrossEntropy.cost_derivative(&predicted.view(), &expected.view());
+        assert!(costs.iter().all(|c| c.is_finite()));
+        assert!(derivatives.iter().all(|d| d.is_finite()));
+    }
+
+    #[test]
+    fn softmax_cross_entropy_gradients() {
+        let mut neural_network = NeuralNetworkBuilder::with_seed(2, 79)
+            .init(WeightInit::XavierUniform)
+            .layer(4, TanH)
+            .softmax_output(5, 3)
+            .unwrap();
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2], [-0.3, 0.8], [0.0, 0.0]]);
+        let expected_outputs = one_hot(&[0, 2, 1, 2], 3).unwrap();
+        let (step, tolerance) = gradient_check_tolerance();
+        let error = gradient_check(
+            &mut neural_network,
+            inputs.view(),
+            expected_outputs.view(),
+            step,
+        ).unwrap();
+        assert!(error < tolerance, "max relative error : {}", error);
+
+        let predicted = arr2(&[[0.7, 0.2, 0.1]]);
+        let expected = arr2(&[[0.0, 1.0, 0.0]]);
+        assert_eq!(
+            SoftmaxCrossEntropy.cost_derivative(&predicted.view(), &expected.view()),
+            &predicted - &expected
+        );
+    }
+}
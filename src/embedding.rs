@@ -0,0 +1,257 @@
+//! Embedding of categorical inputs into dense vectors.
+
+use rand::Rng;
+use ndarray::{Array2, ArrayView2, NdFloat};
+
+use super::{Float, NetworkError, NetworkResult};
+use init::WeightInit;
+
+/// A layer mapping categorical inputs (integer indices within
+/// [0, categories[) to dense, learnable vectors, meant to precede the first
+/// ```Layer``` of a ```NeuralNetwork``` instead of one-hot encoding the
+/// categories by hand.
+///
+/// Each sample holds the categories of one or more fields, whose embeddings
+/// are concatenated into the inputs of the next layer:
+///
+/// indices : ([samples] * [fields])
+///
+/// weights : ([categories] * [dimension]), one embedding per row
+///
+/// outputs : ([samples] * [fields * dimension])
+///
+/// The gradient is only ever non-zero for, and the weights only updated on,
+/// the rows of the categories seen by the last forward propagation.
+///
+/// An embedding layer is not one of the layers of a ```NeuralNetwork```,
+/// whose inputs are dense: the caller drives it by hand at each training
+/// step, by
+///
+/// 1. passing the outputs of ```forward_propagation``` as the inputs of
+///    ```NeuralNetwork::backward_propagation```,
+/// 2. passing the partial derivative of the loss with respect to these
+///    inputs, which it returns, to ```backward_propagation```,
+/// 3. updating both the network and the embeddings (```apply_gradients```).
+///
+/// At prediction time, the outputs of ```forward_propagation``` are likewise
+/// the inputs of ```NeuralNetwork::predict```. Nor is the embedding layer
+/// saved along with the network.
+pub struct EmbeddingLayer<F: NdFloat> {
+    weights: Array2<F>,
+    // cached results
+    indices: Array2<usize>,
+    outputs: Array2<F>,
+    cost_d_weights: Array2<F>,
+    /// Categories of the cached indices, in increasing order.
+    seen_categories: Vec<usize>,
+}
+
+impl<F: NdFloat> EmbeddingLayer<F> {
+    /// Create a new embedding layer from its ([categories] * [dimension])
+    /// weights.
+    pub fn new(weights: Array2<F>) -> Self {
+        EmbeddingLayer {
+            cost_d_weights: Array2::zeros(weights.dim()),
+            weights,
+            indices: Array2::zeros((0, 0)),
+            outputs: Array2::zeros((0, 0)),
+            seen_categories: Vec::new(),
+        }
+    }
+
+    /// Get the number of categories that can be embedded.
+    pub fn num_categories(&self) -> usize {
+        self.weights.rows()
+    }
+
+    /// Get the dimension of each embedding.
+    pub fn dimension(&self) -> usize {
+        self.weights.cols()
+    }
+
+    /// Get the ([categories] * [dimension]) embeddings.
+    pub fn weights(&self) -> ArrayView2<F> {
+        self.weights.view()
+    }
+
+    /// Look up the embeddings of the ([samples] * [fields]) `indices` and
+    /// return a view to their concatenation, sample by sample.
+    ///
+    /// outputs
+    /// : ([samples] * [fields * dimension])
+    pub fn forward_propagation(
+        &mut self,
+        indices: &ArrayView2<usize>,
+    ) -> NetworkResult<ArrayView2<F>> {
+        let num_categories = self.num_categories();
+        if let Some(&category) = indices.iter().find(|&&index| index >= num_categories) {
+            return Err(NetworkError::CategoryOutOfRange {
+                category,
+                num_categories,
+            });
+        }
+        let dimension = self.dimension();
+        let weights = &self.weights;
+        self.outputs = Array2::from_shape_fn(
+            (indices.rows(), indices.cols() * dimension),
+            |(sample, j)| weights[[indices[[sample, j / dimension]], j % dimension]],
+        );
+        self.indices = indices.to_owned();
+        let mut seen_categories = self.indices.iter().cloned().collect::<Vec<_>>();
+        seen_categories.sort();
+        seen_categories.dedup();
+        self.seen_categories = seen_categories;
+        Ok(self.outputs.view())
+    }
+
+    /// Compute and store the gradient of the cost function with respect to
+    /// the embeddings, given its partial derivative with respect to the
+    /// outputs of the last forward propagation (as returned by
+    /// ```NeuralNetwork::backward_propagation```).
+    ///
+    /// outputs_error
+    /// : ([samples] * [fields * dimension])
+    ///
+    /// cost_d_weights
+    /// : ([categories] * [dimension]), the row of each category being the
+    /// sum of the errors of its embedding over the samples and fields
+    pub fn backward_propagation(&mut self, outputs_error: &ArrayView2<F>) -> NetworkResult<()> {
+        let dimension = self.dimension();
+        let expected = (self.indices.rows(), self.indices.cols() * dimension);
+        if outputs_error.dim() != expected {
+            return Err(NetworkError::DimensionMismatch {
+                expected: expected.0 * expected.1,
+                got: outputs_error.len(),
+            });
+        }
+        self.cost_d_weights.fill(F::zero());
+        for ((sample, field), &category) in self.indices.indexed_iter() {
+            for k in 0..dimension {
+                let error = outputs_error[[sample, field * dimension + k]];
+                self.cost_d_weights[[category, k]] = self.cost_d_weights[[category, k]] + error;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the embeddings of the categories seen by the last forward
+    /// propagation with one step of gradient descent.
+    ///
+    /// weights[category] -= learning_rate * cost_d_weights[category]
+    pub fn apply_gradients(&mut self, learning_rate: F) {
+        for &category in &self.seen_categories {
+            let gradient = self.cost_d_weights.row(category);
+            self.weights
+                .row_mut(category)
+                .scaled_add(-learning_rate, &gradient);
+        }
+    }
+
+    /// Get the partial derivative of the cost with respect to the
+    /// embeddings, as computed by the last backward propagation.
+    pub fn cost_d_weights(&self) -> ArrayView2<F> {
+        self.cost_d_weights.view()
+    }
+}
+
+impl EmbeddingLayer<Float> {
+    /// Create a new embedding layer with weights drawn from `init`.
+    pub fn with_weight_init<R: Rng>(
+        init: WeightInit,
+        num_categories: usize,
+        dimension: usize,
+        rng: &mut R,
+    ) -> Self {
+        EmbeddingLayer::new(init.weights(num_categories, dimension, rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use rand::{SeedableRng, StdRng};
+
+    use activation::Identity;
+    use builder::NeuralNetworkBuilder;
+    use super::*;
+
+    #[test]
+    fn forward_propagation() {
+        let mut embedding = EmbeddingLayer::new(arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]));
+        let indices = arr2(&[[2, 0], [1, 1]]);
+        let outputs = embedding.forward_propagation(&indices.view()).unwrap();
+        assert_eq!(outputs, arr2(&[[5.0, 6.0, 1.0, 2.0], [3.0, 4.0, 3.0, 4.0]]));
+
+        let out_of_range = arr2(&[[0], [3]]);
+        assert_eq!(
+            embedding.forward_propagation(&out_of_range.view()).err(),
+            Some(NetworkError::CategoryOutOfRange {
+                category: 3,
+                num_categories: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn unseen_categories_are_not_updated() {
+        let mut embedding = EmbeddingLayer::new(arr2(&[[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]));
+        let indices = arr2(&[[2], [0], [2]]);
+        embedding.forward_propagation(&indices.view()).unwrap();
+        let outputs_error = arr2(&[[0.5, -1.0], [1.0, 1.0], [0.25, 0.5]]);
+        embedding
+            .backward_propagation(&outputs_error.view())
+            .unwrap();
+        assert_eq!(
+            embedding.cost_d_weights(),
+            arr2(&[[1.0, 1.0], [0.0, 0.0], [0.75, -0.5]])
+        );
+
+        embedding.apply_gradients(1.0);
+        assert_eq!(embedding.weights(), arr2(&[[0.0, 1.0], [3.0, 4.0], [4.25, 6.5]]));
+        assert!(
+            embedding
+                .backward_propagation(&arr2(&[[0.5, -1.0]]).view())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn embedding_before_network() {
+        // the target of each category is its index, the same for both fields
+        let mut rng = StdRng::from_seed(&[78usize][..]);
+        let mut embedding =
+            EmbeddingLayer::with_weight_init(WeightInit::XavierUniform, 4, 3, &mut rng);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(6)
+            .init(WeightInit::XavierUniform)
+            .output(4, 1, Identity, &mut rng);
+        let indices = arr2(&[[0, 0], [1, 1], [2, 2]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [2.0]]);
+        let unseen_embedding = embedding.weights().row(3).to_owned();
+
+        let mut loss = |embedding: &mut EmbeddingLayer<Float>| {
+            let inputs = embedding
+                .forward_propagation(&indices.view())
+                .unwrap()
+                .to_owned();
+            let loss = neural_network
+                .loss(inputs.view(), expected_outputs.view())
+                .unwrap();
+            let inputs_error = neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            embedding
+                .backward_propagation(&inputs_error.view())
+                .unwrap();
+            neural_network.update(0.03);
+            embedding.apply_gradients(0.03);
+            loss
+        };
+        let initial_loss = loss(&mut embedding);
+        let mut final_loss = initial_loss;
+        for _ in 0..100 {
+            final_loss = loss(&mut embedding);
+        }
+        assert!(final_loss < 0.1 * initial_loss, "{} -> {}", initial_loss, final_loss);
+        assert_eq!(embedding.weights().row(3), unseen_embedding);
+    }
+}
@@ -0,0 +1,137 @@
+//! Ensembles of Neural Networks, combining the predictions of their members.
+
+use ndarray::{Array2, ArrayView2};
+
+use super::{Float, NetworkError, NetworkResult};
+use metrics::argmax_rows;
+use network::NeuralNetwork;
+use utils::check_shape;
+
+/// An ensemble of Neural Networks sharing the same inputs and outputs, for
+/// instance trained from different seeds, whose predictions are combined
+/// into more robust ones.
+#[derive(Clone)]
+pub struct Ensemble {
+    members: Vec<NeuralNetwork>,
+}
+
+impl Ensemble {
+    /// Create an ensemble from its `members`.
+    ///
+    /// Fails if there is no member, or if the members do not all have the
+    /// same number of outputs.
+    pub fn new(members: Vec<NeuralNetwork>) -> NetworkResult<Self> {
+        let output_dim = match members.first() {
+            Some(member) => member.output_dim(),
+            None => return Err(NetworkError::EmptyEnsemble),
+        };
+        if let Some(member) = members
+            .iter()
+            .find(|member| member.output_dim() != output_dim)
+        {
+            return Err(NetworkError::DimensionMismatch {
+                expected: output_dim,
+                got: member.output_dim(),
+            });
+        }
+        Ok(Ensemble { members })
+    }
+
+    /// Get the members of the ensemble.
+    pub fn members(&self) -> &[NeuralNetwork] {
+        &self.members
+    }
+
+    /// Get the number of outputs shared by the members.
+    pub fn output_dim(&self) -> usize {
+        self.members[0].output_dim()
+    }
+
+    /// Average the predictions of the members for `inputs`.
+    ///
+    /// outputs
+    /// : ([samples] * [outputs])
+    /// = sum(member.predict(inputs)) / [members]
+    pub fn predict(&mut self, inputs: ArrayView2<Float>) -> NetworkResult<Array2<Float>> {
+        let mut sum = Array2::zeros((inputs.rows(), self.output_dim()));
+        for member in &mut self.members {
+            let outputs = member.predict(inputs)?;
+            check_shape(sum.dim(), outputs.dim())?;
+            sum += &outputs;
+        }
+        Ok(sum / self.members.len() as Float)
+    }
+
+    /// Get the class of each sample of `inputs` predicted by the majority of
+    /// the members, each member voting for the class of its highest output.
+    ///
+    /// Ties are broken in favor of the lowest class.
+    pub fn vote(&mut self, inputs: ArrayView2<Float>) -> NetworkResult<Vec<usize>> {
+        let mut votes = Array2::<Float>::zeros((inputs.rows(), self.output_dim()));
+        for member in &mut self.members {
+            let outputs = member.predict(inputs)?;
+            check_shape(votes.dim(), outputs.dim())?;
+            for (sample, class) in argmax_rows(&outputs.view()).into_iter().enumerate() {
+                votes[[sample, class]] += 1.0;
+            }
+        }
+        Ok(argmax_rows(&votes.view()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use activation::Identity;
+    use layer::Layer;
+    use super::*;
+
+    /// A network whose outputs are its inputs times `factor`.
+    fn scaling_network(factor: Float) -> NeuralNetwork {
+        NeuralNetwork::new(vec![
+            Layer::new(
+                Identity,
+                arr2(&[[factor, 0.0], [0.0, factor]]),
+                arr2(&[[1.0, 0.0], [0.0, 1.0]]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn predict_averages_members() {
+        let mut ensemble = Ensemble::new(vec![scaling_network(1.0), scaling_network(2.0)]).unwrap();
+        let inputs = arr2(&[[1.0, 0.5], [-2.0, 4.0]]);
+        assert_eq!(
+            ensemble.predict(inputs.view()).unwrap(),
+            arr2(&[[1.5, 0.75], [-3.0, 6.0]])
+        );
+        assert!(ensemble.predict(arr2(&[[1.0]]).view()).is_err());
+
+        let mismatch = NeuralNetwork::new(vec![Layer::with_init_fn(Identity, 2, 2, 3, |_| 1.0)]);
+        assert_eq!(
+            Ensemble::new(vec![scaling_network(1.0), mismatch]).err(),
+            Some(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 3,
+            })
+        );
+        assert_eq!(Ensemble::new(Vec::new()).err(), Some(NetworkError::EmptyEnsemble));
+    }
+
+    #[test]
+    fn majority_vote() {
+        let mut ensemble = Ensemble::new(vec![
+            scaling_network(1.0),
+            scaling_network(-1.0),
+            scaling_network(2.0),
+        ]).unwrap();
+        let inputs = arr2(&[[1.0, 0.5], [-2.0, 4.0]]);
+        // 2 votes for the largest input against 1
+        assert_eq!(ensemble.vote(inputs.view()).unwrap(), vec![0, 1]);
+        // tied votes
+        let members = vec![scaling_network(1.0), scaling_network(-1.0)];
+        let mut ensemble = Ensemble::new(members).unwrap();
+        assert_eq!(ensemble.vote(inputs.view()).unwrap(), vec![0, 0]);
+    }
+}
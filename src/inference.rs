@@ -0,0 +1,160 @@
+//! Lightweight, inference-only Neural Networks.
+
+use ndarray::{Array1, Array2, ArrayView2, Ix2, NdFloat};
+
+use super::{Float, NetworkError, NetworkResult};
+use activation::Activation;
+
+/// The weights and activation of a trained ```layer::Layer```, without any of
+/// the results it caches for the backward propagation.
+///
+/// A batch normalization of the layer is folded into its inputs weights and
+/// biases, using its running statistics.
+#[derive(Clone)]
+pub(crate) struct InferenceLayer<F: NdFloat> {
+    activation: Box<Activation<F, Ix2>>,
+    inputs_weights: Array2<F>,
+    inputs_bias: Array1<F>,
+    outputs_weights: Array2<F>,
+    outputs_bias: Array1<F>,
+}
+
+impl<F: NdFloat> InferenceLayer<F> {
+    pub(crate) fn new(
+        activation: Box<Activation<F, Ix2>>,
+        inputs_weights: Array2<F>,
+        inputs_bias: Array1<F>,
+        outputs_weights: Array2<F>,
+        outputs_bias: Array1<F>,
+    ) -> Self {
+        InferenceLayer {
+            activation,
+            inputs_weights,
+            inputs_bias,
+            outputs_weights,
+            outputs_bias,
+        }
+    }
+
+    fn dim_inputs(&self) -> usize {
+        self.inputs_weights.rows()
+    }
+
+    fn dim_outputs(&self) -> usize {
+        self.outputs_weights.cols()
+    }
+
+    /// Same as ```Layer::forward_propagation``` in inference mode.
+    fn forward(&self, inputs: &ArrayView2<F>) -> NetworkResult<Array2<F>> {
+        if inputs.cols() != self.dim_inputs() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.dim_inputs(),
+                got: inputs.cols(),
+            });
+        }
+        let activated = self.activation
+            .compute(&(inputs.dot(&self.inputs_weights) + &self.inputs_bias));
+        Ok(self.activation
+            .compute(&(activated.dot(&self.outputs_weights) + &self.outputs_bias)))
+    }
+}
+
+/// An inference-only copy of a trained ```NeuralNetwork```, holding nothing
+/// but the weights and activations of its layers, as returned by
+/// ```NeuralNetwork::to_inference```.
+///
+/// Its outputs are the ones of the Neural Network in inference mode, which
+/// makes it a lighter and immutable alternative for serving predictions.
+#[derive(Clone)]
+pub struct InferenceNetwork {
+    layers: Vec<InferenceLayer<Float>>,
+}
+
+impl InferenceNetwork {
+    pub(crate) fn new(layers: Vec<InferenceLayer<Float>>) -> Self {
+        InferenceNetwork { layers }
+    }
+
+    /// Get the number of inputs of the network.
+    pub fn input_dim(&self) -> usize {
+        self.layers.first().map_or(0, InferenceLayer::dim_inputs)
+    }
+
+    /// Get the number of outputs of the network.
+    pub fn output_dim(&self) -> usize {
+        self.layers.last().map_or(0, InferenceLayer::dim_outputs)
+    }
+
+    /// Perform a forward propagation of the ([samples] * [inputs]) `inputs`
+    /// and return the ([samples] * [outputs]) outputs.
+    pub fn forward(&self, inputs: ArrayView2<Float>) -> NetworkResult<Array2<Float>> {
+        let (first_layer, layers) = match self.layers.split_first() {
+            Some(split) => split,
+            None => return Err(NetworkError::NoLayers),
+        };
+        let mut outputs = first_layer.forward(&inputs)?;
+        for layer in layers {
+            outputs = layer.forward(&outputs.view())?;
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use rand::{SeedableRng, StdRng};
+
+    use activation::{Rectifier, Sigmoid};
+    use builder::NeuralNetworkBuilder;
+    use init::WeightInit;
+    use layer::Layer;
+    use super::*;
+
+    #[test]
+    fn same_outputs_as_network() {
+        let mut rng = StdRng::from_seed(&[93usize][..]);
+        let init = WeightInit::XavierUniform;
+        let batch_norm_layer = Layer::with_weight_init(Sigmoid, init, 3, 3, 1, &mut rng)
+            .with_batch_norm(0.5)
+            .with_dropout(0.5);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .init(init)
+            .layer(3, Rectifier::default(), &mut rng)
+            .add_layer(batch_norm_layer)
+            .and_then(NeuralNetworkBuilder::build)
+            .unwrap();
+        neural_network.set_training(true);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, -2.0], [0.5, 3.0], [-1.5, 0.25]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+        for _ in 0..10 {
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            neural_network.update(0.1);
+        }
+
+        let inference_network = neural_network.to_inference();
+        assert_eq!(inference_network.input_dim(), 2);
+        assert_eq!(inference_network.output_dim(), 1);
+        neural_network.set_training(false);
+        let outputs = inference_network.forward(inputs.view()).unwrap();
+        let network_outputs = neural_network.run_forward(inputs.view()).unwrap();
+        for (output, network_output) in outputs.iter().zip(network_outputs.iter()) {
+            assert_relative_eq!(output, network_output, epsilon = 1e-6);
+        }
+        assert_eq!(
+            inference_network.forward(arr2(&[[1.0]]).view()).err(),
+            Some(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 1,
+            })
+        );
+        assert_eq!(
+            InferenceNetwork::new(Vec::new())
+                .forward(inputs.view())
+                .err(),
+            Some(NetworkError::NoLayers)
+        );
+    }
+}
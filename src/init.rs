@@ -0,0 +1,126 @@
+//! Weight initialization schemes.
+
+use rand::Rng;
+use rand::distributions::{IndependentSample, Normal, Range};
+use ndarray::Array2;
+
+use super::Float;
+use utils::NdArrayRandomizer;
+
+/// Defines how the weights of a ```Layer``` are randomly initialized.
+///
+/// For a ([fan_in] * [fan_out]) weights matrix:
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightInit {
+    /// Uniform distribution over [low, high[.
+    Uniform(Float, Float),
+    /// Normal distribution of variance 2 / (fan_in + fan_out), suited to
+    /// sigmoid and tanh activations.
+    XavierNormal,
+    /// Uniform distribution over [-limit, limit[ with
+    /// limit = sqrt(6 / (fan_in + fan_out)).
+    XavierUniform,
+    /// Normal distribution of variance 2 / fan_in, suited to rectifier
+    /// activations.
+    HeNormal,
+    /// Uniform distribution over [-limit, limit[ with
+    /// limit = sqrt(6 / fan_in).
+    HeUniform,
+}
+
+impl Default for WeightInit {
+    fn default() -> Self {
+        WeightInit::Uniform(0.0, 1.0)
+    }
+}
+
+impl WeightInit {
+    /// Get the weight initialization named `name` ("Uniform" standing for
+    /// the default uniform distribution over [0, 1[), or ```None``` for an
+    /// unknown name.
+    pub fn from_name(name: &str) -> Option<WeightInit> {
+        match name {
+            "Uniform" => Some(WeightInit::default()),
+            "XavierNormal" => Some(WeightInit::XavierNormal),
+            "XavierUniform" => Some(WeightInit::XavierUniform),
+            "HeNormal" => Some(WeightInit::HeNormal),
+            "HeUniform" => Some(WeightInit::HeUniform),
+            _ => None,
+        }
+    }
+
+    /// Generate a random ([fan_in] * [fan_out]) weights matrix.
+    pub fn weights<R: Rng>(&self, fan_in: usize, fan_out: usize, rng: &mut R) -> Array2<Float> {
+        use self::WeightInit::*;
+        let shape = (fan_in, fan_out);
+        let (fan_in, fan_out) = (fan_in as Float, fan_out as Float);
+        match *self {
+            Uniform(low, high) => Array2::random(shape, Range::new(low, high), rng),
+            XavierNormal => {
+                let std_dev = (2.0 / (fan_in + fan_out)).sqrt();
+                normal_weights(shape, std_dev, rng)
+            }
+            XavierUniform => {
+                let limit = (6.0 / (fan_in + fan_out)).sqrt();
+                Array2::random(shape, Range::new(-limit, limit), rng)
+            }
+            HeNormal => {
+                let std_dev = (2.0 / fan_in).sqrt();
+                normal_weights(shape, std_dev, rng)
+            }
+            HeUniform => {
+                let limit = (6.0 / fan_in).sqrt();
+                Array2::random(shape, Range::new(-limit, limit), rng)
+            }
+        }
+    }
+}
+
+/// Draw weights from a centered normal distribution, which rand only
+/// provides for f64.
+fn normal_weights<R: Rng>(shape: (usize, usize), std_dev: Float, rng: &mut R) -> Array2<Float> {
+    let normal = Normal::new(0.0, std_dev.into());
+    Array2::from_shape_fn(shape, |_| normal.ind_sample(rng) as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, StdRng};
+    use super::*;
+
+    fn variance(weights: &Array2<Float>) -> Float {
+        let n = weights.len() as Float;
+        let mean = weights.scalar_sum() / n;
+        weights.map(|w| (w - mean).powi(2)).scalar_sum() / n
+    }
+
+    #[test]
+    fn he_variance() {
+        let mut rng = StdRng::from_seed(&[7usize][..]);
+        let (fan_in, fan_out) = (200, 500);
+        let expected = 2.0 / fan_in as Float;
+        for init in &[WeightInit::HeNormal, WeightInit::HeUniform] {
+            let weights = init.weights(fan_in, fan_out, &mut rng);
+            assert_eq!(weights.dim(), (fan_in, fan_out));
+            let variance = variance(&weights);
+            assert!(
+                (variance - expected).abs() < 0.02 * expected,
+                "{:?}: variance = {} instead of {}",
+                init,
+                variance,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn xavier_variance() {
+        let mut rng = StdRng::from_seed(&[7usize][..]);
+        let (fan_in, fan_out) = (200, 500);
+        let expected = 2.0 / (fan_in + fan_out) as Float;
+        for init in &[WeightInit::XavierNormal, WeightInit::XavierUniform] {
+            let variance = variance(&init.weights(fan_in, fan_out, &mut rng));
+            assert!((variance - expected).abs() < 0.02 * expected);
+        }
+    }
+}
@@ -1,10 +1,20 @@
-use rand::Rng;
-use rand::distributions::Range;
-use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Ix2, NdFloat, Zip};
+use std::fmt;
 
-use super::{Float, ResultString};
+use rand::{thread_rng, Rng, StdRng};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, Ix2, NdFloat, Zip};
+#[cfg(feature = "rayon")]
+use ndarray::stack;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{Float, NetworkError, NetworkResult};
 use activation::Activation;
-use utils::NdArrayRandomizer;
+use cost::{Cost, MeanSquaredError, WeightedMeanSquaredError};
+use inference::InferenceLayer;
+use init::WeightInit;
+use normalization::BatchNorm;
+use serialization::{invalid_model, LayerModel};
+use utils::check_shape;
 
 /// A layer of artificial Neurons within an Artificial Neural Network.
 ///
@@ -37,23 +47,116 @@ use utils::NdArrayRandomizer;
 /// ]
 ///
 ///
+#[derive(Clone)]
 pub struct Layer<F: NdFloat> {
     activation: Box<Activation<F, Ix2>>,
+    cost: Box<Cost<F>>,
     inputs_weights: Array2<F>,
+    inputs_bias: Array1<F>,
     outputs: Array2<F>,
     outputs_weights: Array2<F>,
+    outputs_bias: Array1<F>,
+    /// Are the biases learned during training?
+    bias: bool,
+    /// L1 regularization factor of the weights.
+    l1_lambda: F,
+    /// L2 regularization factor of the weights.
+    l2_lambda: F,
+    /// Probability of dropping each neuron during training.
+    dropout: F,
+    /// RNG of the dropout masks (```thread_rng``` if not seeded).
+    dropout_rng: Option<StdRng>,
+    /// Weight of each class (output) in the cost, if any.
+    class_weights: Option<Array1<F>>,
+    /// Is the layer being trained (as opposed to used for inference)?
+    training: bool,
+    /// Are the parameters of the layer left untouched by the updates?
+    frozen: bool,
+    /// Batch normalization of the weighted sums of the neurons, if any.
+    batch_norm: Option<BatchNorm<F>>,
+    /// Temperature dividing the weighted sums of the outputs before their
+    /// activation.
+    temperature: F,
+    /// Initialization scheme of the weights, if drawn from one.
+    weight_init: Option<WeightInit>,
     // cached results
+    layer_inputs: Array2<F>,
     layer_inputs_sum: Array2<F>,
     layer_inputs_sum_activated: Array2<F>,
+    dropout_mask: Option<Array2<F>>,
     layer_outputs_sum: Array2<F>,
     backprop_error_1: Array2<F>,
     backprop_error_2: Array2<F>,
     costs: Array1<F>,
     cost_d_inputs: Array2<F>,
     cost_d_outputs: Array2<F>,
+    cost_d_inputs_bias: Array1<F>,
+    cost_d_outputs_bias: Array1<F>,
+    cost_d_activation: Vec<F>,
+}
+
+/// A copy of the learnable parameters of a ```Layer```, used to restore
+/// it to an earlier state.
+#[derive(Clone, Debug)]
+pub(crate) struct LayerParameters<F: NdFloat> {
+    inputs_weights: Array2<F>,
+    inputs_bias: Array1<F>,
+    outputs_weights: Array2<F>,
+    outputs_bias: Array1<F>,
+    activation: Vec<F>,
+    batch_norm: Option<BatchNorm<F>>,
+}
+
+/// A copy of the gradients of a ```Layer```, used to accumulate them over
+/// several backward propagations.
+#[derive(Clone, Debug)]
+pub(crate) struct LayerGradients<F: NdFloat> {
+    inputs_weights: Array2<F>,
+    inputs_bias: Array1<F>,
+    outputs_weights: Array2<F>,
+    outputs_bias: Array1<F>,
+    activation: Vec<F>,
+    /// Gradients of the batch normalization's gamma and beta.
+    batch_norm: Option<(Array1<F>, Array1<F>)>,
+}
+
+impl<F: NdFloat> LayerGradients<F> {
+    /// Add `other`, copied from the same layer, to these gradients.
+    pub(crate) fn add(&mut self, other: &LayerGradients<F>) {
+        self.inputs_weights += &other.inputs_weights;
+        self.inputs_bias += &other.inputs_bias;
+        self.outputs_weights += &other.outputs_weights;
+        self.outputs_bias += &other.outputs_bias;
+        for (d, &other_d) in self.activation.iter_mut().zip(&other.activation) {
+            *d = *d + other_d;
+        }
+        if let (Some((ref mut d_gamma, ref mut d_beta)), Some((ref other_gamma, ref other_beta))) =
+            (self.batch_norm.as_mut(), other.batch_norm.as_ref())
+        {
+            *d_gamma += other_gamma;
+            *d_beta += other_beta;
+        }
+    }
+
+    /// Multiply every gradient by `factor`.
+    pub(crate) fn scale(&mut self, factor: F) {
+        self.inputs_weights.mapv_inplace(|d| d * factor);
+        self.inputs_bias.mapv_inplace(|d| d * factor);
+        self.outputs_weights.mapv_inplace(|d| d * factor);
+        self.outputs_bias.mapv_inplace(|d| d * factor);
+        for d in &mut self.activation {
+            *d = *d * factor;
+        }
+        if let Some((ref mut d_gamma, ref mut d_beta)) = self.batch_norm {
+            d_gamma.mapv_inplace(|d| d * factor);
+            d_beta.mapv_inplace(|d| d * factor);
+        }
+    }
 }
 
 impl<F: NdFloat> Layer<F> {
+    /// Create a new layer without biases: the weighted sums of the layer
+    /// always go through the origin.
     pub fn new<A: 'static>(
         activation: A,
         inputs_weights: Array2<F>,
@@ -62,22 +165,334 @@ impl<F: NdFloat> Layer<F> {
     where
         A: Activation<F, Ix2>,
     {
+        let inputs_bias = Array1::zeros(inputs_weights.cols());
+        let outputs_bias = Array1::zeros(outputs_weights.cols());
+        let mut layer = Layer::new_with_bias(
+            activation,
+            inputs_weights,
+            outputs_weights,
+            inputs_bias,
+            outputs_bias,
+        );
+        layer.bias = false;
+        layer
+    }
+
+    /// Create a new layer with learnable biases added to its weighted sums.
+    ///
+    /// - `inputs_bias`: (1 * [neurons])
+    ///
+    /// - `outputs_bias`: (1 * [outputs])
+    pub fn new_with_bias<A: 'static>(
+        activation: A,
+        inputs_weights: Array2<F>,
+        outputs_weights: Array2<F>,
+        inputs_bias: Array1<F>,
+        outputs_bias: Array1<F>,
+    ) -> Self
+    where
+        A: Activation<F, Ix2>,
+    {
+        Layer::with_boxed_activation(
+            Box::new(activation),
+            inputs_weights,
+            outputs_weights,
+            inputs_bias,
+            outputs_bias,
+        )
+    }
+
+    fn with_boxed_activation(
+        activation: Box<Activation<F, Ix2>>,
+        inputs_weights: Array2<F>,
+        outputs_weights: Array2<F>,
+        inputs_bias: Array1<F>,
+        outputs_bias: Array1<F>,
+    ) -> Self {
         assert_eq!(inputs_weights.cols(), outputs_weights.rows());
+        assert_eq!(inputs_weights.cols(), inputs_bias.len());
+        assert_eq!(outputs_weights.cols(), outputs_bias.len());
         let dim = inputs_weights.dim();
         Layer {
-            activation: Box::new(activation),
+            activation,
+            cost: Box::new(MeanSquaredError),
             inputs_weights,
+            inputs_bias,
             outputs: Array2::zeros(dim),
             outputs_weights: outputs_weights,
+            outputs_bias,
+            bias: true,
+            l1_lambda: F::zero(),
+            l2_lambda: F::zero(),
+            dropout: F::zero(),
+            dropout_rng: None,
+            class_weights: None,
+            training: false,
+            frozen: false,
+            batch_norm: None,
+            temperature: F::one(),
+            weight_init: None,
+            layer_inputs: Array2::zeros((0, 0)),
             layer_inputs_sum: Array2::zeros((0, 0)),
             layer_inputs_sum_activated: Array2::zeros((0, 0)),
+            dropout_mask: None,
             layer_outputs_sum: Array2::zeros((0, 0)),
             backprop_error_1: Array2::zeros((0, 0)),
             backprop_error_2: Array2::zeros((0, 0)),
             costs: Array1::zeros(0),
             cost_d_inputs: Array2::zeros((0, 0)),
             cost_d_outputs: Array2::zeros((0, 0)),
+            cost_d_inputs_bias: Array1::zeros(0),
+            cost_d_outputs_bias: Array1::zeros(0),
+            cost_d_activation: Vec::new(),
+        }
+    }
+
+    /// Create a new layer whose weights are given by `init_fn`, called with
+    /// the (row, column) index of each weight: first for the
+    /// ([inputs] * [neurons]) inputs weights, then for the
+    /// ([neurons] * [outputs]) outputs weights.
+    ///
+    /// For instance, `|(i, j)| if i == j { 1.0 } else { 0.0 }` gives
+    /// identity-like weights.
+    pub fn with_init_fn<A: 'static, I>(
+        activation: A,
+        dim_inputs: usize,
+        dim_neurons: usize,
+        dim_outputs: usize,
+        mut init_fn: I,
+    ) -> Self
+    where
+        A: Activation<F, Ix2>,
+        I: FnMut((usize, usize)) -> F,
+    {
+        let inputs_weights = Array2::from_shape_fn((dim_inputs, dim_neurons), &mut init_fn);
+        let outputs_weights = Array2::from_shape_fn((dim_neurons, dim_outputs), &mut init_fn);
+        Layer::new(activation, inputs_weights, outputs_weights)
+    }
+
+    /// Replace the cost function used to evaluate the layer's outputs
+    /// (```MeanSquaredError``` by default).
+    pub fn with_cost<C: 'static>(mut self, cost: C) -> Self
+    where
+        C: Cost<F>,
+    {
+        self.cost = Box::new(cost);
+        self
+    }
+
+    /// Evaluate the outputs with a ```WeightedMeanSquaredError``` cost, the
+    /// squared error (and thus the gradient) of each output being scaled by
+    /// its weight in `output_weights`.
+    ///
+    /// Fails if there is not one weight per output of the layer, or if a
+    /// weight is negative or not finite.
+    pub fn with_output_weights(mut self, output_weights: Array1<F>) -> NetworkResult<Self> {
+        if output_weights.len() != self.dim_outputs() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.dim_outputs(),
+                got: output_weights.len(),
+            });
+        }
+        let cost = WeightedMeanSquaredError::new(output_weights)
+            .ok_or(NetworkError::InvalidOutputWeights)?;
+        self.cost = Box::new(cost);
+        Ok(self)
+    }
+
+    /// Weigh the cost of each sample by the weight of its class, the
+    /// expected outputs being one-hot vectors (or, more generally, by the
+    /// weighted sum of its expected outputs), so that the minority classes
+    /// of an imbalanced dataset are not neglected.
+    ///
+    /// Fails if there is not one weight per output of the layer.
+    pub fn with_class_weights(mut self, class_weights: Array1<F>) -> NetworkResult<Self> {
+        if class_weights.len() != self.dim_outputs() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.dim_outputs(),
+                got: class_weights.len(),
+            });
         }
+        self.class_weights = Some(class_weights);
+        Ok(self)
+    }
+
+    /// Get the weight of each class in the cost, if any.
+    pub fn class_weights(&self) -> Option<ArrayView1<F>> {
+        self.class_weights.as_ref().map(|weights| weights.view())
+    }
+
+    /// Compute the weight of each sample from its expected outputs, if the
+    /// classes are weighted.
+    ///
+    /// sample_weights
+    /// : ([samples])
+    /// = expected_outputs . class_weights
+    fn sample_weights(&self, expected_outputs: &ArrayView2<F>) -> Option<Array1<F>> {
+        self.class_weights
+            .as_ref()
+            .map(|class_weights| expected_outputs.dot(class_weights))
+    }
+
+    /// Regularize the weights with an L1 penalty, which drives the weights
+    /// of irrelevant inputs to zero (sparse solutions):
+    ///
+    /// cost += l1_lambda * sum(|weights|)
+    ///
+    /// The biases are not regularized.
+    pub fn with_l1(mut self, l1_lambda: F) -> Self {
+        self.l1_lambda = l1_lambda;
+        self
+    }
+
+    /// Regularize the weights with an L2 penalty (weight decay), so that
+    /// large weights are discouraged:
+    ///
+    /// cost += 1/2 * l2_lambda * sum(weights ^ 2)
+    ///
+    /// The biases are not regularized.
+    pub fn with_l2(mut self, l2_lambda: F) -> Self {
+        self.l2_lambda = l2_lambda;
+        self
+    }
+
+    /// Randomly drop each neuron of the layer with the probability `dropout`
+    /// (within [0, 1[) during training, the remaining neurons being scaled
+    /// up by 1 / (1 - dropout) (inverted dropout).
+    ///
+    /// Dropout only applies to a layer in training mode.
+    pub fn with_dropout(mut self, dropout: F) -> Self {
+        self.dropout = dropout;
+        self
+    }
+
+    /// Normalize the weighted sums of the neurons, over the samples of each
+    /// batch, before their activation (see ```BatchNorm```), with a running
+    /// statistics momentum of `momentum`.
+    ///
+    /// The statistics of the batch are only used in training mode, the
+    /// running ones otherwise.
+    pub fn with_batch_norm(mut self, momentum: F) -> Self {
+        self.batch_norm = Some(BatchNorm::new(self.dim_neurons()).momentum(momentum));
+        self
+    }
+
+    /// Get the batch normalization of the neurons, if any.
+    pub fn batch_norm(&self) -> Option<&BatchNorm<F>> {
+        self.batch_norm.as_ref()
+    }
+
+    /// Draw the dropout masks from `rng` instead of ```thread_rng```, so that
+    /// they are reproducible.
+    pub fn set_dropout_rng(&mut self, rng: StdRng) {
+        self.dropout_rng = Some(rng);
+    }
+
+    /// Switch the layer between training and inference (the default) modes.
+    pub fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    /// Is the layer in training mode?
+    pub fn training(&self) -> bool {
+        self.training
+    }
+
+    /// Freeze (or unfreeze) the parameters of the layer: the updates of a
+    /// frozen layer are no-ops, whatever the optimizer, while the error
+    /// still backpropagates through it (to fine-tune the last layers of a
+    /// pre-trained network for instance).
+    ///
+    /// Since the ```step``` of an ```optimizer::Optimizer``` is not called for
+    /// the weights of a frozen layer, an optimizer keeping a state for each
+    /// weights matrix should be reset when freezing or unfreezing a layer of
+    /// a network being trained.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// Is the layer frozen?
+    pub fn frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Divide the weighted sums of the outputs (the logits) by `temperature`
+    /// before their activation, 1 (the default) leaving them unchanged.
+    ///
+    /// outputs = activation(outputs_sum / temperature)
+    ///
+    /// For a ```activation::Softmax``` output layer, a temperature above 1
+    /// softens the predicted distributions (below 1, it sharpens them)
+    /// without changing the most likely class: tuning it after training, on
+    /// a validation dataset, calibrates the confidence of a classifier.
+    ///
+    /// Panics if `temperature` is not strictly positive.
+    pub fn set_temperature(&mut self, temperature: F) {
+        assert!(
+            temperature > F::zero(),
+            "Layer.set_temperature : the temperature must be strictly positive"
+        );
+        self.temperature = temperature;
+    }
+
+    /// Get the temperature dividing the weighted sums of the outputs.
+    pub fn temperature(&self) -> F {
+        self.temperature
+    }
+
+    /// Get the number of inputs of the layer.
+    pub fn dim_inputs(&self) -> usize {
+        self.inputs_weights.rows()
+    }
+
+    /// Get the number of neurons of the layer.
+    pub fn dim_neurons(&self) -> usize {
+        self.inputs_weights.cols()
+    }
+
+    /// Get the number of outputs of the layer.
+    pub fn dim_outputs(&self) -> usize {
+        self.outputs_weights.cols()
+    }
+
+    /// Get the name of the layer's activation function (see
+    /// ```Activation::name```).
+    pub fn activation_name(&self) -> &'static str {
+        self.activation.name()
+    }
+
+    /// Get the number of learnable parameters of the layer: the weights,
+    /// the biases (if any), the activation function's parameters and the
+    /// batch normalization's gamma and beta (if any).
+    pub fn num_parameters(&self) -> usize {
+        let batch_norm_count = self.batch_norm
+            .as_ref()
+            .map_or(0, |batch_norm| 2 * batch_norm.features());
+        self.flat_parameters_count() + self.activation.parameters().len() + batch_norm_count
+    }
+
+    /// Get the ([inputs] * [neurons]) inputs weights.
+    pub fn inputs_weights(&self) -> ArrayView2<F> {
+        self.inputs_weights.view()
+    }
+
+    /// Get the ([neurons] * [outputs]) outputs weights.
+    pub fn outputs_weights(&self) -> ArrayView2<F> {
+        self.outputs_weights.view()
+    }
+
+    /// Overwrite the inputs weights, which must keep the same shape.
+    pub fn set_inputs_weights(&mut self, inputs_weights: Array2<F>) -> NetworkResult<()> {
+        check_shape(self.inputs_weights.dim(), inputs_weights.dim())?;
+        self.inputs_weights = inputs_weights;
+        Ok(())
+    }
+
+    /// Overwrite the outputs weights, which must keep the same shape.
+    pub fn set_outputs_weights(&mut self, outputs_weights: Array2<F>) -> NetworkResult<()> {
+        check_shape(self.outputs_weights.dim(), outputs_weights.dim())?;
+        self.outputs_weights = outputs_weights;
+        Ok(())
     }
 
     /// Compute and store the outputs of the layer using forward propagation.
@@ -96,14 +511,14 @@ impl<F: NdFloat> Layer<F> {
     /// - layer_inputs_sum
     ///   : ([samples] * [inputs]) * ([inputs] * [neurons])
     ///   : ([samples] * [neurons])
-    ///   = inputs * inputs_weights
+    ///   = inputs * inputs_weights + inputs_bias
     ///
     /// - layer_inputs_sum_activated = activation(layer_sum)
     ///
     /// - layer_outputs_sum
     ///   : ([samples * [neurons]) * ([neurons] * [outputs])
     ///   : ([samples] * [outputs])
-    ///   = layers_inputs_sum_activated * outputs_weights
+    ///   = layers_inputs_sum_activated * outputs_weights + outputs_bias
     ///
     ///
     /// ## Output
@@ -113,24 +528,74 @@ impl<F: NdFloat> Layer<F> {
     /// : ([samples] * [output])
     /// = activation(outputs_sum)
     ///
-    pub fn forward_propagation(&mut self, inputs: &ArrayView2<F>) -> ResultString<ArrayView2<F>> {
+    pub fn forward_propagation(&mut self, inputs: &ArrayView2<F>) -> NetworkResult<ArrayView2<F>> {
         if inputs.cols() != self.inputs_weights.rows() {
-            return Err(format!(
-                "Layer.forward : inputs size mismatch (inputs cols = {} != {} = weights rows)",
-                inputs.cols(),
-                self.inputs_weights.rows(),
-            ));
-        }
-        println!("rezrezrez\n{}\n{}\n\n\n", inputs, self.inputs_weights);
-        self.layer_inputs_sum = inputs.dot(&self.inputs_weights);
-        self.layer_inputs_sum_activated = self.activation.compute(&self.layer_inputs_sum);
-        self.layer_outputs_sum = self.layer_inputs_sum_activated.dot(&self.outputs_weights);
-        self.outputs = self.activation.compute(&self.layer_outputs_sum);
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.inputs_weights.rows(),
+                got: inputs.cols(),
+            });
+        }
+        trace!(
+            "Layer.forward_propagation : inputs\n{}\ninputs weights\n{}",
+            inputs,
+            self.inputs_weights
+        );
+        self.layer_inputs = inputs.to_owned();
+        let (inputs_sum, inputs_sum_activated) = match self.batch_norm {
+            Some(ref mut batch_norm) => {
+                // the cached sums are the normalized ones, as activated
+                let sum = inputs.dot(&self.inputs_weights) + &self.inputs_bias;
+                let normalized = batch_norm.forward_propagation(&sum.view(), self.training)?;
+                let activated = self.activation.compute(&normalized);
+                (normalized, activated)
+            }
+            None => activated_sum(
+                &*self.activation,
+                inputs,
+                &self.inputs_weights,
+                &self.inputs_bias,
+            ),
+        };
+        self.layer_inputs_sum = inputs_sum;
+        self.layer_inputs_sum_activated = inputs_sum_activated;
+        self.dropout_mask = if self.training && self.dropout > F::zero() {
+            let mask = self.sample_dropout_mask();
+            self.layer_inputs_sum_activated *= &mask;
+            Some(mask)
+        } else {
+            None
+        };
+        let (outputs_sum, outputs) = if self.temperature == F::one() {
+            activated_sum(
+                &*self.activation,
+                &self.layer_inputs_sum_activated.view(),
+                &self.outputs_weights,
+                &self.outputs_bias,
+            )
+        } else {
+            // the cached sums are the ones divided by the temperature
+            let sum = (self.layer_inputs_sum_activated.dot(&self.outputs_weights)
+                + &self.outputs_bias) / self.temperature;
+            let activated = self.activation.compute(&sum);
+            (sum, activated)
+        };
+        self.layer_outputs_sum = outputs_sum;
+        self.outputs = outputs;
         Ok(self.outputs.view())
     }
 
-    /// Compute and store the gradient of the Mean Squared Error cost function
-    /// for the current ```Layer```.
+    /// Draw the dropout mask of the neurons for the cached inputs, from the
+    /// seeded RNG if any.
+    fn sample_dropout_mask(&mut self) -> Array2<F> {
+        let (dim, dropout) = (self.layer_inputs_sum.dim(), self.dropout);
+        match self.dropout_rng {
+            Some(ref mut rng) => dropout_mask(dim, dropout, rng),
+            None => dropout_mask(dim, dropout, &mut thread_rng()),
+        }
+    }
+
+    /// Compute and store the gradient of the cost function (Mean Squared Error
+    /// by default) for the current ```Layer```, considered on its own.
     ///
     /// ## Input
     ///
@@ -138,13 +603,70 @@ impl<F: NdFloat> Layer<F> {
     ///
     /// - `expected_outputs`: ([samples] * [outputs])
     ///
+    /// ## Output
+    ///
+    /// Returns a view to the gradient of the cost function with respect to
+    /// the inputs weights and to the outputs weights, averaged over the
+    /// samples consistently with ```cost_mse```.
+    /// See ```backward_propagation``` for the details of the computation.
+    ///
+    pub fn cost_gradient_mse(
+        &mut self,
+        inputs: &ArrayView2<F>,
+        expected_outputs: &ArrayView2<F>,
+    ) -> (ArrayView2<F>, ArrayView2<F>) {
+        let samples = F::from(expected_outputs.rows().max(1)).unwrap();
+        let outputs_error = self.outputs_error(expected_outputs) / samples;
+        self.backpropagate_error(&outputs_error.view());
+        self.cost_d_inputs = inputs.t().dot(&self.backprop_error_2);
+        self.regularize_gradients();
+        (self.cost_d_inputs.view(), self.cost_d_outputs.view())
+    }
+
+    /// Compute the partial derivative of the cost function with respect to
+    /// the outputs of the last forward propagation.
+    ///
+    /// outputs_error
+    /// : ([samples] * [outputs])
+    /// = cost_derivative(self.outputs, expected_outputs)
+    ///
+    /// With class weights, the row of each sample is scaled by its weight.
+    pub fn outputs_error(&self, expected_outputs: &ArrayView2<F>) -> Array2<F> {
+        let mut outputs_error = self.cost
+            .cost_derivative(&self.outputs.view(), expected_outputs);
+        if let Some(sample_weights) = self.sample_weights(expected_outputs) {
+            let rows = outputs_error.genrows_mut().into_iter();
+            for (mut error, &weight) in rows.zip(&sample_weights) {
+                error *= weight;
+            }
+        }
+        outputs_error
+    }
+
+    /// Compute and store the gradients of the cost function for the current
+    /// ```Layer``` as part of a network, using the inputs cached during the
+    /// last forward propagation.
+    ///
+    /// ## Input
+    ///
+    /// - `outputs_error`: partial derivative of the cost with respect to the
+    ///   outputs of the layer, either given by ```outputs_error``` for the last
+    ///   layer or returned by the ```backward_propagation``` of the next layer
+    ///   : ([samples] * [outputs])
+    ///
     /// ## Intermediate results
     ///
     /// .: = element-wise multiplication
     ///
+    /// For activation functions that are not element-wise, .* activation_derivative
+    /// stands for the product by their Jacobian (see ```Activation::backpropagate```).
+    ///
     /// - `backprop_error_1`
     ///   : ([samples] * [outputs])
-    ///   = - (self.outputs - expected_outputs) .* activation_derivative(self.layer_outputs_sum)
+    ///   = outputs_error .* activation_derivative(self.layer_outputs_sum)
+    ///   or directly outputs_error with a cost function fused with the
+    ///   activation (see ```Cost::fused_with_activation```),
+    ///   then divided by the temperature (see ```set_temperature```)
     ///
     /// - `cost_d_outputs`: partial derivative of the cost with respect to the outputs weights
     ///   : ([neurons] * [samples]) * ([samples] * [outputs]) = ([neurons] * [outputs])
@@ -152,38 +674,358 @@ impl<F: NdFloat> Layer<F> {
     ///
     /// - `backprop_error_2`
     ///   : ([samples] * [outputs]) * ([outputs] * [neurons]) = ([samples] * [neurons])
-    ///   = (backprop_error_1 * outputs_weights.transposed()) .* activation_derivative(self.layer_inputs_sum)
+    ///   = (backprop_error_1 * outputs_weights.transposed())
+    ///     .* activation_derivative(self.layer_inputs_sum)
+    ///     (.* dropout_mask, when neurons were dropped by the forward propagation)
     ///
     /// - `cost_d_inputs`: partial derivative of the cost with respect to the inputs weights
     ///   : ([inputs] * [samples]) * ([samples] * [neurons]) = ([inputs] * [neurons])
     ///   = inputs.transposed() * backprop_error_2
     ///
+    /// - `cost_d_outputs_bias`: partial derivative of the cost with respect to the outputs bias
+    ///   : (1 * [outputs])
+    ///   = sum of the rows of backprop_error_1
+    ///
+    /// - `cost_d_inputs_bias`: partial derivative of the cost with respect to the inputs bias
+    ///   : (1 * [neurons])
+    ///   = sum of the rows of backprop_error_2
+    ///
+    /// - `cost_d_activation`: partial derivatives of the cost with respect to
+    ///   each learnable parameter `p` of the activation function
+    ///   = sum(outputs_error .* activation_parameter_derivative(self.layer_outputs_sum))
+    ///   + sum((backprop_error_1 * outputs_weights.transposed())
+    ///         .* activation_parameter_derivative(self.layer_inputs_sum))
+    ///
     /// ## Output
     ///
-    /// Returns a view to the gradient of the cost function.
+    /// Returns the partial derivative of the cost with respect to the inputs
+    /// of the layer, to be fed to the previous layer.
+    /// : ([samples] * [neurons]) * ([neurons] * [inputs]) = ([samples] * [inputs])
+    /// = backprop_error_2 * inputs_weights.transposed()
     ///
-    pub fn cost_gradient_mse(
+    pub fn backward_propagation(&mut self, outputs_error: &ArrayView2<F>) -> Array2<F> {
+        let inputs_error = self.unregularized_backward_propagation(outputs_error);
+        self.regularize_gradients();
+        trace!(
+            "Layer.backward_propagation : cost_d_inputs\n{}\ncost_d_outputs\n{}",
+            self.cost_d_inputs,
+            self.cost_d_outputs
+        );
+        inputs_error
+    }
+
+    /// Same as ```backward_propagation```, without the derivatives of the
+    /// regularization penalty: see ```regularize_gradients```.
+    pub(crate) fn unregularized_backward_propagation(
         &mut self,
-        inputs: &ArrayView2<F>,
-        expected_outputs: &ArrayView2<F>,
-    ) -> (ArrayView2<F>, ArrayView2<F>) {
-        let outputs_derivative = self.activation.compute_derivative(&self.layer_outputs_sum);
-        let outputs_delta = expected_outputs - &self.outputs;
-        self.backprop_error_1 = outputs_delta * outputs_derivative;
+        outputs_error: &ArrayView2<F>,
+    ) -> Array2<F> {
+        self.backpropagate_error(outputs_error);
+        self.cost_d_inputs = self.layer_inputs.t().dot(&self.backprop_error_2);
+        self.backprop_error_2.dot(&self.inputs_weights.t())
+    }
+
+    /// Get the regularization penalty of the current weights, added to the
+    /// cost of the layer's outputs.
+    pub fn regularization_cost(&self) -> F {
+        let absolute_sum = |weights: &Array2<F>| weights.fold(F::zero(), |sum, &w| sum + w.abs());
+        let squared_sum = |weights: &Array2<F>| weights.fold(F::zero(), |sum, &w| sum + w * w);
+        let l1_sum = absolute_sum(&self.inputs_weights) + absolute_sum(&self.outputs_weights);
+        let l2_sum = squared_sum(&self.inputs_weights) + squared_sum(&self.outputs_weights);
+        self.l1_lambda * l1_sum + F::from(0.5).unwrap() * self.l2_lambda * l2_sum
+    }
+
+    /// Add the derivatives of the regularization penalty to the weights
+    /// gradients.
+    pub(crate) fn regularize_gradients(&mut self) {
+        if self.l1_lambda != F::zero() {
+            let (zero, l1_lambda) = (F::zero(), self.l1_lambda);
+            let sign = |w: F| if w == zero { zero } else { w.signum() };
+            Zip::from(&mut self.cost_d_inputs)
+                .and(&self.inputs_weights)
+                .apply(|d, &w| *d = *d + l1_lambda * sign(w));
+            Zip::from(&mut self.cost_d_outputs)
+                .and(&self.outputs_weights)
+                .apply(|d, &w| *d = *d + l1_lambda * sign(w));
+        }
+        if self.l2_lambda != F::zero() {
+            self.cost_d_inputs.scaled_add(self.l2_lambda, &self.inputs_weights);
+            self.cost_d_outputs.scaled_add(self.l2_lambda, &self.outputs_weights);
+        }
+    }
+
+    /// Get the sum of the squares of the weights gradients.
+    pub(crate) fn gradients_squared_norm(&self) -> F {
+        let squared_sum = |gradients: &Array2<F>| gradients.fold(F::zero(), |sum, &d| sum + d * d);
+        squared_sum(&self.cost_d_inputs) + squared_sum(&self.cost_d_outputs)
+    }
+
+    /// Multiply every gradient (biases and activation parameters included)
+    /// by `factor`.
+    pub(crate) fn scale_gradients(&mut self, factor: F) {
+        self.cost_d_inputs.mapv_inplace(|d| d * factor);
+        self.cost_d_outputs.mapv_inplace(|d| d * factor);
+        self.cost_d_inputs_bias.mapv_inplace(|d| d * factor);
+        self.cost_d_outputs_bias.mapv_inplace(|d| d * factor);
+        for d in &mut self.cost_d_activation {
+            *d = *d * factor;
+        }
+        if let Some(ref mut batch_norm) = self.batch_norm {
+            batch_norm.scale_gradients(factor);
+        }
+    }
+
+    /// Compute and store every gradient but the one with respect to the
+    /// inputs weights, which depends on the layer's inputs.
+    fn backpropagate_error(&mut self, outputs_error: &ArrayView2<F>) {
+        self.backprop_error_1 = if self.cost.fused_with_activation() {
+            outputs_error.to_owned()
+        } else {
+            self.activation.backpropagate(
+                &self.layer_outputs_sum,
+                &self.outputs,
+                &outputs_error.to_owned(),
+            )
+        };
+        if self.temperature != F::one() {
+            self.backprop_error_1 /= self.temperature;
+        }
         self.cost_d_outputs = self.layer_inputs_sum_activated
             .t()
             .dot(&self.backprop_error_1);
 
-        let inputs_derivative = self.activation.compute_derivative(&self.layer_inputs_sum);
-        self.backprop_error_2 =
-            self.backprop_error_1.dot(&self.outputs_weights.t()) * inputs_derivative;
-        self.cost_d_inputs = inputs.t().dot(&self.backprop_error_2);
+        let mut inputs_error = self.backprop_error_1.dot(&self.outputs_weights.t());
+        self.backprop_error_2 = match self.dropout_mask {
+            Some(ref mask) => {
+                // dropped neurons do not contribute to the cost, and the
+                // cached activated sums are altered by the mask
+                inputs_error *= mask;
+                let activated = self.activation.compute(&self.layer_inputs_sum);
+                self.activation
+                    .backpropagate(&self.layer_inputs_sum, &activated, &inputs_error)
+            }
+            None => self.activation.backpropagate(
+                &self.layer_inputs_sum,
+                &self.layer_inputs_sum_activated,
+                &inputs_error,
+            ),
+        };
+        if let Some(ref mut batch_norm) = self.batch_norm {
+            self.backprop_error_2 = batch_norm.backward_propagation(&self.backprop_error_2.view());
+        }
 
-        (self.cost_d_inputs.view(), self.cost_d_outputs.view())
+        if self.bias {
+            self.cost_d_outputs_bias = self.backprop_error_1.sum_axis(Axis(0));
+            self.cost_d_inputs_bias = self.backprop_error_2.sum_axis(Axis(0));
+        }
+
+        let outputs_parameters_derivative = self.activation
+            .compute_parameters_derivative(&self.layer_outputs_sum);
+        let inputs_parameters_derivative = self.activation
+            .compute_parameters_derivative(&self.layer_inputs_sum);
+        self.cost_d_activation = outputs_parameters_derivative
+            .iter()
+            .zip(inputs_parameters_derivative.iter())
+            .map(|(d_outputs, d_inputs)| {
+                (outputs_error * d_outputs).scalar_sum() + (&inputs_error * d_inputs).scalar_sum()
+            })
+            .collect();
+    }
+
+    /// Update the weights (and the activation function's parameters, if any)
+    /// with one step of gradient descent, using the gradients computed by
+    /// the last backward propagation.
+    ///
+    /// - inputs_weights -= learning_rate * cost_d_inputs
+    ///
+    /// - outputs_weights -= learning_rate * cost_d_outputs
+    ///
+    /// - inputs_bias -= learning_rate * cost_d_inputs_bias (if biased)
+    ///
+    /// - outputs_bias -= learning_rate * cost_d_outputs_bias (if biased)
+    ///
+    /// Panics if no gradients were computed yet.
+    pub fn apply_gradients(&mut self, learning_rate: F) {
+        self.apply_gradients_with(learning_rate, |weights, gradients| {
+            weights.scaled_add(-learning_rate, gradients)
+        });
+    }
+
+    /// Same as ```apply_gradients```, except that the inputs then the outputs
+    /// weights are updated by `update_weights`, given each weights matrix
+    /// along with its gradient.
+    ///
+    /// This allows for other optimization algorithms than plain gradient
+    /// descent, the biases, activation parameters and batch normalization
+    /// (if any) still being updated with `learning_rate`.
+    ///
+    /// Nothing is updated if the layer is frozen.
+    pub fn apply_gradients_with<U>(&mut self, learning_rate: F, mut update_weights: U)
+    where
+        U: FnMut(&mut Array2<F>, &ArrayView2<F>),
+    {
+        if self.frozen {
+            return;
+        }
+        update_weights(&mut self.inputs_weights, &self.cost_d_inputs.view());
+        update_weights(&mut self.outputs_weights, &self.cost_d_outputs.view());
+        if self.bias {
+            self.inputs_bias
+                .scaled_add(-learning_rate, &self.cost_d_inputs_bias);
+            self.outputs_bias
+                .scaled_add(-learning_rate, &self.cost_d_outputs_bias);
+        }
+        if !self.cost_d_activation.is_empty() {
+            let parameters: Vec<F> = self.activation
+                .parameters()
+                .iter()
+                .zip(self.cost_d_activation.iter())
+                .map(|(p, d)| *p - learning_rate * *d)
+                .collect();
+            self.activation.set_parameters(&parameters);
+        }
+        if let Some(ref mut batch_norm) = self.batch_norm {
+            batch_norm.apply_gradients(learning_rate);
+        }
+    }
+
+    /// Get the partial derivative of the cost with respect to the inputs
+    /// weights, as computed by the last backward propagation.
+    pub fn cost_d_inputs(&self) -> ArrayView2<F> {
+        self.cost_d_inputs.view()
+    }
+
+    /// Get the partial derivative of the cost with respect to the outputs
+    /// weights, as computed by the last backward propagation.
+    pub fn cost_d_outputs(&self) -> ArrayView2<F> {
+        self.cost_d_outputs.view()
+    }
+
+    /// Get the partial derivative of the cost with respect to the inputs
+    /// bias, as computed by the last backward propagation (empty if the
+    /// layer has no biases).
+    pub fn cost_d_inputs_bias(&self) -> ArrayView1<F> {
+        self.cost_d_inputs_bias.view()
+    }
+
+    /// Get the partial derivative of the cost with respect to the outputs
+    /// bias, as computed by the last backward propagation (empty if the
+    /// layer has no biases).
+    pub fn cost_d_outputs_bias(&self) -> ArrayView1<F> {
+        self.cost_d_outputs_bias.view()
+    }
+
+    /// Get the learnable parameters of the layer's activation function.
+    pub fn activation_parameters(&self) -> Vec<F> {
+        self.activation.parameters()
+    }
+
+    /// Overwrite the learnable parameters of the layer's activation function.
+    pub fn set_activation_parameters(&mut self, parameters: &[F]) {
+        self.activation.set_parameters(parameters);
+    }
+
+    /// Get the partial derivatives of the cost with respect to the learnable
+    /// parameters of the activation function, as computed by the last
+    /// backward propagation.
+    pub fn cost_d_activation(&self) -> &[F] {
+        &self.cost_d_activation
+    }
+
+    /// Get the number of weights and, if learned, biases of the layer.
+    pub(crate) fn flat_parameters_count(&self) -> usize {
+        let bias_count = if self.bias {
+            self.inputs_bias.len() + self.outputs_bias.len()
+        } else {
+            0
+        };
+        self.inputs_weights.len() + self.outputs_weights.len() + bias_count
+    }
+
+    /// Append the inputs then outputs weights and, if learned, the inputs
+    /// then outputs biases of the layer to `parameters`.
+    pub(crate) fn push_flat_parameters(&self, parameters: &mut Vec<F>) {
+        parameters.extend(self.inputs_weights.iter().chain(self.outputs_weights.iter()));
+        if self.bias {
+            parameters.extend(self.inputs_bias.iter().chain(self.outputs_bias.iter()));
+        }
+    }
+
+    /// Overwrite the weights and biases of the layer, in the order of
+    /// ```push_flat_parameters```, from the start of `parameters`.
+    pub(crate) fn set_flat_parameters(&mut self, parameters: &[F]) {
+        let mut parameters = parameters.iter();
+        {
+            let weights = self.inputs_weights
+                .iter_mut()
+                .chain(self.outputs_weights.iter_mut());
+            for (weight, &parameter) in weights.zip(&mut parameters) {
+                *weight = parameter;
+            }
+        }
+        if self.bias {
+            let biases = self.inputs_bias.iter_mut().chain(self.outputs_bias.iter_mut());
+            for (bias, &parameter) in biases.zip(&mut parameters) {
+                *bias = parameter;
+            }
+        }
+    }
+
+    /// Copy the current weights, biases and activation parameters.
+    pub(crate) fn snapshot(&self) -> LayerParameters<F> {
+        LayerParameters {
+            inputs_weights: self.inputs_weights.clone(),
+            inputs_bias: self.inputs_bias.clone(),
+            outputs_weights: self.outputs_weights.clone(),
+            outputs_bias: self.outputs_bias.clone(),
+            activation: self.activation.parameters(),
+            batch_norm: self.batch_norm.clone(),
+        }
+    }
+
+    /// Copy the gradients computed by the last backward propagation.
+    pub(crate) fn gradients(&self) -> LayerGradients<F> {
+        LayerGradients {
+            inputs_weights: self.cost_d_inputs.clone(),
+            inputs_bias: self.cost_d_inputs_bias.clone(),
+            outputs_weights: self.cost_d_outputs.clone(),
+            outputs_bias: self.cost_d_outputs_bias.clone(),
+            activation: self.cost_d_activation.clone(),
+            batch_norm: self.batch_norm.as_ref().map(|batch_norm| {
+                (batch_norm.cost_d_gamma().to_owned(), batch_norm.cost_d_beta().to_owned())
+            }),
+        }
+    }
+
+    /// Replace the gradients by ones copied from this layer, so that the
+    /// next update applies them.
+    pub(crate) fn set_gradients(&mut self, gradients: &LayerGradients<F>) {
+        self.cost_d_inputs.assign(&gradients.inputs_weights);
+        self.cost_d_inputs_bias.assign(&gradients.inputs_bias);
+        self.cost_d_outputs.assign(&gradients.outputs_weights);
+        self.cost_d_outputs_bias.assign(&gradients.outputs_bias);
+        self.cost_d_activation = gradients.activation.clone();
+        if let (Some(batch_norm), Some((ref d_gamma, ref d_beta))) =
+            (self.batch_norm.as_mut(), gradients.batch_norm.as_ref())
+        {
+            batch_norm.set_gradients(d_gamma, d_beta);
+        }
+    }
+
+    /// Restore parameters previously copied from this layer.
+    pub(crate) fn restore(&mut self, parameters: &LayerParameters<F>) {
+        self.inputs_weights.assign(&parameters.inputs_weights);
+        self.inputs_bias.assign(&parameters.inputs_bias);
+        self.outputs_weights.assign(&parameters.outputs_weights);
+        self.outputs_bias.assign(&parameters.outputs_bias);
+        self.activation.set_parameters(&parameters.activation);
+        self.batch_norm = parameters.batch_norm.clone();
     }
 }
 
 impl Layer<Float> {
+    /// Create a new layer with weights drawn from the default
+    /// ```WeightInit``` (uniformly over [0, 1[).
     pub fn with_random_weights<A: 'static, R>(
         activation: A,
         dim_inputs: usize,
@@ -195,15 +1037,207 @@ impl Layer<Float> {
         A: Activation<Float, Ix2>,
         R: Rng,
     {
-        let inputs_weights =
-            Array2::<Float>::random((dim_inputs, dim_neurons), Range::new(0.0, 1.0), rng);
-        let outputs_weights =
-            Array2::<Float>::random((dim_neurons, dim_outputs), Range::new(0.0, 1.0), rng);
-        Layer::new(activation, inputs_weights, outputs_weights)
+        Layer::with_weight_init(
+            activation,
+            WeightInit::default(),
+            dim_inputs,
+            dim_neurons,
+            dim_outputs,
+            rng,
+        )
+    }
+
+    /// Create a new layer with weights drawn according to `init`.
+    pub fn with_weight_init<A: 'static, R>(
+        activation: A,
+        init: WeightInit,
+        dim_inputs: usize,
+        dim_neurons: usize,
+        dim_outputs: usize,
+        rng: &mut R,
+    ) -> Self
+    where
+        A: Activation<Float, Ix2>,
+        R: Rng,
+    {
+        Layer::with_boxed_weight_init(
+            Box::new(activation),
+            init,
+            dim_inputs,
+            dim_neurons,
+            dim_outputs,
+            rng,
+        )
+    }
+
+    /// Same as ```with_weight_init```, for a boxed activation function (for
+    /// instance reconstructed from its ```ActivationTag```).
+    pub(crate) fn with_boxed_weight_init<R: Rng>(
+        activation: Box<Activation<Float, Ix2>>,
+        init: WeightInit,
+        dim_inputs: usize,
+        dim_neurons: usize,
+        dim_outputs: usize,
+        rng: &mut R,
+    ) -> Self {
+        let inputs_weights = init.weights(dim_inputs, dim_neurons, rng);
+        let outputs_weights = init.weights(dim_neurons, dim_outputs, rng);
+        let mut layer = Layer::with_boxed_activation(
+            activation,
+            inputs_weights,
+            outputs_weights,
+            Array1::zeros(dim_neurons),
+            Array1::zeros(dim_outputs),
+        );
+        layer.bias = false;
+        layer.weight_init = Some(init);
+        layer
+    }
+
+    /// Get the initialization scheme the weights were drawn from, if any.
+    pub fn weight_init(&self) -> Option<WeightInit> {
+        self.weight_init
+    }
+
+    /// Redraw the weights of the layer from `rng`, with the same dimensions
+    /// and initialization scheme (the one recommended by the activation
+    /// function if the weights were not drawn from one), and reset its
+    /// biases, the learnable parameters of its activation function and its
+    /// batch normalization, if any.
+    ///
+    /// The hyperparameters of the layer (activation and cost functions,
+    /// regularization, dropout...) are kept.
+    pub fn reinitialize<R: Rng>(&mut self, rng: &mut R) {
+        let init = self.weight_init
+            .unwrap_or_else(|| self.activation.recommended_init());
+        let (dim_inputs, dim_neurons) = self.inputs_weights.dim();
+        self.inputs_weights = init.weights(dim_inputs, dim_neurons, rng);
+        self.outputs_weights = init.weights(dim_neurons, self.dim_outputs(), rng);
+        self.inputs_bias.fill(0.0);
+        self.outputs_bias.fill(0.0);
+        self.activation.reset_parameters();
+        if let Some(ref mut batch_norm) = self.batch_norm {
+            batch_norm.reset();
+        }
+    }
+
+    /// Get a copy of the weights and activation of the layer, without its
+    /// cached results, its batch normalization being folded into its inputs
+    /// weights and biases, and its temperature into its outputs ones.
+    ///
+    /// inputs_weights .* scale, inputs_bias .* scale + shift
+    ///
+    /// where (scale, shift) is the affine transform of the batch
+    /// normalization in inference mode.
+    pub(crate) fn to_inference(&self) -> InferenceLayer<Float> {
+        let (inputs_weights, inputs_bias) = match self.batch_norm {
+            Some(ref batch_norm) => {
+                let (scale, shift) = batch_norm.inference_affine();
+                (
+                    &self.inputs_weights * &scale,
+                    &self.inputs_bias * &scale + &shift,
+                )
+            }
+            None => (self.inputs_weights.clone(), self.inputs_bias.clone()),
+        };
+        InferenceLayer::new(
+            self.activation.clone(),
+            inputs_weights,
+            inputs_bias,
+            &self.outputs_weights / self.temperature,
+            &self.outputs_bias / self.temperature,
+        )
+    }
+
+    /// Get the serializable representation of the layer.
+    pub(crate) fn to_model(&self) -> NetworkResult<LayerModel> {
+        let activation = match self.activation.tag() {
+            Some(tag) => tag,
+            None => return Err(invalid_model("unsupported activation function".into())),
+        };
+        if self.batch_norm.is_some() {
+            return Err(invalid_model("unsupported batch normalization".into()));
+        }
+        let (inputs_bias, outputs_bias) = if self.bias {
+            (Some(self.inputs_bias.clone()), Some(self.outputs_bias.clone()))
+        } else {
+            (None, None)
+        };
+        let temperature = if self.temperature != 1.0 {
+            Some(self.temperature)
+        } else {
+            None
+        };
+        Ok(LayerModel {
+            activation,
+            inputs_weights: self.inputs_weights.clone(),
+            outputs_weights: self.outputs_weights.clone(),
+            inputs_bias,
+            outputs_bias,
+            temperature,
+        })
+    }
+
+    /// Reconstruct a layer from its serializable representation, checking
+    /// the consistency of its dimensions.
+    pub(crate) fn from_model(model: LayerModel) -> NetworkResult<Self> {
+        let dim_neurons = model.inputs_weights.cols();
+        let (weights_neurons, dim_outputs) = model.outputs_weights.dim();
+        if weights_neurons != dim_neurons {
+            return Err(invalid_model(format!(
+                "neurons count mismatch ({} != {})",
+                dim_neurons, weights_neurons,
+            )));
+        }
+        let bias = match (model.inputs_bias, model.outputs_bias) {
+            (Some(inputs_bias), Some(outputs_bias)) => {
+                if inputs_bias.len() != dim_neurons || outputs_bias.len() != dim_outputs {
+                    return Err(invalid_model(format!(
+                        "biases size mismatch ({:?} != {:?})",
+                        (inputs_bias.len(), outputs_bias.len()),
+                        (dim_neurons, dim_outputs),
+                    )));
+                }
+                Some((inputs_bias, outputs_bias))
+            }
+            (None, None) => None,
+            _ => return Err(invalid_model("incomplete biases".into())),
+        };
+        let has_bias = bias.is_some();
+        let (inputs_bias, outputs_bias) =
+            bias.unwrap_or_else(|| (Array1::zeros(dim_neurons), Array1::zeros(dim_outputs)));
+        let mut layer = Layer::with_boxed_activation(
+            model.activation.activation(),
+            model.inputs_weights,
+            model.outputs_weights,
+            inputs_bias,
+            outputs_bias,
+        );
+        layer.bias = has_bias;
+        match model.temperature {
+            Some(temperature) if temperature > 0.0 => layer.temperature = temperature,
+            Some(temperature) => {
+                return Err(invalid_model(format!("invalid temperature {}", temperature)))
+            }
+            None => {}
+        }
+        Ok(layer)
+    }
+
+    /// Compute the cost of the last forward propagation for each output,
+    /// summed over the (weighted) samples and without regularization.
+    pub(crate) fn summed_costs(&self, expected_outputs: &ArrayView2<Float>) -> Array1<Float> {
+        let outputs = self.outputs.view();
+        match self.sample_weights(expected_outputs) {
+            Some(weights) => self.cost
+                .weighted_cost(&outputs, expected_outputs, &weights.view()),
+            None => self.cost.cost(&outputs, expected_outputs),
+        }
     }
 
     /// Compute and store the "score" of our current outputs evaluation compared
-    /// to the expected outputs using the Mean Squared Error cost function.
+    /// to the expected outputs using the layer's cost function (Mean Squared
+    /// Error by default).
     ///
     /// ## Input
     ///
@@ -214,22 +1248,648 @@ impl Layer<Float> {
     ///
     /// costs
     /// : (1 * [ouputs])
-    /// = 1/2 * sum((expected_output - output) ^ 2)
+    /// = 1/2 * sum((expected_output - output) ^ 2) / [samples]
+    ///
+    /// The cost is averaged over the samples, so that it does not depend on
+    /// the batch size. The ```regularization_cost``` of the layer, if any, is
+    /// evenly split across the outputs.
     pub fn cost_mse(
         &mut self,
         inputs: &ArrayView2<Float>,
         expected_outputs: &ArrayView2<Float>,
     ) -> ArrayView1<Float> {
-        let mut squared_diffs = Array2::zeros(expected_outputs.dim());
-        Zip::from(&mut squared_diffs)
-            .and(&self.outputs)
-            .and(expected_outputs)
-            .apply(|d, expected, approx| *d = (expected - approx).powi(2));
-
-        self.costs = Array1::zeros(expected_outputs.cols());
-        Zip::from(&mut self.costs)
-            .and(squared_diffs.gencolumns())
-            .apply(|c, d_row| *c = 0.5 * d_row.scalar_sum());
+        let samples = expected_outputs.rows().max(1) as Float;
+        self.costs = self.summed_costs(expected_outputs) / samples;
+        let regularization_cost = self.regularization_cost() / self.costs.len() as Float;
+        self.costs += regularization_cost;
         self.costs.view()
     }
 }
+
+/// Describe the topology of the layer, for instance:
+///
+/// Layer(inputs=2, neurons=3, outputs=1, activation=Sigmoid)
+impl<F: NdFloat> fmt::Display for Layer<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Layer(inputs={}, neurons={}, outputs={}, activation={})",
+            self.dim_inputs(),
+            self.dim_neurons(),
+            self.dim_outputs(),
+            self.activation_name()
+        )
+    }
+}
+
+/// Draw a dropout mask of shape `dim` from `rng`: each element is either 0
+/// (dropped neuron, with the probability `dropout`) or 1 / (1 - dropout).
+fn dropout_mask<F: NdFloat, R: Rng>(dim: (usize, usize), dropout: F, rng: &mut R) -> Array2<F> {
+    let keep = F::one() - dropout;
+    let scale = F::one() / keep;
+    Array2::from_shape_fn(dim, |_| {
+        if F::from(rng.gen::<f64>()).unwrap() < keep {
+            scale
+        } else {
+            F::zero()
+        }
+    })
+}
+
+/// Number of samples evaluated by each task of the parallel forward
+/// propagation.
+#[cfg(feature = "rayon")]
+const PARALLEL_SAMPLES_CHUNK: usize = 256;
+
+/// Compute the weighted sum of `inputs` and its activation.
+///
+/// (inputs * weights + bias, activation(inputs * weights + bias))
+fn serial_activated_sum<F: NdFloat>(
+    activation: &Activation<F, Ix2>,
+    inputs: &ArrayView2<F>,
+    weights: &Array2<F>,
+    bias: &Array1<F>,
+) -> (Array2<F>, Array2<F>) {
+    let sum = inputs.dot(weights) + bias;
+    let activated = activation.compute(&sum);
+    (sum, activated)
+}
+
+/// Same as ```serial_activated_sum```, with the samples (rows) of `inputs`
+/// split into chunks evaluated in parallel. The results are identical.
+#[cfg(feature = "rayon")]
+fn parallel_activated_sum<F: NdFloat>(
+    activation: &Activation<F, Ix2>,
+    inputs: &ArrayView2<F>,
+    weights: &Array2<F>,
+    bias: &Array1<F>,
+) -> (Array2<F>, Array2<F>) {
+    if inputs.rows() <= PARALLEL_SAMPLES_CHUNK {
+        return serial_activated_sum(activation, inputs, weights, bias);
+    }
+    let chunks: Vec<_> = inputs
+        .axis_chunks_iter(Axis(0), PARALLEL_SAMPLES_CHUNK)
+        .collect();
+    let results: Vec<_> = chunks
+        .par_iter()
+        .map(|chunk| serial_activated_sum(activation, chunk, weights, bias))
+        .collect();
+    let sums: Vec<_> = results.iter().map(|&(ref sum, _)| sum.view()).collect();
+    let activated: Vec<_> = results.iter().map(|&(_, ref activated)| activated.view()).collect();
+    (
+        stack(Axis(0), &sums).unwrap(),
+        stack(Axis(0), &activated).unwrap(),
+    )
+}
+
+#[cfg(not(feature = "rayon"))]
+use self::serial_activated_sum as activated_sum;
+#[cfg(feature = "rayon")]
+use self::parallel_activated_sum as activated_sum;
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use log::{self, LevelFilter, Log, Metadata, Record};
+    use ndarray::{arr1, arr2, stack};
+    use rand::{SeedableRng, StdRng};
+    use rand::distributions::Range;
+    use activation::{Identity, Sigmoid, TanH};
+    use network::NeuralNetwork;
+    use testing::{gradient_check, gradient_check_tolerance};
+    use utils::NdArrayRandomizer;
+    use super::*;
+
+    thread_local! {
+        static CAPTURING: Cell<bool> = Cell::new(false);
+        static CAPTURED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    /// Records the messages logged by the test threads capturing them.
+    struct CapturingLogger;
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            CAPTURING.with(Cell::get)
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                CAPTURED.with(|captured| captured.borrow_mut().push(record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger;
+
+    fn forward(layers: &mut [Layer<Float>], inputs: &Array2<Float>) {
+        let mut layer_inputs = inputs.clone();
+        for layer in layers.iter_mut() {
+            layer_inputs = layer
+                .forward_propagation(&layer_inputs.view())
+                .unwrap()
+                .to_owned();
+        }
+    }
+
+    fn cost(
+        layers: &mut [Layer<Float>],
+        inputs: &Array2<Float>,
+        expected: &Array2<Float>,
+    ) -> Float {
+        forward(layers, inputs);
+        let (last_layer, layers) = layers.split_last_mut().unwrap();
+        let regularization_cost: Float = layers.iter().map(Layer::regularization_cost).sum();
+        last_layer.summed_costs(&expected.view()).scalar_sum()
+            + last_layer.regularization_cost() + regularization_cost
+    }
+
+    fn weights_mut(layer: &mut Layer<Float>, outputs_weights: bool) -> &mut Array2<Float> {
+        if outputs_weights {
+            &mut layer.outputs_weights
+        } else {
+            &mut layer.inputs_weights
+        }
+    }
+
+    fn two_layers() -> Vec<Layer<Float>> {
+        vec![
+            Layer::new(
+                TanH,
+                arr2(&[[0.1, -0.4, 0.3], [0.7, 0.2, -0.5]]),
+                arr2(&[[0.3, 0.1], [-0.2, 0.6], [0.5, -0.8]]),
+            ),
+            Layer::new(
+                Sigmoid,
+                arr2(&[[0.4, -0.6], [0.9, 0.2]]),
+                arr2(&[[-0.3], [0.8]]),
+            ),
+        ]
+    }
+
+    fn assert_gradients_match_finite_differences(mut layers: Vec<Layer<Float>>) {
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.5], [-1.0, 2.0]]);
+        let expected = arr2(&[[1.0], [0.0], [0.5]]);
+
+        forward(&mut layers, &inputs);
+        let mut error = layers[1].outputs_error(&expected.view());
+        for layer in layers.iter_mut().rev() {
+            error = layer.backward_propagation(&error.view());
+        }
+        let gradients: Vec<(Array2<Float>, Array2<Float>)> = layers
+            .iter()
+            .map(|l| (l.cost_d_inputs().to_owned(), l.cost_d_outputs().to_owned()))
+            .collect();
+
+        let (epsilon, tolerance) = gradient_check_tolerance();
+        for l in 0..layers.len() {
+            for &outputs_weights in &[false, true] {
+                let dim = weights_mut(&mut layers[l], outputs_weights).dim();
+                for i in 0..dim.0 {
+                    for j in 0..dim.1 {
+                        weights_mut(&mut layers[l], outputs_weights)[[i, j]] += epsilon;
+                        let cost_plus = cost(&mut layers, &inputs, &expected);
+                        weights_mut(&mut layers[l], outputs_weights)[[i, j]] -= 2.0 * epsilon;
+                        let cost_minus = cost(&mut layers, &inputs, &expected);
+                        weights_mut(&mut layers[l], outputs_weights)[[i, j]] += epsilon;
+
+                        let numerical = (cost_plus - cost_minus) / (2.0 * epsilon);
+                        let analytical = if outputs_weights {
+                            gradients[l].1[[i, j]]
+                        } else {
+                            gradients[l].0[[i, j]]
+                        };
+                        assert_relative_eq!(
+                            numerical,
+                            analytical,
+                            epsilon = 0.01 * tolerance,
+                            max_relative = tolerance
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backward_propagation_matches_finite_differences() {
+        assert_gradients_match_finite_differences(two_layers());
+    }
+
+    #[test]
+    fn l2_gradients_match_finite_differences() {
+        let layers = two_layers()
+            .into_iter()
+            .map(|layer| layer.with_l2(0.3))
+            .collect();
+        assert_gradients_match_finite_differences(layers);
+    }
+
+    #[test]
+    fn l1_gradients_match_finite_differences() {
+        let layers = two_layers()
+            .into_iter()
+            .map(|layer| layer.with_l1(0.2).with_l2(0.1))
+            .collect();
+        assert_gradients_match_finite_differences(layers);
+    }
+
+    #[test]
+    fn l1_sparsity() {
+        // only the first 2 of the 8 inputs are relevant, the noise being
+        // fitted with the others when not regularized
+        let mut rng = StdRng::from_seed(&[5usize][..]);
+        let inputs = Array2::random((32, 8), Range::new(-1.0, 1.0), &mut rng);
+        let noise = Array2::random((32, 1), Range::new(-0.5, 0.5), &mut rng);
+        let expected = inputs.column(0).mapv(|x| 2.0 * x) - &inputs.column(1);
+        let expected = expected.into_shape((32, 1)).unwrap() + &noise;
+        // number of weights of the irrelevant inputs driven to ~0
+        let sparse_weights = |l1_lambda: Float| {
+            let inputs_weights = Array2::from_elem((8, 1), 0.5);
+            let mut layer =
+                Layer::new(Identity, inputs_weights, arr2(&[[1.0]])).with_l1(l1_lambda);
+            for _ in 0..2000 {
+                layer.forward_propagation(&inputs.view()).unwrap();
+                layer.cost_gradient_mse(&inputs.view(), &expected.view());
+                layer.apply_gradients(0.05);
+            }
+            layer
+                .inputs_weights()
+                .iter()
+                .skip(2)
+                .filter(|w| w.abs() < 0.05)
+                .count()
+        };
+        let unregularized = sparse_weights(0.0);
+        let regularized = sparse_weights(0.1);
+        assert!(regularized > unregularized, "{} <= {}", regularized, unregularized);
+    }
+
+    #[test]
+    fn cost_mse_is_averaged_over_samples() {
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 2.0], [-0.5, 0.0]]);
+        let expected = arr2(&[[0.2], [0.9], [-0.4]]);
+        let cost_and_gradients = |inputs: &Array2<Float>, expected: &Array2<Float>| {
+            let mut layer = Layer::new(
+                TanH,
+                arr2(&[[0.3, -0.2, 0.5], [0.1, 0.4, -0.6]]),
+                arr2(&[[0.7], [-0.3], [0.2]]),
+            ).with_l2(0.1);
+            layer.forward_propagation(&inputs.view()).unwrap();
+            let cost = layer
+                .cost_mse(&inputs.view(), &expected.view())
+                .to_owned();
+            let (d_inputs, d_outputs) = layer.cost_gradient_mse(&inputs.view(), &expected.view());
+            (cost, d_inputs.to_owned(), d_outputs.to_owned())
+        };
+
+        let duplicated_inputs = stack(Axis(0), &[inputs.view(); 10]).unwrap();
+        let duplicated_expected = stack(Axis(0), &[expected.view(); 10]).unwrap();
+        let (cost, d_inputs, d_outputs) = cost_and_gradients(&inputs, &expected);
+        let (duplicated_cost, duplicated_d_inputs, duplicated_d_outputs) =
+            cost_and_gradients(&duplicated_inputs, &duplicated_expected);
+        assert_relative_eq!(cost[0], duplicated_cost[0], epsilon = 1e-6);
+        for (d, duplicated_d) in d_inputs.iter().zip(duplicated_d_inputs.iter()) {
+            assert_relative_eq!(d, duplicated_d, epsilon = 1e-6);
+        }
+        for (d, duplicated_d) in d_outputs.iter().zip(duplicated_d_outputs.iter()) {
+            assert_relative_eq!(d, duplicated_d, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn dropout_modes() {
+        let mut layer = Layer::with_init_fn(Sigmoid, 4, 64, 2, |(i, j)| {
+            ((i * 13 + j * 7) % 11) as Float / 11.0 - 0.5
+        }).with_dropout(0.5);
+        let inputs = arr2(&[[0.1, 0.2, 0.3, 0.4], [0.5, -0.5, 1.0, -1.0]]);
+        let forward = |layer: &mut Layer<Float>| {
+            layer.forward_propagation(&inputs.view()).unwrap().to_owned()
+        };
+
+        assert!(!layer.training());
+        assert_eq!(forward(&mut layer), forward(&mut layer));
+
+        layer.set_training(true);
+        assert_ne!(forward(&mut layer), forward(&mut layer));
+        layer.set_training(false);
+        assert_eq!(forward(&mut layer), forward(&mut layer));
+    }
+
+    #[test]
+    fn dropout_masks_gradients() {
+        let mut layer = Layer::with_init_fn(TanH, 3, 32, 1, |(i, j)| {
+            ((i * 5 + j * 3) % 7) as Float / 7.0 - 0.4
+        }).with_dropout(0.5);
+        layer.set_training(true);
+        let inputs = arr2(&[[0.5, -1.0, 2.0]]);
+        let expected = arr2(&[[0.3]]);
+        layer.forward_propagation(&inputs.view()).unwrap();
+        layer.cost_gradient_mse(&inputs.view(), &expected.view());
+
+        let mask = layer.dropout_mask.clone().unwrap();
+        let dropped = mask.iter().filter(|&&m| m == 0.0).count();
+        assert!(dropped > 0 && dropped < 32);
+        for (neuron, &m) in mask.row(0).iter().enumerate() {
+            assert!(m == 0.0 || m == 2.0);
+            let inputs_gradients = layer.cost_d_inputs().column(neuron).to_owned();
+            let outputs_gradients = layer.cost_d_outputs().row(neuron).to_owned();
+            if m == 0.0 {
+                assert!(inputs_gradients.iter().all(|&d| d == 0.0));
+                assert!(outputs_gradients.iter().all(|&d| d == 0.0));
+            } else {
+                assert!(inputs_gradients.iter().all(|&d| d != 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn l2_shrinks_weights() {
+        let inputs = arr2(&[[-1.0, 0.5], [-0.5, 1.0], [0.5, -1.0], [1.0, 0.0]]);
+        let expected = inputs.dot(&arr2(&[[3.0], [-2.0]]));
+        let weights_norm = |l2_lambda: Float| {
+            let inputs_weights = arr2(&[[0.5, 0.1], [-0.3, 0.2]]);
+            let outputs_weights = arr2(&[[0.4], [0.6]]);
+            let mut layer =
+                Layer::new(Identity, inputs_weights, outputs_weights).with_l2(l2_lambda);
+            for _ in 0..500 {
+                layer.forward_propagation(&inputs.view()).unwrap();
+                layer.cost_gradient_mse(&inputs.view(), &expected.view());
+                layer.apply_gradients(0.02);
+            }
+            let squared = |w: ArrayView2<Float>| w.mapv(|w| w * w).scalar_sum();
+            squared(layer.inputs_weights()) + squared(layer.outputs_weights())
+        };
+        let norms: Vec<Float> = [0.0, 0.5, 2.0].iter().map(|&l| weights_norm(l)).collect();
+        assert!(norms[1] < norms[0] && norms[2] < norms[1], "{:?}", norms);
+    }
+
+    #[test]
+    fn bias_fits_intercept() {
+        let mut layer = Layer::new_with_bias(
+            Identity,
+            arr2(&[[0.5]]),
+            arr2(&[[0.5]]),
+            Array1::zeros(1),
+            Array1::zeros(1),
+        );
+        // y = 2 * x + 1
+        let inputs = arr2(&[[-1.0], [-0.5], [0.0], [0.5], [1.0]]);
+        let expected = inputs.mapv(|x| 2.0 * x + 1.0);
+        for _ in 0..1000 {
+            layer.forward_propagation(&inputs.view()).unwrap();
+            layer.cost_gradient_mse(&inputs.view(), &expected.view());
+            layer.apply_gradients(0.05);
+        }
+        let outputs = layer.forward_propagation(&inputs.view()).unwrap();
+        for (output, expected) in outputs.iter().zip(expected.iter()) {
+            assert_relative_eq!(output, expected, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn no_bias_goes_through_origin() {
+        let mut layer = Layer::new(Identity, arr2(&[[0.5]]), arr2(&[[0.5]]));
+        let inputs = arr2(&[[0.0], [1.0]]);
+        let expected = arr2(&[[1.0], [3.0]]);
+        for _ in 0..100 {
+            layer.forward_propagation(&inputs.view()).unwrap();
+            layer.cost_gradient_mse(&inputs.view(), &expected.view());
+            layer.apply_gradients(0.05);
+        }
+        let outputs = layer.forward_propagation(&inputs.view()).unwrap();
+        assert_eq!(outputs[[0, 0]], 0.0);
+    }
+
+    #[test]
+    fn init_fn() {
+        let mut calls = 0;
+        let layer = Layer::with_init_fn(Identity, 2, 3, 1, |(i, j)| {
+            calls += 1;
+            (10 * i + j) as Float
+        });
+        assert_eq!(calls, 2 * 3 + 3 * 1);
+        assert_eq!(
+            layer.inputs_weights,
+            arr2(&[[0.0, 1.0, 2.0], [10.0, 11.0, 12.0]])
+        );
+        assert_eq!(layer.outputs_weights, arr2(&[[0.0], [10.0], [20.0]]));
+    }
+
+    #[test]
+    fn diagnostics_go_through_log() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(LevelFilter::Trace);
+        CAPTURING.with(|capturing| capturing.set(true));
+
+        let mut layer = Layer::with_init_fn(Sigmoid, 2, 3, 1, |(i, j)| (i + j) as Float);
+        let inputs = arr2(&[[0.5, 1.0]]);
+        layer.forward_propagation(&inputs.view()).unwrap();
+        layer.backward_propagation(&arr2(&[[0.1]]).view());
+
+        CAPTURING.with(|capturing| capturing.set(false));
+        let captured = CAPTURED.with(|captured| captured.borrow().clone());
+        assert_eq!(captured.len(), 2);
+        assert!(captured[0].starts_with("Layer.forward_propagation"));
+        assert!(captured[1].starts_with("Layer.backward_propagation"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_forward_propagation() {
+        let mut rng = StdRng::from_seed(&[59usize][..]);
+        let inputs = Array2::random((10_000, 128), Range::new(-1.0, 1.0), &mut rng);
+        let mut layer = Layer::with_weight_init(
+            TanH,
+            WeightInit::XavierUniform,
+            128,
+            64,
+            10,
+            &mut rng,
+        );
+
+        let serial = serial_activated_sum(
+            &TanH,
+            &inputs.view(),
+            &layer.inputs_weights,
+            &layer.inputs_bias,
+        );
+        let parallel = parallel_activated_sum(
+            &TanH,
+            &inputs.view(),
+            &layer.inputs_weights,
+            &layer.inputs_bias,
+        );
+        assert_eq!(serial, parallel);
+
+        let serial_outputs = serial_activated_sum(
+            &TanH,
+            &serial.1.view(),
+            &layer.outputs_weights,
+            &layer.outputs_bias,
+        ).1;
+        assert_eq!(layer.forward_propagation(&inputs.view()).unwrap(), serial_outputs);
+    }
+
+    #[test]
+    fn weights_round_trip() {
+        let mut layer = Layer::with_init_fn(Sigmoid, 2, 3, 1, |(i, j)| (i + j) as Float);
+        let inputs_weights = layer.inputs_weights().to_owned();
+        let outputs_weights = layer.outputs_weights().to_owned();
+
+        layer
+            .set_inputs_weights(Array2::from_elem((2, 3), 0.5))
+            .unwrap();
+        layer
+            .set_outputs_weights(Array2::from_elem((3, 1), 0.5))
+            .unwrap();
+        assert_eq!(layer.inputs_weights(), Array2::from_elem((2, 3), 0.5));
+
+        layer.set_inputs_weights(inputs_weights.clone()).unwrap();
+        layer.set_outputs_weights(outputs_weights.clone()).unwrap();
+        assert_eq!(layer.inputs_weights(), inputs_weights);
+        assert_eq!(layer.outputs_weights(), outputs_weights);
+
+        assert_eq!(
+            layer.set_inputs_weights(Array2::zeros((3, 2))),
+            Err(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 3,
+            })
+        );
+        assert_eq!(
+            layer.set_outputs_weights(Array2::zeros((3, 2))),
+            Err(NetworkError::DimensionMismatch {
+                expected: 1,
+                got: 2,
+            })
+        );
+        assert_eq!(layer.inputs_weights(), inputs_weights);
+    }
+
+    #[test]
+    fn batch_norm_gradients() {
+        // no vanishing gradient, whose relative error single precision
+        // could not check
+        let mut rng = StdRng::from_seed(&[82usize][..]);
+        let layer = Layer::with_weight_init(TanH, WeightInit::XavierUniform, 2, 4, 1, &mut rng)
+            .with_batch_norm(0.9);
+        assert_eq!(layer.num_parameters(), 2 * 4 + 4 * 1 + 2 * 4);
+        assert_eq!(layer.flat_parameters_count(), 2 * 4 + 4 * 1);
+        let mut neural_network = NeuralNetwork::new(vec![layer]);
+        neural_network.set_training(true);
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2], [-0.3, 0.8], [0.0, 0.0]]);
+        let expected_outputs = arr2(&[[0.2], [-0.4], [0.7], [0.1]]);
+        let (step, tolerance) = gradient_check_tolerance();
+        let error = gradient_check(
+            &mut neural_network,
+            inputs.view(),
+            expected_outputs.view(),
+            step,
+        ).unwrap();
+        assert!(error < tolerance, "max relative error : {}", error);
+
+        let batch_norm = neural_network.layers()[0].batch_norm().unwrap();
+        assert!(batch_norm.running_mean().iter().any(|&mean| mean != 0.0));
+        assert!(neural_network.layers()[0].to_model().is_err());
+    }
+
+    #[test]
+    fn class_weights_scale_gradients() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 5 + j * 3) % 7) as Float / 7.0 - 0.4;
+        let mut layer = Layer::with_init_fn(Sigmoid, 2, 3, 2, init_fn);
+        let mut weighted_layer = layer
+            .clone()
+            .with_class_weights(arr1(&[1.0, 10.0]))
+            .unwrap();
+        assert_eq!(weighted_layer.class_weights(), Some(arr1(&[1.0, 10.0]).view()));
+        // a sample of the majority class, then one of the minority class
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2]]);
+        let expected_outputs = arr2(&[[1.0, 0.0], [0.0, 1.0]]);
+        for layer in vec![&mut layer, &mut weighted_layer] {
+            layer.forward_propagation(&inputs.view()).unwrap();
+        }
+
+        let error = layer.outputs_error(&expected_outputs.view());
+        let weighted_error = weighted_layer.outputs_error(&expected_outputs.view());
+        assert_eq!(weighted_error.row(0), error.row(0));
+        assert_eq!(weighted_error.row(1), error.row(1).to_owned() * 10.0);
+        let costs = layer.summed_costs(&expected_outputs.view());
+        let weighted_costs = weighted_layer.summed_costs(&expected_outputs.view());
+        assert!(weighted_costs.scalar_sum() > costs.scalar_sum());
+
+        // the gradients of the minority sample alone are 10 times larger
+        let minority_inputs = arr2(&[[1.0, 0.2]]);
+        let minority_outputs = arr2(&[[0.0, 1.0]]);
+        let mut gradients = Vec::new();
+        for layer in vec![&mut layer, &mut weighted_layer] {
+            layer.forward_propagation(&minority_inputs.view()).unwrap();
+            let (d_inputs, d_outputs) =
+                layer.cost_gradient_mse(&minority_inputs.view(), &minority_outputs.view());
+            gradients.push((d_inputs.to_owned(), d_outputs.to_owned()));
+        }
+        for (&d, &weighted_d) in gradients[0].0.iter().zip(gradients[1].0.iter()) {
+            assert_relative_eq!(weighted_d, 10.0 * d, max_relative = 1e-6);
+        }
+        for (&d, &weighted_d) in gradients[0].1.iter().zip(gradients[1].1.iter()) {
+            assert_relative_eq!(weighted_d, 10.0 * d, max_relative = 1e-6);
+        }
+    }
+
+    #[test]
+    fn class_weights_count_mismatch() {
+        let layer = Layer::with_init_fn(Sigmoid, 2, 3, 2, |_| 0.1);
+        assert!(layer.with_class_weights(arr1(&[1.0])).is_err());
+    }
+
+    #[test]
+    fn zero_weighted_outputs_are_ignored() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 5 + j * 3) % 7) as Float / 7.0 - 0.4;
+        let layer = Layer::with_init_fn(Sigmoid, 2, 3, 2, init_fn);
+        assert_eq!(
+            layer.clone().with_output_weights(arr1(&[1.0])).err(),
+            Some(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 1,
+            })
+        );
+        assert_eq!(
+            layer.clone().with_output_weights(arr1(&[1.0, -1.0])).err(),
+            Some(NetworkError::InvalidOutputWeights)
+        );
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2]]);
+        // the expected values of the second, zero-weighted output differ
+        let expected_outputs = [arr2(&[[1.0, 0.0], [0.0, 1.0]]), arr2(&[[1.0, 5.0], [0.0, -3.0]])];
+        let mut results = Vec::new();
+        for expected_outputs in &expected_outputs {
+            let mut layer = layer.clone().with_output_weights(arr1(&[2.0, 0.0])).unwrap();
+            layer.forward_propagation(&inputs.view()).unwrap();
+            let costs = layer
+                .cost_mse(&inputs.view(), &expected_outputs.view())
+                .to_owned();
+            assert_eq!(costs[1], 0.0);
+            let (d_inputs, d_outputs) =
+                layer.cost_gradient_mse(&inputs.view(), &expected_outputs.view());
+            assert!(d_outputs.column(1).iter().all(|&d| d == 0.0));
+            results.push((costs, d_inputs.to_owned(), d_outputs.to_owned()));
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn display() {
+        let layer = Layer::with_init_fn(Sigmoid, 2, 3, 1, |_| 0.5);
+        assert_eq!(
+            layer.to_string(),
+            "Layer(inputs=2, neurons=3, outputs=1, activation=Sigmoid)"
+        );
+        let layer = Layer::with_init_fn(TanH, 4, 8, 2, |_| 0.5);
+        assert_eq!(
+            format!("{}", layer),
+            "Layer(inputs=4, neurons=8, outputs=2, activation=TanH)"
+        );
+    }
+}
@@ -1,15 +1,191 @@
 #[cfg(test)]
 #[macro_use]
 extern crate approx;
+extern crate bincode;
+#[macro_use]
+extern crate log;
 extern crate ndarray;
 extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The floating-point type of the weights and samples, f64 unless the `f32`
+/// feature is enabled.
+#[cfg(not(feature = "f32"))]
 pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
 pub type ResultString<T> = Result<T, String>;
+pub type NetworkResult<T> = Result<T, NetworkError>;
+
+/// The errors raised when building, running or training a Neural Network.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkError {
+    /// A dimension (number of rows or columns of a matrix) differs from the
+    /// expected one.
+    DimensionMismatch { expected: usize, got: usize },
+    /// The number of values of a sample differs from the first sample's.
+    SampleMismatch {
+        sample_index: usize,
+        expected: usize,
+        got: usize,
+    },
+    /// The dataset holds no sample.
+    EmptyDataset,
+    /// The Neural Network holds no layer.
+    NoLayers,
+    /// The Neural Network has no inputs.
+    NoInputs,
+    /// A layer of the Neural Network has no neurons or no outputs.
+    EmptyLayer { layer_index: usize },
+    /// A sample of a training dataset has no observed outputs.
+    MissingOutputs { sample_index: usize },
+    /// A class label is not within [0, num_classes[.
+    LabelOutOfRange { label: usize, num_classes: usize },
+    /// A category index is not within [0, num_categories[.
+    CategoryOutOfRange {
+        category: usize,
+        num_categories: usize,
+    },
+    /// No validation dataset was given to the trainer.
+    NoValidationDataset,
+    /// Batches must hold at least 1 sample.
+    ZeroBatchSize,
+    /// A k-fold split requires from 2 folds up to one fold per sample.
+    InvalidFoldCount { folds: usize, samples: usize },
+    /// The fraction of samples held out as a test set is not within [0, 1].
+    InvalidTestFraction { test_fraction: f64 },
+    /// The loss is no longer finite (NaN or infinite) after the given epoch,
+    /// starting at 1 (0 standing for the loss before any training).
+    Diverged { epoch: usize },
+    /// No batch size was given to the trainer.
+    NoBatchSize,
+    /// A sample read from a stream (such as a ```CsvDataset```) is invalid.
+    InvalidSample { reason: String },
+    /// A file could not be created, opened, read or written.
+    Io { path: PathBuf, reason: String },
+    /// A saved Neural Network could not be serialized or deserialized.
+    InvalidModel { reason: String },
+    /// No built-in activation function has this name.
+    UnknownActivation { name: String },
+    /// No weight initialization has this name.
+    UnknownWeightInit { name: String },
+    /// An ensemble holds no Neural Network.
+    EmptyEnsemble,
+    /// The weights of the outputs in the cost must be finite and non-negative.
+    InvalidOutputWeights,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use NetworkError::*;
+        match *self {
+            DimensionMismatch { expected, got } => {
+                write!(f, "dimension mismatch ({} instead of the expected {})", got, expected)
+            }
+            SampleMismatch {
+                sample_index,
+                expected,
+                got,
+            } => write!(
+                f,
+                "dataset error for sample of index {} : values count mismatch \
+                 ({} instead of the expected {})",
+                sample_index, got, expected
+            ),
+            EmptyDataset => write!(f, "empty dataset"),
+            NoLayers => write!(f, "no layers defined"),
+            NoInputs => write!(f, "a Neural Network requires at least 1 input"),
+            EmptyLayer { layer_index } => write!(
+                f,
+                "the layer of index {} requires at least 1 neuron and 1 output",
+                layer_index
+            ),
+            MissingOutputs { sample_index } => write!(
+                f,
+                "dataset error : no observed output for the sample of index {}",
+                sample_index
+            ),
+            LabelOutOfRange { label, num_classes } => write!(
+                f,
+                "label {} is out of range ({} classes)",
+                label, num_classes
+            ),
+            CategoryOutOfRange {
+                category,
+                num_categories,
+            } => write!(
+                f,
+                "category {} is out of range ({} categories)",
+                category, num_categories
+            ),
+            NoValidationDataset => write!(f, "no validation dataset"),
+            ZeroBatchSize => write!(f, "the batch size must be at least 1"),
+            InvalidFoldCount { folds, samples } => write!(
+                f,
+                "cannot split {} samples into {} folds (at least 2 folds, and at most 1 per \
+                 sample)",
+                samples, folds
+            ),
+            InvalidTestFraction { test_fraction } => write!(
+                f,
+                "the test fraction must be within [0, 1] (got {})",
+                test_fraction
+            ),
+            Diverged { epoch } => write!(f, "loss diverged at epoch {}", epoch),
+            NoBatchSize => write!(f, "no batch size set"),
+            InvalidSample { ref reason } => write!(f, "invalid sample : {}", reason),
+            Io { ref path, ref reason } => {
+                write!(f, "cannot access {} ({})", path.display(), reason)
+            }
+            InvalidModel { ref reason } => write!(f, "invalid model : {}", reason),
+            UnknownActivation { ref name } => {
+                write!(f, "unknown activation function \"{}\"", name)
+            }
+            UnknownWeightInit { ref name } => {
+                write!(f, "unknown weight initialization \"{}\"", name)
+            }
+            EmptyEnsemble => write!(f, "an ensemble requires at least 1 member"),
+            InvalidOutputWeights => {
+                write!(f, "the output weights must be finite and non-negative")
+            }
+        }
+    }
+}
+
+impl Error for NetworkError {
+    fn description(&self) -> &str {
+        "Neural Network error"
+    }
+}
+
+impl From<NetworkError> for String {
+    fn from(error: NetworkError) -> String {
+        error.to_string()
+    }
+}
 
 pub mod activation;
 pub mod builder;
+pub mod cost;
+pub mod embedding;
+pub mod ensemble;
+pub mod inference;
+pub mod init;
 pub mod layer;
+pub mod metrics;
 pub mod network;
+pub mod normalization;
+pub mod optimizer;
+pub mod testing;
 pub mod training;
+mod serialization;
 mod utils;
@@ -0,0 +1,287 @@
+//! Evaluation metrics of a trained Neural Network.
+
+use std::cmp::Ordering;
+
+use ndarray::{Array2, ArrayView1, ArrayView2};
+
+use super::Float;
+
+/// Get the index of the maximum of `values` (the first one in case of ties).
+fn argmax(values: ArrayView1<Float>) -> usize {
+    let mut max_index = 0;
+    for (index, &value) in values.iter().enumerate() {
+        if value > values[max_index] {
+            max_index = index;
+        }
+    }
+    max_index
+}
+
+/// Get the predicted class of each row (sample) of `predictions`, that is
+/// the index of its maximum value (the first one in case of ties).
+pub fn argmax_rows(predictions: &ArrayView2<Float>) -> Vec<usize> {
+    predictions.genrows().into_iter().map(argmax).collect()
+}
+
+/// Get the `k` most likely classes of each row (sample) of `predictions`,
+/// as (class, score) pairs sorted by decreasing score (and increasing class
+/// in case of ties).
+///
+/// Every class of a row is returned if `k` exceeds the number of classes.
+pub fn top_k_rows(predictions: &ArrayView2<Float>, k: usize) -> Vec<Vec<(usize, Float)>> {
+    predictions
+        .genrows()
+        .into_iter()
+        .map(|row| {
+            let mut classes: Vec<(usize, Float)> = row.iter().cloned().enumerate().collect();
+            classes.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+            classes.truncate(k);
+            classes
+        })
+        .collect()
+}
+
+/// Compute the accuracy of a classifier, that is the fraction of samples
+/// whose predicted class matches the expected one.
+///
+/// The class of each row (sample) is the index of its maximum value, so that
+/// `expected` holds one-hot vectors and `predicted`, for instance, the
+/// probabilities given by a ```activation::Softmax``` output layer.
+///
+/// Returns 0 for an empty batch. Panics if the shapes of `predicted` and
+/// `expected` differ.
+pub fn accuracy(predicted: &ArrayView2<Float>, expected: &ArrayView2<Float>) -> Float {
+    assert_eq!(
+        predicted.dim(),
+        expected.dim(),
+        "accuracy : shape mismatch between the predictions and the expected outputs"
+    );
+    if predicted.rows() == 0 {
+        return 0.0;
+    }
+    let correct = predicted
+        .genrows()
+        .into_iter()
+        .zip(expected.genrows())
+        .filter(|&(predicted, expected)| argmax(predicted) == argmax(expected))
+        .count();
+    correct as Float / predicted.rows() as Float
+}
+
+/// Count the samples of each (expected class, predicted class) pair, the
+/// classes being given by the index of the maximum of each row as in
+/// ```accuracy```.
+///
+/// confusion_matrix : ([num_classes] * [num_classes])
+///
+/// Panics if `predicted` and `expected` do not both have `num_classes`
+/// columns and as many rows.
+pub fn confusion_matrix(
+    predicted: &ArrayView2<Float>,
+    expected: &ArrayView2<Float>,
+    num_classes: usize,
+) -> Array2<usize> {
+    assert_eq!(
+        predicted.dim(),
+        (expected.rows(), num_classes),
+        "confusion_matrix : shape mismatch between the predictions and the expected outputs"
+    );
+    assert_eq!(
+        expected.cols(),
+        num_classes,
+        "confusion_matrix : the expected outputs must have one column per class"
+    );
+    let mut confusion = Array2::zeros((num_classes, num_classes));
+    for (predicted, expected) in predicted.genrows().into_iter().zip(expected.genrows()) {
+        confusion[[argmax(expected), argmax(predicted)]] += 1;
+    }
+    confusion
+}
+
+/// Compute the precision, recall and F1 score of each class of a classifier,
+/// from the ```confusion_matrix``` of its predictions.
+///
+/// - precision = true positives / predicted positives
+///
+/// - recall = true positives / actual positives
+///
+/// - F1 = 2 * precision * recall / (precision + recall)
+///
+/// Any ratio with a zero denominator is 0.
+pub fn precision_recall_f1(
+    predicted: &ArrayView2<Float>,
+    expected: &ArrayView2<Float>,
+    num_classes: usize,
+) -> Vec<(Float, Float, Float)> {
+    let ratio = |numerator: Float, denominator: Float| {
+        if denominator == 0.0 {
+            0.0
+        } else {
+            numerator / denominator
+        }
+    };
+    let confusion = confusion_matrix(predicted, expected, num_classes);
+    (0..num_classes)
+        .map(|class| {
+            let true_positives = confusion[[class, class]] as Float;
+            let predicted_positives = confusion.column(class).scalar_sum() as Float;
+            let actual_positives = confusion.row(class).scalar_sum() as Float;
+            let precision = ratio(true_positives, predicted_positives);
+            let recall = ratio(true_positives, actual_positives);
+            let f1 = ratio(2.0 * precision * recall, precision + recall);
+            (precision, recall, f1)
+        })
+        .collect()
+}
+
+/// Compute the coefficient of determination (R²) of a regression, averaged
+/// over the outputs (columns).
+///
+/// R² = 1 - SS_res / SS_tot
+///
+/// with SS_res the sum of the squared residuals (expected - predicted) and
+/// SS_tot the sum of the squared deviations of `expected` from its mean. An
+/// output with a constant target (SS_tot = 0) scores 1 if it is perfectly
+/// predicted, and 0 otherwise.
+///
+/// Returns 0 for an empty batch. Panics if the shapes of `predicted` and
+/// `expected` differ.
+pub fn r_squared(predicted: &ArrayView2<Float>, expected: &ArrayView2<Float>) -> Float {
+    assert_eq!(
+        predicted.dim(),
+        expected.dim(),
+        "r_squared : shape mismatch between the predictions and the expected outputs"
+    );
+    if predicted.is_empty() {
+        return 0.0;
+    }
+    let scores = predicted
+        .gencolumns()
+        .into_iter()
+        .zip(expected.gencolumns())
+        .map(|(predicted, expected)| {
+            let mean = expected.scalar_sum() / expected.len() as Float;
+            let ss_tot = expected.fold(0.0, |sum, &y| sum + (y - mean) * (y - mean));
+            let ss_res = (&expected - &predicted).fold(0.0, |sum, &r| sum + r * r);
+            if ss_tot != 0.0 {
+                1.0 - ss_res / ss_tot
+            } else if ss_res == 0.0 {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .sum::<Float>();
+    scores / predicted.cols() as Float
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr2, Array2};
+    use super::*;
+
+    #[test]
+    fn accuracy_three_classes() {
+        let predicted = arr2(&[
+            [0.7, 0.2, 0.1],
+            [0.1, 0.8, 0.1],
+            [0.3, 0.3, 0.4],
+            [0.5, 0.4, 0.1],
+            [0.2, 0.2, 0.6],
+        ]);
+        let expected = arr2(&[
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0],
+        ]);
+        assert_relative_eq!(accuracy(&predicted.view(), &expected.view()), 3.0 / 5.0);
+        assert_relative_eq!(accuracy(&expected.view(), &expected.view()), 1.0);
+    }
+
+    #[test]
+    fn argmax_and_top_k_rows() {
+        let predictions = arr2(&[[0.1, 0.6, 0.3], [0.5, 0.2, 0.3], [0.4, 0.2, 0.4]]);
+        assert_eq!(argmax_rows(&predictions.view()), vec![1, 0, 0]);
+
+        let top_2 = top_k_rows(&predictions.view(), 2);
+        assert_eq!(
+            top_2,
+            vec![
+                vec![(1, 0.6), (2, 0.3)],
+                vec![(0, 0.5), (2, 0.3)],
+                vec![(0, 0.4), (2, 0.4)],
+            ]
+        );
+        let top_5 = top_k_rows(&predictions.view(), 5);
+        assert_eq!(top_5[1], vec![(0, 0.5), (2, 0.3), (1, 0.2)]);
+        assert!(top_k_rows(&predictions.view(), 0).iter().all(Vec::is_empty));
+
+        let empty = Array2::zeros((0, 3));
+        assert!(argmax_rows(&empty.view()).is_empty());
+        assert!(top_k_rows(&empty.view(), 2).is_empty());
+    }
+
+    #[test]
+    fn accuracy_empty_batch() {
+        let empty = Array2::zeros((0, 3));
+        assert_eq!(accuracy(&empty.view(), &empty.view()), 0.0);
+    }
+
+    #[test]
+    fn precision_recall_f1_per_class() {
+        // actual \ predicted : 0  1  2
+        // 0                  : 3  1  0
+        // 1                  : 2  1  0
+        // 2                  : 0  1  0
+        let one_hot = |class: usize| {
+            let mut row = [0.0; 3];
+            row[class] = 1.0;
+            row
+        };
+        let pairs = [(0, 0), (0, 0), (0, 0), (0, 1), (1, 0), (1, 0), (1, 1), (2, 1)];
+        let expected = arr2(&pairs.iter().map(|&(e, _)| one_hot(e)).collect::<Vec<_>>());
+        let predicted = arr2(&pairs.iter().map(|&(_, p)| one_hot(p)).collect::<Vec<_>>());
+
+        let confusion = confusion_matrix(&predicted.view(), &expected.view(), 3);
+        assert_eq!(confusion, arr2(&[[3, 1, 0], [2, 1, 0], [0, 1, 0]]));
+
+        let scores = precision_recall_f1(&predicted.view(), &expected.view(), 3);
+        assert_eq!(scores.len(), 3);
+        let (precision, recall, f1) = scores[0];
+        assert_relative_eq!(precision, 3.0 / 5.0);
+        assert_relative_eq!(recall, 3.0 / 4.0);
+        assert_relative_eq!(f1, 2.0 / 3.0);
+        let (precision, recall, f1) = scores[1];
+        assert_relative_eq!(precision, 1.0 / 3.0);
+        assert_relative_eq!(recall, 1.0 / 3.0);
+        assert_relative_eq!(f1, 1.0 / 3.0);
+        // never predicted: zero precision and F1 instead of NaN
+        assert_eq!(scores[2], (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn r_squared_regression() {
+        let expected = arr2(&[[1.0, 10.0], [2.0, 20.0], [3.0, 30.0], [6.0, 40.0]]);
+        assert_relative_eq!(r_squared(&expected.view(), &expected.view()), 1.0);
+
+        let means = arr2(&[[3.0, 25.0], [3.0, 25.0], [3.0, 25.0], [3.0, 25.0]]);
+        assert_relative_eq!(r_squared(&means.view(), &expected.view()), 0.0);
+
+        // the first output is perfect, the second one predicts its mean
+        let predicted = arr2(&[[1.0, 25.0], [2.0, 25.0], [3.0, 25.0], [6.0, 25.0]]);
+        assert_relative_eq!(r_squared(&predicted.view(), &expected.view()), 0.5);
+    }
+
+    #[test]
+    fn r_squared_constant_target() {
+        let expected = arr2(&[[2.0], [2.0], [2.0]]);
+        assert_eq!(r_squared(&expected.view(), &expected.view()), 1.0);
+        let predicted = arr2(&[[2.0], [1.0], [2.0]]);
+        assert_eq!(r_squared(&predicted.view(), &expected.view()), 0.0);
+
+        let empty = Array2::zeros((0, 1));
+        assert_eq!(r_squared(&empty.view(), &empty.view()), 0.0);
+    }
+}
@@ -1,51 +1,1359 @@
-use ndarray::ArrayView2;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 
-use super::{Float, ResultString};
-use layer::Layer;
+use bincode;
+use rand::Rng;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use serde_json;
+
+use super::{Float, NetworkError, NetworkResult};
+use activation::parse_activation;
+use builder::{NetworkConfig, NeuralNetworkBuilder};
+use inference::InferenceNetwork;
+use init::WeightInit;
+use layer::{Layer, LayerGradients, LayerParameters};
+use serialization::{invalid_model, NetworkModel};
+use training::{Sample, TargetScaler};
+use utils::{check_shape, io_error, seeded_rng, DROPOUT_STREAM};
 
 /// An Artificial Neural Network mimics the behavior of real nervous systems
 /// by simulating Neurons (grouped by ```Layer```).
 ///
 /// The Neural Network is composed of several ```Layer```s.
+#[derive(Clone)]
 pub struct NeuralNetwork {
     layers: Vec<Layer<Float>>,
+    target_scaler: Option<TargetScaler>,
 }
 
 impl NeuralNetwork {
     pub fn new(layers: Vec<Layer<Float>>) -> Self {
-        NeuralNetwork { layers }
+        NeuralNetwork {
+            layers,
+            target_scaler: None,
+        }
+    }
+
+    /// Attach the scaler fitted on the outputs of the training dataset, so
+    /// that ```predict_denormalized``` returns predictions in the original
+    /// units. The scaler is saved along with the layers.
+    ///
+    /// The scaler must have as many columns as the Neural Network has
+    /// outputs.
+    pub fn set_target_scaler<S: Into<TargetScaler>>(&mut self, scaler: S) -> NetworkResult<()> {
+        let scaler = scaler.into();
+        if scaler.columns() != self.output_dim() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.output_dim(),
+                got: scaler.columns(),
+            });
+        }
+        self.target_scaler = Some(scaler);
+        Ok(())
+    }
+
+    /// Get the scaler of the outputs, if any.
+    pub fn target_scaler(&self) -> Option<&TargetScaler> {
+        self.target_scaler.as_ref()
+    }
+
+    /// Switch every layer between training and inference (the default)
+    /// modes.
+    ///
+    /// Behaviors such as dropout only apply in training mode, so that the
+    /// predictions of a Neural Network in inference mode are deterministic.
+    pub fn set_training(&mut self, training: bool) {
+        for layer in &mut self.layers {
+            layer.set_training(training);
+        }
+    }
+
+    /// Redraw the weights of every layer from `rng`, keeping the topology
+    /// and hyperparameters of the Neural Network: see
+    /// ```Layer::reinitialize```.
+    ///
+    /// Useful to train the same Neural Network from scratch several times,
+    /// for instance during a hyperparameter search.
+    pub fn reinitialize<R: Rng>(&mut self, rng: &mut R) {
+        for layer in &mut self.layers {
+            layer.reinitialize(rng);
+        }
+    }
+
+    /// Set the temperature of the output layer, dividing its logits before
+    /// their activation: see ```Layer::set_temperature```.
+    ///
+    /// It can be adjusted after training, to calibrate the probabilities
+    /// predicted by a classifier.
+    pub fn set_temperature(&mut self, temperature: Float) {
+        if let Some(layer) = self.layers.last_mut() {
+            layer.set_temperature(temperature);
+        }
+    }
+
+    /// Build a Neural Network from its `config`, the weights being drawn
+    /// from `rng`.
+    ///
+    /// Fails on an unknown activation function or weight initialization
+    /// name, or on an invalid topology.
+    pub fn from_config<R: Rng>(
+        config: &NetworkConfig,
+        rng: &mut R,
+    ) -> NetworkResult<NeuralNetwork> {
+        let mut builder = NeuralNetworkBuilder::with_inputs(config.input_dim);
+        let mut inputs = config.input_dim;
+        for (index, spec) in config.layers.iter().enumerate() {
+            let activation = parse_activation(&spec.activation)?;
+            let init = match spec.init.as_str() {
+                "Auto" => activation.recommended_init(),
+                name => WeightInit::from_name(name).ok_or_else(|| {
+                    NetworkError::UnknownWeightInit {
+                        name: name.to_owned(),
+                    }
+                })?,
+            };
+            let outputs = if index + 1 == config.layers.len() {
+                config.output_dim
+            } else {
+                spec.neurons
+            };
+            let layer =
+                Layer::with_boxed_weight_init(activation, init, inputs, spec.neurons, outputs, rng);
+            builder = builder.add_layer(layer)?;
+            inputs = outputs;
+        }
+        builder.build()
+    }
+
+    /// Seed the dropout masks of every layer from `seed` (each layer drawing
+    /// its own masks), so that the training of the Neural Network is
+    /// reproducible.
+    pub fn seed(&mut self, seed: u64) {
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            layer.set_dropout_rng(seeded_rng(seed, DROPOUT_STREAM + index));
+        }
+    }
+
+    /// Same as ```seed```, consuming and returning the Neural Network.
+    pub fn seeded(mut self, seed: u64) -> Self {
+        self.seed(seed);
+        self
+    }
+
+    /// Get the number of inputs of the Neural Network, that is of its first
+    /// layer (0 without layers).
+    pub fn input_dim(&self) -> usize {
+        self.layers.first().map_or(0, Layer::dim_inputs)
+    }
+
+    /// Get the number of outputs of the Neural Network, that is of its last
+    /// layer (0 without layers).
+    pub fn output_dim(&self) -> usize {
+        self.layers.last().map_or(0, Layer::dim_outputs)
     }
 
+    /// Get the number of learnable parameters of the Neural Network, summed
+    /// over its layers (see ```Layer::num_parameters```).
+    pub fn num_parameters(&self) -> usize {
+        self.layers.iter().map(Layer::num_parameters).sum()
+    }
+
+    /// Describe the topology of the Neural Network: the dimensions,
+    /// activation function and parameters count of each layer, followed by
+    /// the total parameters count.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{:<8}{:>8}{:>10}{:>10}  {:<14}{:>12}\n",
+            "Layer", "Inputs", "Neurons", "Outputs", "Activation", "Parameters"
+        );
+        for (index, layer) in self.layers.iter().enumerate() {
+            summary += &format!(
+                "{:<8}{:>8}{:>10}{:>10}  {:<14}{:>12}\n",
+                index,
+                layer.dim_inputs(),
+                layer.dim_neurons(),
+                layer.dim_outputs(),
+                layer.activation_name(),
+                layer.num_parameters()
+            );
+        }
+        summary + &format!("Total parameters: {}\n", self.num_parameters())
+    }
+
+    /// Save the Neural Network's layers (weights, biases and activation
+    /// functions) and target scaler (if any) to a JSON file.
+    ///
+    /// The cost functions and any cached results are not saved.
+    pub fn save_json(&self, path: &Path) -> NetworkResult<()> {
+        let model = self.to_model()?;
+        let file = File::create(path).map_err(|why| io_error(path, &why))?;
+        serde_json::to_writer(BufWriter::new(file), &model)
+            .map_err(|why| invalid_model(format!("serialization error ({})", why)))
+    }
+
+    /// Load a Neural Network previously saved with ```save_json```.
+    pub fn load_json(path: &Path) -> NetworkResult<NeuralNetwork> {
+        let file = File::open(path).map_err(|why| io_error(path, &why))?;
+        let model = serde_json::from_reader(BufReader::new(file))
+            .map_err(|why| invalid_model(format!("deserialization error ({})", why)))?;
+        NeuralNetwork::from_model(model)
+    }
+
+    /// Save the Neural Network's layers to a compact binary file, better
+    /// suited than JSON to large weight matrices.
+    pub fn save_bin(&self, path: &Path) -> NetworkResult<()> {
+        let model = self.to_model()?;
+        let file = File::create(path).map_err(|why| io_error(path, &why))?;
+        bincode::serialize_into(BufWriter::new(file), &model)
+            .map_err(|why| invalid_model(format!("serialization error ({})", why)))
+    }
+
+    /// Load a Neural Network previously saved with ```save_bin```.
+    pub fn load_bin(path: &Path) -> NetworkResult<NeuralNetwork> {
+        let file = File::open(path).map_err(|why| io_error(path, &why))?;
+        let model = bincode::deserialize_from(BufReader::new(file))
+            .map_err(|why| invalid_model(format!("deserialization error ({})", why)))?;
+        NeuralNetwork::from_model(model)
+    }
+
+    fn to_model(&self) -> NetworkResult<NetworkModel> {
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for layer in &self.layers {
+            layers.push(layer.to_model()?);
+        }
+        Ok(NetworkModel {
+            layers,
+            target_scaler: self.target_scaler.clone(),
+        })
+    }
+
+    fn from_model(model: NetworkModel) -> NetworkResult<NeuralNetwork> {
+        if model.layers.is_empty() {
+            return Err(NetworkError::NoLayers);
+        }
+        let mut layers: Vec<Layer<Float>> = Vec::with_capacity(model.layers.len());
+        for layer_model in model.layers {
+            let layer = Layer::from_model(layer_model)?;
+            if let Some(previous_layer) = layers.last() {
+                let (outputs, inputs) = (
+                    previous_layer.outputs_weights().cols(),
+                    layer.inputs_weights().rows(),
+                );
+                if outputs != inputs {
+                    return Err(NetworkError::DimensionMismatch {
+                        expected: outputs,
+                        got: inputs,
+                    });
+                }
+            }
+            layers.push(layer);
+        }
+        let mut network = NeuralNetwork::new(layers);
+        if let Some(scaler) = model.target_scaler {
+            network.set_target_scaler(scaler)?;
+        }
+        Ok(network)
+    }
+
+    /// Perform a forward propagation of `inputs` followed by a backward
+    /// propagation of the error with respect to `expected_outputs`, from the
+    /// last layer to the first one.
+    ///
+    /// The gradients of the ```loss```, the cost being averaged over the
+    /// samples, are stored within each layer.
+    ///
+    /// Returns the partial derivative of the loss with respect to `inputs`,
+    /// to be propagated further back (e.g. to an ```EmbeddingLayer```).
     pub fn backward_propagation(
         &mut self,
         inputs: ArrayView2<Float>,
         expected_outputs: ArrayView2<Float>,
-    ) -> ResultString<()> {
-        let mut layer_result = Err("backprop error".into());
+    ) -> NetworkResult<Array2<Float>> {
+        let inputs_error = self.unregularized_backward_propagation(inputs, expected_outputs)?;
+        self.regularize_gradients();
+        Ok(inputs_error)
+    }
+
+    /// Same as ```backward_propagation```, without the derivatives of the
+    /// regularization penalties: see ```regularize_gradients```.
+    pub(crate) fn unregularized_backward_propagation(
+        &mut self,
+        inputs: ArrayView2<Float>,
+        expected_outputs: ArrayView2<Float>,
+    ) -> NetworkResult<Array2<Float>> {
+        {
+            let outputs = self.run_forward(inputs)?;
+            check_shape(outputs.dim(), expected_outputs.dim())?;
+        }
+        // the cost is averaged over the samples, and so are its gradients
+        let samples = expected_outputs.rows().max(1) as Float;
+        let mut error = self.layers.last().unwrap().outputs_error(&expected_outputs) / samples;
+        for layer in self.layers.iter_mut().rev() {
+            error = layer.unregularized_backward_propagation(&error.view());
+        }
+        Ok(error)
+    }
+
+    /// Add the derivatives of the regularization penalty of every layer to
+    /// its current gradients.
+    pub(crate) fn regularize_gradients(&mut self) {
         for layer in &mut self.layers {
-            {
-                let cost = layer.cost_mse(&inputs, &expected_outputs);
-                println!("layer cost=\n{}", cost);
-            }
-            {
-                let (cost_d_inputs, cost_d_outputs) =
-                    layer.cost_gradient_mse(&inputs, &expected_outputs);
-                println!(
-                    "layer cost gradient:\n/inputs = {}\n/outputs = {}",
-                    cost_d_inputs, cost_d_outputs
-                );
+            layer.regularize_gradients();
+        }
+    }
+
+    /// Compute the loss of the Neural Network on `inputs`, that is the total
+    /// cost (as defined by the last layer's cost function) with respect to
+    /// `expected_outputs` divided by the number of samples, plus the
+    /// regularization costs of the layers.
+    ///
+    /// The gradients computed by ```backward_propagation``` are the ones of
+    /// this loss, whose scale does not depend on the number of samples.
+    pub fn loss(
+        &mut self,
+        inputs: ArrayView2<Float>,
+        expected_outputs: ArrayView2<Float>,
+    ) -> NetworkResult<Float> {
+        {
+            let outputs = self.run_forward(inputs)?;
+            check_shape(outputs.dim(), expected_outputs.dim())?;
+        }
+        Ok(self.last_loss(expected_outputs))
+    }
+
+    /// Compute the loss of the outputs of the last forward propagation with
+    /// respect to `expected_outputs`: see ```loss```.
+    fn last_loss(&self, expected_outputs: ArrayView2<Float>) -> Float {
+        let regularization_cost: Float = self.layers.iter().map(Layer::regularization_cost).sum();
+        let costs = self.layers.last().unwrap().summed_costs(&expected_outputs);
+        costs.scalar_sum() / expected_outputs.rows() as Float + regularization_cost
+    }
+
+    /// Train the Neural Network online, on a single sample (or batch) as it
+    /// arrives: perform a forward and backward propagation of `inputs` in
+    /// training mode, then update the weights with one step of gradient
+    /// descent of `learning_rate`. The layers are then put back in their
+    /// previous mode.
+    ///
+    /// Returns the loss of `inputs` before the update (see ```loss```).
+    pub fn train_one(
+        &mut self,
+        inputs: ArrayView2<Float>,
+        expected_outputs: ArrayView2<Float>,
+        learning_rate: Float,
+    ) -> NetworkResult<Float> {
+        let modes: Vec<bool> = self.layers.iter().map(Layer::training).collect();
+        self.set_training(true);
+        let propagation = self.backward_propagation(inputs, expected_outputs);
+        for (layer, &training) in self.layers.iter_mut().zip(&modes) {
+            layer.set_training(training);
+        }
+        propagation?;
+        let loss = self.last_loss(expected_outputs);
+        self.update(learning_rate);
+        Ok(loss)
+    }
+
+    /// Evaluate the Neural Network on a dataset: the mean squared error
+    /// between its outputs for `inputs` and `expected_outputs`, averaged over
+    /// both the samples and the outputs.
+    ///
+    /// Unlike ```loss```, this ignores the cost function of the last layer
+    /// and the regularization of the layers.
+    pub fn evaluate(
+        &mut self,
+        inputs: ArrayView2<Float>,
+        expected_outputs: ArrayView2<Float>,
+    ) -> NetworkResult<Float> {
+        let outputs = self.run_forward(inputs)?;
+        check_shape(outputs.dim(), expected_outputs.dim())?;
+        if outputs.is_empty() {
+            return Err(NetworkError::EmptyDataset);
+        }
+        let squared_errors = (&outputs - &expected_outputs).mapv(|d| d * d);
+        Ok(squared_errors.scalar_sum() / outputs.len() as Float)
+    }
+
+    /// Scale down the gradients computed by the last backward propagation so
+    /// that their global L2 norm, over the weights gradients of all the
+    /// layers, does not exceed `max_norm`.
+    ///
+    /// Must be called between ```backward_propagation``` and the update of
+    /// the weights. Returns the global norm before clipping.
+    pub fn clip_gradients(&mut self, max_norm: Float) -> Float {
+        let norm = self.layers
+            .iter()
+            .map(Layer::gradients_squared_norm)
+            .sum::<Float>()
+            .sqrt();
+        if norm > max_norm {
+            for layer in &mut self.layers {
+                layer.scale_gradients(max_norm / norm);
             }
         }
-        layer_result
+        norm
+    }
+
+    /// Update the weights of every layer with one step of gradient descent,
+    /// using the gradients computed by the last backward propagation.
+    pub fn update(&mut self, learning_rate: Float) {
+        for layer in &mut self.layers {
+            layer.apply_gradients(learning_rate);
+        }
+    }
+
+    /// Update the weights of every layer with `update_weights`, given the
+    /// index of each weights matrix (the inputs then the outputs weights of
+    /// each layer, in order) along with the matrix and its gradient.
+    ///
+    /// The weights of frozen layers are skipped, but still numbered.
+    ///
+    /// See ```Layer::apply_gradients_with```.
+    pub fn update_with<U>(&mut self, learning_rate: Float, mut update_weights: U)
+    where
+        U: FnMut(usize, &mut Array2<Float>, &ArrayView2<Float>),
+    {
+        for (l, layer) in self.layers.iter_mut().enumerate() {
+            let mut index = 2 * l;
+            layer.apply_gradients_with(learning_rate, |weights, gradients| {
+                update_weights(index, weights, gradients);
+                index += 1;
+            });
+        }
+    }
+
+    pub(crate) fn layers(&self) -> &[Layer<Float>] {
+        &self.layers
+    }
+
+    pub(crate) fn layers_mut(&mut self) -> &mut [Layer<Float>] {
+        &mut self.layers
+    }
+
+    /// Flatten the weights and biases of every layer into a single vector.
+    ///
+    /// Each layer contributes, in order, its inputs weights, its outputs
+    /// weights and, if it learns them, its inputs then outputs biases.
+    pub fn get_parameters(&self) -> Array1<Float> {
+        let mut parameters = Vec::new();
+        for layer in &self.layers {
+            layer.push_flat_parameters(&mut parameters);
+        }
+        Array1::from_vec(parameters)
+    }
+
+    /// Overwrite the weights and biases of every layer from a vector laid
+    /// out as by ```get_parameters```.
+    pub fn set_parameters(&mut self, parameters: &ArrayView1<Float>) -> NetworkResult<()> {
+        let expected = self.layers.iter().map(Layer::flat_parameters_count).sum();
+        if parameters.len() != expected {
+            return Err(NetworkError::DimensionMismatch {
+                expected,
+                got: parameters.len(),
+            });
+        }
+        let parameters = parameters.to_vec();
+        let mut offset = 0;
+        for layer in &mut self.layers {
+            layer.set_flat_parameters(&parameters[offset..]);
+            offset += layer.flat_parameters_count();
+        }
+        Ok(())
+    }
+
+    /// Copy the learnable parameters of every layer.
+    pub(crate) fn snapshot(&self) -> Vec<LayerParameters<Float>> {
+        self.layers.iter().map(Layer::snapshot).collect()
+    }
+
+    /// Restore parameters previously copied from this Neural Network.
+    pub(crate) fn restore(&mut self, parameters: &[LayerParameters<Float>]) {
+        for (layer, layer_parameters) in self.layers.iter_mut().zip(parameters) {
+            layer.restore(layer_parameters);
+        }
+    }
+
+    /// Copy the gradients of every layer computed by the last backward
+    /// propagation.
+    pub(crate) fn gradients(&self) -> Vec<LayerGradients<Float>> {
+        self.layers.iter().map(Layer::gradients).collect()
+    }
+
+    /// Replace the gradients of every layer by ones copied from this Neural
+    /// Network, so that the next update applies them.
+    pub(crate) fn set_gradients(&mut self, gradients: &[LayerGradients<Float>]) {
+        for (layer, layer_gradients) in self.layers.iter_mut().zip(gradients) {
+            layer.set_gradients(layer_gradients);
+        }
     }
 
     /// Perform simple forward propagation accross the layers and return an
     /// ```ÀrrayView``` to the last layer's output.
-    pub fn run_forward(&mut self, inputs: ArrayView2<Float>) -> ResultString<ArrayView2<Float>> {
-        let mut layer_result = Err("NeuralNetwork.run_foward : no layers defined.".into());
+    ///
+    /// Each layer is fed with the outputs of the previous one.
+    pub fn run_forward(&mut self, inputs: ArrayView2<Float>) -> NetworkResult<ArrayView2<Float>> {
+        let (last_layer, layers) = match self.layers.split_last_mut() {
+            Some(split) => split,
+            None => return Err(NetworkError::NoLayers),
+        };
+        let mut layer_inputs = inputs.to_owned();
+        for layer in layers {
+            layer_inputs = layer.forward_propagation(&layer_inputs.view())?.to_owned();
+        }
+        last_layer.forward_propagation(&layer_inputs.view())
+    }
+
+    /// Get an inference-only copy of the Neural Network, holding nothing but
+    /// the weights and activations of its layers, whose outputs are the ones
+    /// of ```run_forward``` in inference mode.
+    pub fn to_inference(&self) -> InferenceNetwork {
+        InferenceNetwork::new(self.layers.iter().map(Layer::to_inference).collect())
+    }
+
+    /// Perform a forward propagation of `inputs` and return the outputs of
+    /// every layer, in order, the last one being the result of
+    /// ```run_forward```.
+    pub fn forward_with_activations(
+        &mut self,
+        inputs: ArrayView2<Float>,
+    ) -> NetworkResult<Vec<Array2<Float>>> {
+        if self.layers.is_empty() {
+            return Err(NetworkError::NoLayers);
+        }
+        let mut activations: Vec<Array2<Float>> = Vec::with_capacity(self.layers.len());
         for layer in &mut self.layers {
-            layer_result = layer.forward_propagation(&inputs);
+            let outputs = match activations.last() {
+                Some(layer_inputs) => layer.forward_propagation(&layer_inputs.view()),
+                None => layer.forward_propagation(&inputs),
+            }?.to_owned();
+            activations.push(outputs);
+        }
+        Ok(activations)
+    }
+
+    /// Perform a forward propagation of `inputs` and return an owned copy of
+    /// the last layer's output.
+    ///
+    /// Unlike ```run_forward```, the result does not borrow the network.
+    pub fn predict(&mut self, inputs: ArrayView2<Float>) -> NetworkResult<Array2<Float>> {
+        self.run_forward(inputs).map(|outputs| outputs.to_owned())
+    }
+
+    /// Same as ```predict```, except that the outputs are mapped back to the
+    /// original scale by the target scaler, if any (see
+    /// ```set_target_scaler```).
+    pub fn predict_denormalized(
+        &mut self,
+        inputs: ArrayView2<Float>,
+    ) -> NetworkResult<Array2<Float>> {
+        let outputs = self.predict(inputs)?;
+        Ok(match self.target_scaler {
+            Some(ref scaler) => scaler.inverse_transform(&outputs),
+            None => outputs,
+        })
+    }
+
+    /// Perform a forward propagation of a single ```Sample``` and return the
+    /// corresponding outputs.
+    ///
+    /// Any observed outputs contained in the sample are ignored.
+    pub fn predict_sample(&mut self, sample: &Sample) -> NetworkResult<Array1<Float>> {
+        let sample_inputs = sample.inputs();
+        let inputs = sample_inputs
+            .into_shape((1, sample_inputs.len()))
+            .expect("NeuralNetwork.predict_sample : the sample inputs are contiguous");
+        self.run_forward(inputs).map(|outputs| outputs.row(0).to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use ndarray::{arr1, arr2, Array1, Array2, ArrayView2};
+    use rand::{SeedableRng, StdRng};
+    use serde_json;
+
+    use activation::{Identity, PReLU, Sigmoid, TanH};
+    use builder::{LayerSpec, NetworkConfig, NeuralNetworkBuilder};
+    use init::WeightInit;
+    use layer::Layer;
+    use metrics::argmax_rows;
+    use network::NeuralNetwork;
+    use optimizer::{Adam, Optimizer};
+    use testing::{gradient_check, gradient_check_tolerance};
+    use training::{MinMaxScaler, Sample, StandardScaler};
+    use {Float, NetworkError};
+
+    #[test]
+    fn run_forward_chains_layers() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(3)
+            .layer(4, TanH, &mut rng)
+            .layer(5, TanH, &mut rng)
+            .output(6, 2, Sigmoid, &mut rng);
+        let inputs = Array2::zeros((7, 3));
+        let outputs = neural_network.run_forward(inputs.view()).unwrap();
+        assert_eq!(outputs.dim(), (7, 2));
+    }
+
+    #[test]
+    fn flat_parameters_round_trip() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(3)
+            .layer(4, TanH, &mut rng)
+            .output(2, 1, Sigmoid, &mut rng);
+        let parameters = neural_network.get_parameters();
+        assert_eq!(parameters.len(), 3 * 4 + 4 * 4 + 4 * 2 + 2 * 1);
+        neural_network.set_parameters(&parameters.view()).unwrap();
+        assert_eq!(neural_network.get_parameters(), parameters);
+        let too_short: Array1<Float> = Array1::zeros(parameters.len() - 1);
+        assert_eq!(
+            neural_network.set_parameters(&too_short.view()).err(),
+            Some(NetworkError::DimensionMismatch {
+                expected: parameters.len(),
+                got: parameters.len() - 1,
+            })
+        );
+
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new_with_bias(
+                Identity,
+                arr2(&[[1.0, 2.0]]),
+                arr2(&[[3.0], [4.0]]),
+                arr1(&[5.0, 6.0]),
+                arr1(&[7.0]),
+            ),
+        ]);
+        let parameters = neural_network.get_parameters();
+        assert_eq!(parameters, arr1(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]));
+        let parameters = parameters.map(|parameter| parameter * 2.0);
+        neural_network.set_parameters(&parameters.view()).unwrap();
+        assert_eq!(neural_network.get_parameters(), parameters);
+        assert_eq!(neural_network.layers()[0].outputs_weights(), arr2(&[[6.0], [8.0]]));
+    }
+
+    #[test]
+    fn backward_propagation_gradients() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, Sigmoid, &mut rng)
+            .output(4, 1, Sigmoid, &mut rng);
+        let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+        assert!(
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .is_ok()
+        );
+        let dims = [((2, 3), (3, 3)), ((3, 4), (4, 1))];
+        for (layer, &(inputs_dim, outputs_dim)) in neural_network.layers.iter().zip(dims.iter()) {
+            assert_eq!(layer.cost_d_inputs().dim(), inputs_dim);
+            assert_eq!(layer.cost_d_outputs().dim(), outputs_dim);
+        }
+
+        let wrong_outputs = arr2(&[[0.0, 1.0]]);
+        assert!(
+            neural_network
+                .backward_propagation(inputs.view(), wrong_outputs.view())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, PReLU::default(), &mut rng)
+            .output(4, 1, Sigmoid, &mut rng);
+        let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+        for _ in 0..10 {
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            neural_network.update(0.5);
+        }
+
+        let path = env::temp_dir().join("rust-neuralnet-json_round_trip.json");
+        neural_network.save_json(&path).unwrap();
+        let mut loaded_network = NeuralNetwork::load_json(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let outputs = neural_network.predict(inputs.view()).unwrap();
+        let loaded_outputs = loaded_network.predict(inputs.view()).unwrap();
+        assert_eq!(outputs, loaded_outputs);
+    }
+
+    #[test]
+    fn predict_denormalized() {
+        let targets = arr2(&[[100.0], [200.0], [300.0]]);
+        let mut scaler = StandardScaler::default();
+        scaler.fit(&targets);
+        // the network maps each input to the standardized target
+        let standardized_slope = (1.5 as Float).sqrt();
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new(Identity, arr2(&[[1.0]]), arr2(&[[standardized_slope]])),
+        ]);
+        let inputs = arr2(&[[-1.0], [0.0], [1.0]]);
+        assert_eq!(
+            neural_network.predict_denormalized(inputs.view()).unwrap(),
+            neural_network.predict(inputs.view()).unwrap()
+        );
+
+        neural_network.set_target_scaler(scaler.clone()).unwrap();
+        let predictions = neural_network.predict_denormalized(inputs.view()).unwrap();
+        for (&prediction, &target) in predictions.iter().zip(targets.iter()) {
+            assert_relative_eq!(prediction, target, epsilon = 1e-3);
+        }
+
+        // the scaler is saved along with the model
+        let path = env::temp_dir().join("rust-neuralnet-predict_denormalized.json");
+        neural_network.save_json(&path).unwrap();
+        let mut loaded_network = NeuralNetwork::load_json(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded_network.target_scaler().is_some());
+        assert_eq!(
+            loaded_network.predict_denormalized(inputs.view()).unwrap(),
+            predictions
+        );
+
+        let mut two_columns = MinMaxScaler::default();
+        two_columns.fit(&arr2(&[[0.0, 1.0], [1.0, 2.0]]));
+        assert_eq!(
+            neural_network.set_target_scaler(two_columns),
+            Err(NetworkError::DimensionMismatch {
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn from_config() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let config = NetworkConfig {
+            input_dim: 2,
+            layers: vec![
+                LayerSpec {
+                    neurons: 3,
+                    activation: "Sigmoid".to_owned(),
+                    init: "XavierUniform".to_owned(),
+                },
+            ],
+            output_dim: 1,
+        };
+        let mut neural_network = NeuralNetwork::from_config(&config, &mut rng).unwrap();
+        assert_eq!(neural_network.layers().len(), 1);
+        assert_eq!(
+            neural_network.layers()[0].to_string(),
+            "Layer(inputs=2, neurons=3, outputs=1, activation=Sigmoid)"
+        );
+        let outputs = neural_network
+            .predict(arr2(&[[0.0, 1.0], [1.0, 0.5]]).view())
+            .unwrap();
+        assert_eq!(outputs.dim(), (2, 1));
+        assert!(outputs.iter().all(|&output| output > 0.0 && output < 1.0));
+
+        let json = r#"{
+            "input_dim": 4,
+            "layers": [
+                {"neurons": 8, "activation": "Rectifier", "init": "Auto"},
+                {"neurons": 5, "activation": "TanH", "init": "Uniform"}
+            ],
+            "output_dim": 2
+        }"#;
+        let config: NetworkConfig = serde_json::from_str(json).unwrap();
+        let neural_network = NeuralNetwork::from_config(&config, &mut rng).unwrap();
+        assert_eq!((neural_network.input_dim(), neural_network.output_dim()), (4, 2));
+        assert_eq!(neural_network.layers()[0].dim_outputs(), 8);
+        assert_eq!(neural_network.layers()[1].dim_inputs(), 8);
+
+        let mut unknown_activation = config.clone();
+        unknown_activation.layers[1].activation = "Sigmoïd".to_owned();
+        assert_eq!(
+            NeuralNetwork::from_config(&unknown_activation, &mut rng).err(),
+            Some(NetworkError::UnknownActivation {
+                name: "Sigmoïd".to_owned(),
+            })
+        );
+        let mut unknown_init = config.clone();
+        unknown_init.layers[0].init = "Glorot".to_owned();
+        assert_eq!(
+            NeuralNetwork::from_config(&unknown_init, &mut rng).err(),
+            Some(NetworkError::UnknownWeightInit {
+                name: "Glorot".to_owned(),
+            })
+        );
+        let mut no_layers = config.clone();
+        no_layers.layers.clear();
+        assert_eq!(
+            NeuralNetwork::from_config(&no_layers, &mut rng).err(),
+            Some(NetworkError::NoLayers)
+        );
+    }
+
+    #[test]
+    fn load_json_errors() {
+        let path = env::temp_dir().join("rust-neuralnet-load_json_errors.json");
+        match NeuralNetwork::load_json(&path) {
+            Err(NetworkError::Io { path: error_path, .. }) => assert_eq!(error_path, path),
+            _ => panic!("loading a missing file should fail"),
+        }
+
+        fs::write(&path, "{\"layers\": []}").unwrap();
+        assert_eq!(NeuralNetwork::load_json(&path).err(), Some(NetworkError::NoLayers));
+        fs::write(&path, "{\"layers\": ").unwrap();
+        match NeuralNetwork::load_json(&path) {
+            Err(NetworkError::InvalidModel { .. }) => {}
+            _ => panic!("loading a truncated file should fail"),
         }
-        layer_result
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bin_round_trip() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(50)
+            .layer(100, TanH, &mut rng)
+            .output(10, 10, Sigmoid, &mut rng);
+        let inputs = Array2::from_shape_fn((4, 50), |(i, j)| ((i * j) % 7) as Float / 7.0);
+
+        let bin_path = env::temp_dir().join("rust-neuralnet-bin_round_trip.bin");
+        let json_path = env::temp_dir().join("rust-neuralnet-bin_round_trip.json");
+        neural_network.save_bin(&bin_path).unwrap();
+        neural_network.save_json(&json_path).unwrap();
+        let bin_length = fs::metadata(&bin_path).unwrap().len();
+        let json_length = fs::metadata(&json_path).unwrap().len();
+        assert!(2 * bin_length < json_length);
+
+        let mut loaded_network = NeuralNetwork::load_bin(&bin_path).unwrap();
+        fs::remove_file(&bin_path).unwrap();
+        fs::remove_file(&json_path).unwrap();
+
+        let outputs = neural_network.predict(inputs.view()).unwrap();
+        let loaded_outputs = loaded_network.predict(inputs.view()).unwrap();
+        assert_eq!(outputs, loaded_outputs);
+    }
+
+    #[test]
+    fn load_bin_errors() {
+        let path = env::temp_dir().join("rust-neuralnet-load_bin_errors.bin");
+        fs::write(&path, [1u8, 2, 3]).unwrap();
+        assert!(NeuralNetwork::load_bin(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn predict_returns_owned_outputs() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, TanH, &mut rng)
+            .output(3, 1, Sigmoid, &mut rng);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.0]]);
+        let expected_outputs = arr2(&[[1.0], [1.0]]);
+
+        let predictions = neural_network.predict(inputs.view()).unwrap();
+        neural_network
+            .backward_propagation(inputs.view(), expected_outputs.view())
+            .unwrap();
+        assert_eq!(predictions.dim(), (2, 1));
+        assert_eq!(predictions, neural_network.run_forward(inputs.view()).unwrap());
+
+        assert_eq!(
+            NeuralNetwork::new(vec![]).predict(inputs.view()),
+            Err(NetworkError::NoLayers)
+        );
+    }
+
+    #[test]
+    fn predict_sample() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, TanH, &mut rng)
+            .output(3, 1, Sigmoid, &mut rng);
+        let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+        let predictions = neural_network.predict(inputs.view()).unwrap();
+
+        let sample = Sample::predict(vec![1.0, 0.0]);
+        let outputs = neural_network.predict_sample(&sample).unwrap();
+        assert_eq!(outputs, predictions.row(2));
+
+        let sample = Sample::predict(vec![1.0, 0.0, 1.0]);
+        assert_eq!(
+            neural_network.predict_sample(&sample),
+            Err(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn loss() {
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new(Identity, arr2(&[[1.0], [2.0]]), arr2(&[[1.0, -1.0]])),
+        ]);
+        let inputs = arr2(&[[1.0, 1.0], [2.0, 0.0]]);
+        // outputs: [[3.0, -3.0], [2.0, -2.0]]
+        let expected_outputs = arr2(&[[3.0, -1.0], [0.0, -2.0]]);
+        let loss = neural_network
+            .loss(inputs.view(), expected_outputs.view())
+            .unwrap();
+        assert_relative_eq!(loss, 0.5 * (4.0 + 4.0) / 2.0);
+
+        let wrong_outputs = arr2(&[[3.0], [0.0]]);
+        assert_eq!(
+            neural_network.loss(inputs.view(), wrong_outputs.view()),
+            Err(NetworkError::DimensionMismatch {
+                expected: 2,
+                got: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate() {
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new(Identity, arr2(&[[1.0, 0.0], [0.0, 1.0]]), arr2(&[[0.5], [0.5]])),
+        ]);
+        let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+        // outputs: [0.0, 0.5, 0.5, 1.0]
+        let mse = neural_network
+            .evaluate(inputs.view(), expected_outputs.view())
+            .unwrap();
+        assert_relative_eq!(mse, (0.0 + 0.25 + 0.25 + 1.0) / 4.0);
+
+        let wrong_outputs = arr2(&[[0.0, 1.0]]);
+        assert!(
+            neural_network
+                .evaluate(inputs.view(), wrong_outputs.view())
+                .is_err()
+        );
+        let no_inputs: Array2<Float> = Array2::zeros((0, 2));
+        let no_outputs: Array2<Float> = Array2::zeros((0, 1));
+        assert_eq!(
+            neural_network.evaluate(no_inputs.view(), no_outputs.view()),
+            Err(NetworkError::EmptyDataset)
+        );
+    }
+
+    #[test]
+    fn clip_gradients() {
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new(Identity, arr2(&[[1.0, 2.0], [3.0, -1.0]]), arr2(&[[1.0], [2.0]])),
+            Layer::new(Identity, arr2(&[[2.0]]), arr2(&[[-1.0, 0.5]])),
+        ]);
+        let inputs = arr2(&[[1.0, 2.0], [-1.0, 3.0]]);
+        let expected_outputs = arr2(&[[1e6, -1e6], [-1e6, 1e6]]);
+        let global_norm = |neural_network: &NeuralNetwork| {
+            neural_network
+                .layers
+                .iter()
+                .map(|layer| {
+                    let squared_sum = |d: ArrayView2<Float>| d.mapv(|d| d * d).scalar_sum();
+                    squared_sum(layer.cost_d_inputs()) + squared_sum(layer.cost_d_outputs())
+                })
+                .sum::<Float>()
+                .sqrt()
+        };
+
+        neural_network
+            .backward_propagation(inputs.view(), expected_outputs.view())
+            .unwrap();
+        let norm = global_norm(&neural_network);
+        assert!(norm > 1e6);
+        assert_relative_eq!(neural_network.clip_gradients(5.0), norm);
+        assert_relative_eq!(global_norm(&neural_network), 5.0, epsilon = 1e-9);
+
+        // gradients within the limit are left untouched
+        let gradients = neural_network.layers[0].cost_d_inputs().to_owned();
+        assert_relative_eq!(neural_network.clip_gradients(10.0), 5.0, epsilon = 1e-9);
+        assert_eq!(neural_network.layers[0].cost_d_inputs(), gradients);
+    }
+
+    #[test]
+    fn input_and_output_dims() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let neural_network = NeuralNetworkBuilder::with_inputs(3)
+            .layer(5, TanH, &mut rng)
+            .layer(4, TanH, &mut rng)
+            .output(6, 2, Sigmoid, &mut rng);
+        assert_eq!(neural_network.input_dim(), 3);
+        assert_eq!(neural_network.output_dim(), 2);
+
+        let neural_network = NeuralNetwork::new(Vec::new());
+        assert_eq!(neural_network.input_dim(), 0);
+        assert_eq!(neural_network.output_dim(), 0);
+    }
+
+    #[test]
+    fn num_parameters() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        // 2 inputs, 3 neurons and 1 output: 2 * 3 + 3 * 1 weights
+        let neural_network = NeuralNetworkBuilder::with_inputs(2).output(3, 1, Sigmoid, &mut rng);
+        assert_eq!(neural_network.num_parameters(), 9);
+        assert_eq!(neural_network.layers()[0].num_parameters(), 9);
+
+        let mut layers = neural_network.layers;
+        layers.push(Layer::new_with_bias(
+            Identity,
+            Array2::zeros((1, 2)),
+            Array2::zeros((2, 4)),
+            Array1::zeros(2),
+            Array1::zeros(4),
+        ));
+        let neural_network = NeuralNetwork::new(layers);
+        // 1 * 2 + 2 * 4 weights and 2 + 4 biases
+        assert_eq!(neural_network.num_parameters(), 9 + 10 + 6);
+        assert_eq!(neural_network.get_parameters().len(), neural_network.num_parameters());
+    }
+
+    #[test]
+    fn summary() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut layers = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, TanH, &mut rng)
+            .output(4, 1, Sigmoid, &mut rng)
+            .layers;
+        layers.push(Layer::new_with_bias(
+            PReLU::default(),
+            Array2::zeros((1, 2)),
+            Array2::zeros((2, 1)),
+            Array1::zeros(2),
+            Array1::zeros(1),
+        ));
+        let summary = NeuralNetwork::new(layers).summary();
+        let lines: Vec<_> = summary.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains("TanH"));
+        assert!(lines[1].ends_with(" 15"));
+        assert!(lines[2].contains("Sigmoid"));
+        assert!(lines[2].ends_with(" 16"));
+        assert!(lines[3].contains("PReLU"));
+        // 2 + 2 weights, 2 + 1 biases and the PReLU's alpha
+        assert!(lines[3].ends_with(" 8"));
+        assert_eq!(lines[4], "Total parameters: 39");
+    }
+
+    #[test]
+    fn clone() {
+        let mut rng = StdRng::from_seed(&[25usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .layer(3, PReLU::default(), &mut rng)
+            .output(3, 1, Sigmoid, &mut rng);
+        let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+        let expected_outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+        for _ in 0..10 {
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            neural_network.update(0.5);
+        }
+
+        let mut clone = neural_network.clone();
+        let outputs = neural_network.predict(inputs.view()).unwrap();
+        assert_eq!(clone.predict(inputs.view()).unwrap(), outputs);
+
+        // the copy is independent from the original
+        clone
+            .backward_propagation(inputs.view(), expected_outputs.view())
+            .unwrap();
+        clone.update(0.5);
+        assert!(clone.predict(inputs.view()).unwrap() != outputs);
+        assert_eq!(neural_network.predict(inputs.view()).unwrap(), outputs);
+    }
+
+    #[test]
+    fn training_mode() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 7 + j * 3) % 5) as Float / 5.0 - 0.4;
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::with_init_fn(TanH, 2, 32, 3, init_fn).with_dropout(0.5),
+            Layer::with_init_fn(Sigmoid, 3, 4, 1, init_fn),
+        ]);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.5]]);
+
+        let predictions = neural_network.predict(inputs.view()).unwrap();
+        assert_eq!(predictions, neural_network.predict(inputs.view()).unwrap());
+
+        neural_network.set_training(true);
+        assert_ne!(
+            neural_network.predict(inputs.view()).unwrap(),
+            neural_network.predict(inputs.view()).unwrap()
+        );
+
+        neural_network.set_training(false);
+        assert_eq!(predictions, neural_network.predict(inputs.view()).unwrap());
+    }
+
+    #[test]
+    fn frozen_layers() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 7 + j * 3) % 5) as Float / 5.0 - 0.4;
+        let mut frozen_layer = Layer::with_init_fn(TanH, 2, 4, 3, init_fn);
+        frozen_layer.set_frozen(true);
+        let mut neural_network = NeuralNetwork::new(vec![
+            frozen_layer,
+            Layer::with_init_fn(Sigmoid, 3, 4, 1, init_fn),
+        ]);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.5], [0.5, -1.0]]);
+        let expected_outputs = arr2(&[[0.2], [0.9], [0.5]]);
+        let frozen_weights = |network: &NeuralNetwork| {
+            let layer = &network.layers()[0];
+            (layer.inputs_weights().to_owned(), layer.outputs_weights().to_owned())
+        };
+        let initial_frozen = frozen_weights(&neural_network);
+        let initial_head = neural_network.layers()[1].inputs_weights().to_owned();
+
+        let mut optimizer = Adam::new(0.1);
+        for _ in 0..5 {
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            // the error still propagates through the frozen layer
+            assert!(neural_network.layers()[0].cost_d_inputs().iter().any(|&d| d != 0.0));
+            neural_network.update(0.5);
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            optimizer.begin_update();
+            let mut indices = Vec::new();
+            neural_network.update_with(0.1, |index, weights, gradients| {
+                indices.push(index);
+                optimizer.step(weights, gradients)
+            });
+            assert_eq!(indices, vec![2, 3]);
+        }
+        assert!(neural_network.layers()[0].frozen());
+        assert_eq!(frozen_weights(&neural_network), initial_frozen);
+        assert_ne!(neural_network.layers()[1].inputs_weights(), initial_head);
+    }
+
+    #[test]
+    fn train_one() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 7 + j * 3) % 5) as Float / 5.0 - 0.4;
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::with_init_fn(TanH, 2, 4, 3, init_fn),
+            Layer::with_init_fn(Sigmoid, 3, 4, 1, init_fn),
+        ]);
+        let inputs = arr2(&[[0.5, -1.0]]);
+        let expected_outputs = arr2(&[[0.9]]);
+        let initial_loss = neural_network
+            .loss(inputs.view(), expected_outputs.view())
+            .unwrap();
+        let mut losses = Vec::new();
+        for _ in 0..20 {
+            let loss = neural_network
+                .train_one(inputs.view(), expected_outputs.view(), 0.5)
+                .unwrap();
+            losses.push(loss);
+        }
+        assert_relative_eq!(losses[0], initial_loss);
+        assert!(losses.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", losses);
+        assert!(!neural_network.layers()[0].training());
+
+        // the previous mode of each layer is restored
+        neural_network.layers_mut()[1].set_training(true);
+        neural_network
+            .train_one(inputs.view(), expected_outputs.view(), 0.5)
+            .unwrap();
+        assert!(!neural_network.layers()[0].training());
+        assert!(neural_network.layers()[1].training());
+
+        let wrong_outputs = arr2(&[[0.9, 0.1]]);
+        assert!(
+            neural_network
+                .train_one(inputs.view(), wrong_outputs.view(), 0.5)
+                .is_err()
+        );
+        assert!(neural_network.layers()[1].training());
+    }
+
+    #[test]
+    fn forward_with_activations() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 7 + j * 3) % 5) as Float / 5.0 - 0.4;
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::with_init_fn(TanH, 2, 4, 3, init_fn),
+            Layer::with_init_fn(Sigmoid, 3, 5, 2, init_fn),
+        ]);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.5], [0.5, -1.0], [-0.5, 0.0]]);
+        let activations = neural_network.forward_with_activations(inputs.view()).unwrap();
+        assert_eq!(activations.len(), 2);
+        assert_eq!(activations[0].dim(), (4, 3));
+        assert_eq!(activations[1].dim(), (4, 2));
+        assert_eq!(activations[1], neural_network.predict(inputs.view()).unwrap());
+        let mut first_layer = neural_network.layers()[0].clone();
+        assert_eq!(
+            first_layer.forward_propagation(&inputs.view()).unwrap(),
+            activations[0]
+        );
+
+        assert!(
+            neural_network
+                .forward_with_activations(arr2(&[[1.0]]).view())
+                .is_err()
+        );
+        assert!(
+            NeuralNetwork::new(Vec::new())
+                .forward_with_activations(inputs.view())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn softmax_temperature() {
+        let mut neural_network = NeuralNetworkBuilder::with_seed(2, 104)
+            .layer(4, TanH)
+            .softmax_output(5, 3)
+            .unwrap();
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2], [-0.3, 0.8]]);
+        let max_probabilities = |outputs: &Array2<Float>| -> Vec<Float> {
+            outputs
+                .genrows()
+                .into_iter()
+                .map(|row| row.fold(0.0, |max: Float, &p| max.max(p)))
+                .collect()
+        };
+        let outputs = neural_network.predict(inputs.view()).unwrap();
+        neural_network.set_temperature(1.0);
+        assert_eq!(neural_network.predict(inputs.view()).unwrap(), outputs);
+
+        neural_network.set_temperature(3.0);
+        assert_eq!(neural_network.layers().last().unwrap().temperature(), 3.0);
+        let softened = neural_network.predict(inputs.view()).unwrap();
+        assert_eq!(argmax_rows(&softened.view()), argmax_rows(&outputs.view()));
+        for (softened_max, max) in max_probabilities(&softened)
+            .into_iter()
+            .zip(max_probabilities(&outputs))
+        {
+            assert!(softened_max < max, "{} >= {}", softened_max, max);
+        }
+        for row in softened.genrows() {
+            assert_relative_eq!(row.scalar_sum(), 1.0, epsilon = 1e-6);
+        }
+
+        // the temperature is saved, and folded into the inference network
+        let path = env::temp_dir().join("rust-neuralnet-softmax_temperature.json");
+        neural_network.save_json(&path).unwrap();
+        let mut loaded_network = NeuralNetwork::load_json(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded_network.predict(inputs.view()).unwrap(), softened);
+        let inference_outputs = neural_network
+            .to_inference()
+            .forward(inputs.view())
+            .unwrap();
+        for (&output, &softened_output) in inference_outputs.iter().zip(softened.iter()) {
+            assert_relative_eq!(output, softened_output, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn temperature_gradients() {
+        let mut neural_network = NeuralNetworkBuilder::with_seed(2, 104)
+            .layer(4, TanH)
+            .output(5, 2, Sigmoid);
+        neural_network.set_temperature(2.5);
+        let inputs = arr2(&[[0.5, -1.0], [1.0, 0.2], [-0.3, 0.8]]);
+        let expected_outputs = arr2(&[[0.0, 1.0], [1.0, 0.5], [0.2, 0.0]]);
+        let (step, tolerance) = gradient_check_tolerance();
+        let error = gradient_check(
+            &mut neural_network,
+            inputs.view(),
+            expected_outputs.view(),
+            step,
+        ).unwrap();
+        assert!(error < tolerance, "max relative error : {}", error);
+    }
+
+    #[test]
+    fn reinitialize() {
+        let mut rng = StdRng::from_seed(&[106usize][..]);
+        let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+            .init(WeightInit::HeUniform)
+            .layer(3, TanH, &mut rng)
+            .output(4, 1, Sigmoid, &mut rng);
+        let mut fixed_layer = Layer::with_init_fn(PReLU::default(), 1, 2, 1, |_| 0.5);
+        fixed_layer.set_activation_parameters(&[0.1]);
+        let mut layers = neural_network.layers().to_vec();
+        layers.push(fixed_layer);
+        neural_network = NeuralNetwork::new(layers);
+        assert_eq!(
+            neural_network.layers()[0].weight_init(),
+            Some(WeightInit::HeUniform)
+        );
+        assert_eq!(neural_network.layers()[2].weight_init(), None);
+        let topology = |network: &NeuralNetwork| -> Vec<String> {
+            network.layers().iter().map(Layer::to_string).collect()
+        };
+        let initial_topology = topology(&neural_network);
+        let initial_parameters = neural_network.get_parameters();
+
+        neural_network.reinitialize(&mut rng);
+        assert_eq!(topology(&neural_network), initial_topology);
+        let parameters = neural_network.get_parameters();
+        assert_eq!(parameters.len(), initial_parameters.len());
+        assert!(
+            parameters
+                .iter()
+                .zip(initial_parameters.iter())
+                .all(|(parameter, initial)| parameter != initial)
+        );
+        assert_eq!(
+            neural_network.layers()[0].weight_init(),
+            Some(WeightInit::HeUniform)
+        );
+        // the learned slope of the PReLU is back to its default
+        assert_eq!(neural_network.layers()[2].activation_parameters(), vec![0.25]);
+    }
+
+    #[test]
+    fn loss_and_gradients_are_averaged_over_samples() {
+        let init_fn = |(i, j): (usize, usize)| ((i * 7 + j * 3) % 5) as Float / 5.0 - 0.4;
+        let loss_and_gradients = |inputs: Array2<Float>, expected_outputs: Array2<Float>| {
+            let mut neural_network = NeuralNetwork::new(vec![
+                Layer::with_init_fn(TanH, 2, 4, 3, init_fn).with_l2(0.1),
+                Layer::with_init_fn(Sigmoid, 3, 4, 1, init_fn).with_l1(0.05),
+            ]);
+            neural_network
+                .backward_propagation(inputs.view(), expected_outputs.view())
+                .unwrap();
+            let loss = neural_network
+                .loss(inputs.view(), expected_outputs.view())
+                .unwrap();
+            let gradients: Vec<Float> = neural_network
+                .layers()
+                .iter()
+                .flat_map(|layer| {
+                    let mut gradients = layer.cost_d_inputs().to_owned().into_raw_vec();
+                    gradients.extend(layer.cost_d_outputs().iter());
+                    gradients
+                })
+                .collect();
+            (loss, gradients)
+        };
+
+        let inputs = [[0.0, 1.0], [1.0, 0.5], [0.5, -1.0]];
+        let expected_outputs = [[0.2], [0.9], [0.5]];
+        let (loss, gradients) = loss_and_gradients(arr2(&inputs), arr2(&expected_outputs));
+        // the same batch, 10 times over
+        let (duplicated_loss, duplicated_gradients) = loss_and_gradients(
+            arr2(&[inputs; 10].concat()),
+            arr2(&[expected_outputs; 10].concat()),
+        );
+        assert_relative_eq!(loss, duplicated_loss, epsilon = 1e-6);
+        for (d, duplicated_d) in gradients.iter().zip(&duplicated_gradients) {
+            assert_relative_eq!(d, duplicated_d, epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn run_forward_without_layers() {
+        let mut neural_network = NeuralNetwork::new(Vec::new());
+        let inputs = Array2::zeros((1, 2));
+        assert_eq!(
+            neural_network.run_forward(inputs.view()),
+            Err(NetworkError::NoLayers)
+        );
     }
 }
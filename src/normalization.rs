@@ -0,0 +1,247 @@
+//! Normalization of the activations within a Neural Network.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, NdFloat};
+
+use super::{NetworkError, NetworkResult};
+
+/// Batch normalization standardizes each feature (column) of its
+/// ([samples] * [features]) inputs over the samples, then scales and shifts
+/// it by the learnable `gamma` and `beta`, which makes deeper networks
+/// faster and more stable to train.
+///
+/// normalized = (inputs - mean) / sqrt(variance + epsilon)
+///
+/// outputs = gamma .* normalized + beta
+///
+/// In training mode, the mean and variance are the ones of the batch, and
+/// update the running statistics:
+///
+/// running = momentum * running + (1 - momentum) * batch
+///
+/// In inference mode, the running statistics are used instead.
+///
+/// A ```layer::Layer``` can normalize the weighted sums of its neurons with
+/// ```Layer::with_batch_norm```.
+#[derive(Clone, Debug)]
+pub struct BatchNorm<F: NdFloat> {
+    gamma: Array1<F>,
+    beta: Array1<F>,
+    running_mean: Array1<F>,
+    running_variance: Array1<F>,
+    momentum: F,
+    epsilon: F,
+    // cached results
+    /// Were the statistics of the batch used by the last forward propagation?
+    batch_statistics: bool,
+    normalized: Array2<F>,
+    inverse_std: Array1<F>,
+    cost_d_gamma: Array1<F>,
+    cost_d_beta: Array1<F>,
+}
+
+impl<F: NdFloat> BatchNorm<F> {
+    /// Create a batch normalization of `features` features, with gamma set
+    /// to 1, beta to 0 and a running statistics momentum of 0.9.
+    pub fn new(features: usize) -> Self {
+        BatchNorm {
+            gamma: Array1::from_elem(features, F::one()),
+            beta: Array1::zeros(features),
+            running_mean: Array1::zeros(features),
+            running_variance: Array1::from_elem(features, F::one()),
+            momentum: F::from(0.9).unwrap(),
+            epsilon: F::from(1e-5).unwrap(),
+            batch_statistics: false,
+            normalized: Array2::zeros((0, 0)),
+            inverse_std: Array1::zeros(0),
+            cost_d_gamma: Array1::zeros(features),
+            cost_d_beta: Array1::zeros(features),
+        }
+    }
+
+    /// Set the momentum (within [0, 1[) of the running statistics: the
+    /// larger, the slower they follow the statistics of the batches.
+    pub fn momentum(mut self, momentum: F) -> Self {
+        self.momentum = momentum;
+        self
+    }
+
+    /// Reset gamma, beta and the running statistics to their initial values.
+    pub(crate) fn reset(&mut self) {
+        *self = BatchNorm::new(self.features()).momentum(self.momentum);
+    }
+
+    /// Get the number of normalized features.
+    pub fn features(&self) -> usize {
+        self.gamma.len()
+    }
+
+    /// Get the learnable scale of each feature.
+    pub fn gamma(&self) -> ArrayView1<F> {
+        self.gamma.view()
+    }
+
+    /// Get the learnable shift of each feature.
+    pub fn beta(&self) -> ArrayView1<F> {
+        self.beta.view()
+    }
+
+    /// Get the running mean of each feature, used in inference mode.
+    pub fn running_mean(&self) -> ArrayView1<F> {
+        self.running_mean.view()
+    }
+
+    /// Get the running variance of each feature, used in inference mode.
+    pub fn running_variance(&self) -> ArrayView1<F> {
+        self.running_variance.view()
+    }
+
+    /// Normalize `inputs` with the statistics of the batch in `training`
+    /// mode (updating the running statistics), or with the running ones
+    /// otherwise.
+    pub fn forward_propagation(
+        &mut self,
+        inputs: &ArrayView2<F>,
+        training: bool,
+    ) -> NetworkResult<Array2<F>> {
+        if inputs.cols() != self.features() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: self.features(),
+                got: inputs.cols(),
+            });
+        }
+        let (mean, variance) = if training && inputs.rows() > 0 {
+            let samples = F::from(inputs.rows()).unwrap();
+            let mean = inputs.sum_axis(Axis(0)) / samples;
+            let variance = (inputs - &mean).mapv(|d| d * d).sum_axis(Axis(0)) / samples;
+            let remaining = F::one() - self.momentum;
+            self.running_mean = &self.running_mean * self.momentum + &(&mean * remaining);
+            self.running_variance =
+                &self.running_variance * self.momentum + &(&variance * remaining);
+            (mean, variance)
+        } else {
+            (self.running_mean.clone(), self.running_variance.clone())
+        };
+        let epsilon = self.epsilon;
+        self.batch_statistics = training;
+        self.inverse_std = variance.mapv(|v| F::one() / (v + epsilon).sqrt());
+        self.normalized = (inputs - &mean) * &self.inverse_std;
+        Ok(&self.normalized * &self.gamma + &self.beta)
+    }
+
+    /// Get the (scale, shift) of each feature such that, in inference mode,
+    /// outputs = inputs .* scale + shift.
+    ///
+    /// scale = gamma / sqrt(running_variance + epsilon)
+    ///
+    /// shift = beta - running_mean .* scale
+    pub(crate) fn inference_affine(&self) -> (Array1<F>, Array1<F>) {
+        let epsilon = self.epsilon;
+        let scale = self.running_variance
+            .mapv(|v| F::one() / (v + epsilon).sqrt()) * &self.gamma;
+        let shift = &self.beta - &(&self.running_mean * &scale);
+        (scale, shift)
+    }
+
+    /// Compute and store the gradients of the cost with respect to gamma and
+    /// beta, given its partial derivative `outputs_error` with respect to
+    /// the outputs of the last forward propagation, and return its partial
+    /// derivative with respect to the inputs.
+    ///
+    /// With the statistics of the batch, which depend on every sample:
+    ///
+    /// d(cost)/d(inputs) = inverse_std / [samples] .* ([samples] * e
+    ///     - sum(e) - normalized .* sum(e .* normalized))
+    ///
+    /// where e = outputs_error .* gamma and the sums are over the samples.
+    pub fn backward_propagation(&mut self, outputs_error: &ArrayView2<F>) -> Array2<F> {
+        self.cost_d_beta = outputs_error.sum_axis(Axis(0));
+        self.cost_d_gamma = (outputs_error * &self.normalized).sum_axis(Axis(0));
+        let normalized_error = outputs_error * &self.gamma;
+        if !self.batch_statistics {
+            return normalized_error * &self.inverse_std;
+        }
+        let samples = F::from(outputs_error.rows()).unwrap();
+        let error_sum = normalized_error.sum_axis(Axis(0));
+        let weighted_error_sum = (&normalized_error * &self.normalized).sum_axis(Axis(0));
+        let inputs_error =
+            normalized_error * samples - &error_sum - &(&self.normalized * &weighted_error_sum);
+        inputs_error * &(&self.inverse_std / samples)
+    }
+
+    /// Update gamma and beta with one step of gradient descent, using the
+    /// gradients computed by the last backward propagation.
+    pub fn apply_gradients(&mut self, learning_rate: F) {
+        self.gamma.scaled_add(-learning_rate, &self.cost_d_gamma);
+        self.beta.scaled_add(-learning_rate, &self.cost_d_beta);
+    }
+
+    /// Scale the gradients computed by the last backward propagation.
+    pub(crate) fn scale_gradients(&mut self, factor: F) {
+        self.cost_d_gamma.mapv_inplace(|d| d * factor);
+        self.cost_d_beta.mapv_inplace(|d| d * factor);
+    }
+
+    /// Replace the gradients, for instance by ones accumulated over several
+    /// backward propagations.
+    pub(crate) fn set_gradients(&mut self, cost_d_gamma: &Array1<F>, cost_d_beta: &Array1<F>) {
+        self.cost_d_gamma.assign(cost_d_gamma);
+        self.cost_d_beta.assign(cost_d_beta);
+    }
+
+    /// Get the partial derivative of the cost with respect to gamma.
+    pub fn cost_d_gamma(&self) -> ArrayView1<F> {
+        self.cost_d_gamma.view()
+    }
+
+    /// Get the partial derivative of the cost with respect to beta.
+    pub fn cost_d_beta(&self) -> ArrayView1<F> {
+        self.cost_d_beta.view()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+
+    use Float;
+    use super::*;
+
+    #[test]
+    fn training_outputs_are_standardized() {
+        let inputs = arr2(&[[1.0, -10.0], [2.0, 0.0], [4.0, 30.0], [9.0, 20.0]]);
+        let mut batch_norm = BatchNorm::new(2);
+        let outputs: Array2<Float> = batch_norm
+            .forward_propagation(&inputs.view(), true)
+            .unwrap();
+        for feature in outputs.gencolumns() {
+            let mean = feature.scalar_sum() / 4.0;
+            let variance = feature.mapv(|v| (v - mean) * (v - mean)).scalar_sum() / 4.0;
+            assert_relative_eq!(mean, 0.0, epsilon = 1e-6);
+            assert_relative_eq!(variance, 1.0, epsilon = 1e-4);
+        }
+        // running statistics: 0.1 of the batch's
+        assert_relative_eq!(batch_norm.running_mean()[0], 0.1 * 4.0, epsilon = 1e-6);
+        assert_relative_eq!(batch_norm.running_variance()[0], 0.9 + 0.1 * 9.5, epsilon = 1e-6);
+
+        assert!(
+            batch_norm
+                .forward_propagation(&arr2(&[[1.0]]).view(), true)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn inference_uses_running_statistics() {
+        let mut batch_norm = BatchNorm::new(1).momentum(0.0);
+        let inputs = arr2(&[[1.0], [3.0]]);
+        batch_norm.forward_propagation(&inputs.view(), true).unwrap();
+        assert_eq!(batch_norm.running_mean()[0], 2.0);
+        assert_eq!(batch_norm.running_variance()[0], 1.0);
+        let outputs: Array2<Float> = batch_norm
+            .forward_propagation(&arr2(&[[4.0]]).view(), false)
+            .unwrap();
+        assert_relative_eq!(outputs[[0, 0]], 2.0, epsilon = 1e-4);
+        // the running statistics are left untouched
+        assert_eq!(batch_norm.running_mean()[0], 2.0);
+    }
+}
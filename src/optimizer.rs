@@ -0,0 +1,465 @@
+use std::f64::consts::PI;
+
+use ndarray::{Array2, ArrayView2, Zip};
+
+use super::Float;
+
+/// An optimizer updates the weights of an Artificial Neural Network from the
+/// gradients of its cost function.
+///
+/// During each update, ```step``` is called once for every weights matrix of
+/// the network, always in the same order: optimizers keeping a state for
+/// each weights matrix can thus identify them by their position.
+pub trait Optimizer {
+    /// Get the learning rate, also used to update the biases and the
+    /// activation functions' parameters with plain gradient descent.
+    fn learning_rate(&self) -> Float;
+
+    /// Set the learning rate.
+    fn set_learning_rate(&mut self, learning_rate: Float);
+
+    /// Signal the beginning of a new update of the whole network.
+    fn begin_update(&mut self) {}
+
+    /// Update `weights` from their `gradients`.
+    fn step(&mut self, weights: &mut Array2<Float>, gradients: &ArrayView2<Float>);
+}
+
+/// Per weights matrix states of an ```Optimizer```, identified by their
+/// position within each update.
+#[derive(Clone, Debug, Default)]
+struct OptimizerStates<S> {
+    states: Vec<S>,
+    current: usize,
+}
+
+impl<S> OptimizerStates<S> {
+    fn begin_update(&mut self) {
+        self.current = 0;
+    }
+
+    /// Get the state of the next weights matrix, initializing it with `init`
+    /// during the first update.
+    fn next<I>(&mut self, init: I) -> &mut S
+    where
+        I: FnOnce() -> S,
+    {
+        if self.current == self.states.len() {
+            self.states.push(init());
+        }
+        self.current += 1;
+        &mut self.states[self.current - 1]
+    }
+}
+
+/// Gradient descent, with optional momentum.
+///
+/// velocity = momentum * velocity - learning_rate * gradient
+///
+/// weights += velocity
+///
+/// Without momentum (the default), this reduces to:
+///
+/// weights -= learning_rate * gradient
+#[derive(Clone, Debug)]
+pub struct GradientDescent {
+    pub learning_rate: Float,
+    /// Fraction of the previous update carried on to the current one, within
+    /// [0, 1[.
+    pub momentum: Float,
+    velocities: OptimizerStates<Array2<Float>>,
+}
+
+impl GradientDescent {
+    pub fn new(learning_rate: Float) -> Self {
+        GradientDescent::with_momentum(learning_rate, 0.0)
+    }
+
+    pub fn with_momentum(learning_rate: Float, momentum: Float) -> Self {
+        GradientDescent {
+            learning_rate,
+            momentum,
+            velocities: OptimizerStates::default(),
+        }
+    }
+}
+
+impl Optimizer for GradientDescent {
+    fn learning_rate(&self) -> Float {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: Float) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn begin_update(&mut self) {
+        self.velocities.begin_update();
+    }
+
+    fn step(&mut self, weights: &mut Array2<Float>, gradients: &ArrayView2<Float>) {
+        let (learning_rate, momentum) = (self.learning_rate, self.momentum);
+        if momentum == 0.0 {
+            weights.scaled_add(-learning_rate, gradients);
+            return;
+        }
+        let velocity = self.velocities.next(|| Array2::zeros(weights.dim()));
+        velocity.mapv_inplace(|v| momentum * v);
+        velocity.scaled_add(-learning_rate, gradients);
+        *weights += &*velocity;
+    }
+}
+
+/// The Adam (adaptive moment estimation) optimizer scales the step of each
+/// weight from running averages of its gradient (first moment) and squared
+/// gradient (second moment).
+///
+/// m = beta1 * m + (1 - beta1) * gradient
+///
+/// v = beta2 * v + (1 - beta2) * gradient ^ 2
+///
+/// weights -= learning_rate * m' / (sqrt(v') + epsilon)
+///
+/// with m' = m / (1 - beta1 ^ t) and v' = v / (1 - beta2 ^ t) the moments
+/// corrected for their initialization bias at the update n°t.
+#[derive(Clone, Debug)]
+pub struct Adam {
+    pub learning_rate: Float,
+    pub beta1: Float,
+    pub beta2: Float,
+    pub epsilon: Float,
+    moments: OptimizerStates<(Array2<Float>, Array2<Float>)>,
+    updates: i32,
+}
+
+impl Adam {
+    pub fn new(learning_rate: Float) -> Self {
+        Adam {
+            learning_rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            moments: OptimizerStates::default(),
+            updates: 0,
+        }
+    }
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Adam::new(0.001)
+    }
+}
+
+impl Optimizer for Adam {
+    fn learning_rate(&self) -> Float {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: Float) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn begin_update(&mut self) {
+        self.moments.begin_update();
+        self.updates += 1;
+    }
+
+    fn step(&mut self, weights: &mut Array2<Float>, gradients: &ArrayView2<Float>) {
+        let (beta1, beta2, epsilon) = (self.beta1, self.beta2, self.epsilon);
+        let first_correction = 1.0 - beta1.powi(self.updates.max(1));
+        let second_correction = 1.0 - beta2.powi(self.updates.max(1));
+        let learning_rate = self.learning_rate;
+        let dim = weights.dim();
+        let &mut (ref mut m, ref mut v) = self.moments
+            .next(|| (Array2::zeros(dim), Array2::zeros(dim)));
+        Zip::from(weights)
+            .and(m)
+            .and(v)
+            .and(gradients)
+            .apply(|w, m, v, &g| {
+                *m = beta1 * *m + (1.0 - beta1) * g;
+                *v = beta2 * *v + (1.0 - beta2) * g * g;
+                let (m, v) = (*m / first_correction, *v / second_correction);
+                *w -= learning_rate * m / (v.sqrt() + epsilon);
+            });
+    }
+}
+
+/// The RMSProp optimizer divides the step of each weight by a running
+/// average of the magnitude of its recent gradients.
+///
+/// s = decay * s + (1 - decay) * gradient ^ 2
+///
+/// weights -= learning_rate * gradient / (sqrt(s) + epsilon)
+#[derive(Clone, Debug)]
+pub struct RmsProp {
+    pub learning_rate: Float,
+    pub decay: Float,
+    pub epsilon: Float,
+    squared_gradients: OptimizerStates<Array2<Float>>,
+}
+
+impl RmsProp {
+    pub fn new(learning_rate: Float) -> Self {
+        RmsProp {
+            learning_rate,
+            decay: 0.9,
+            epsilon: 1e-8,
+            squared_gradients: OptimizerStates::default(),
+        }
+    }
+}
+
+impl Default for RmsProp {
+    fn default() -> Self {
+        RmsProp::new(0.001)
+    }
+}
+
+impl Optimizer for RmsProp {
+    fn learning_rate(&self) -> Float {
+        self.learning_rate
+    }
+
+    fn set_learning_rate(&mut self, learning_rate: Float) {
+        self.learning_rate = learning_rate;
+    }
+
+    fn begin_update(&mut self) {
+        self.squared_gradients.begin_update();
+    }
+
+    fn step(&mut self, weights: &mut Array2<Float>, gradients: &ArrayView2<Float>) {
+        let (learning_rate, decay, epsilon) = (self.learning_rate, self.decay, self.epsilon);
+        let dim = weights.dim();
+        let squared_gradients = self.squared_gradients.next(|| Array2::zeros(dim));
+        Zip::from(weights)
+            .and(squared_gradients)
+            .and(gradients)
+            .apply(|w, s, &g| {
+                *s = decay * *s + (1.0 - decay) * g * g;
+                *w -= learning_rate * g / (s.sqrt() + epsilon);
+            });
+    }
+}
+
+/// A learning rate schedule gives the learning rate to use at each epoch.
+pub trait LrSchedule {
+    fn lr(&self, epoch: u32) -> Float;
+}
+
+/// Multiply the learning rate by `gamma` every `step_size` (strictly
+/// positive) epochs.
+///
+/// lr = initial * gamma ^ floor(epoch / step_size)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepDecay {
+    pub initial: Float,
+    pub gamma: Float,
+    step_size: u32,
+}
+
+impl StepDecay {
+    /// Returns ```None``` if `step_size` is zero.
+    pub fn new(initial: Float, gamma: Float, step_size: u32) -> Option<Self> {
+        if step_size == 0 {
+            return None;
+        }
+        Some(StepDecay {
+            initial,
+            gamma,
+            step_size,
+        })
+    }
+
+    pub fn step_size(&self) -> u32 {
+        self.step_size
+    }
+}
+
+impl LrSchedule for StepDecay {
+    fn lr(&self, epoch: u32) -> Float {
+        self.initial * self.gamma.powi((epoch / self.step_size) as i32)
+    }
+}
+
+/// Multiply the learning rate by `gamma` at every epoch.
+///
+/// lr = initial * gamma ^ epoch
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExponentialDecay {
+    pub initial: Float,
+    pub gamma: Float,
+}
+
+impl LrSchedule for ExponentialDecay {
+    fn lr(&self, epoch: u32) -> Float {
+        self.initial * self.gamma.powi(epoch as i32)
+    }
+}
+
+/// Anneal the learning rate from `initial` down to `min_lr` along a half
+/// cosine, restarting from `initial` every `period` (strictly positive)
+/// epochs.
+///
+/// lr = min_lr + 0.5 * (initial - min_lr) * (1 + cos(pi * t / period))
+///
+/// where t = epoch % period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CosineAnnealing {
+    pub initial: Float,
+    pub min_lr: Float,
+    period: u32,
+}
+
+impl CosineAnnealing {
+    /// Returns ```None``` if `period` is zero.
+    pub fn new(initial: Float, min_lr: Float, period: u32) -> Option<Self> {
+        if period == 0 {
+            return None;
+        }
+        Some(CosineAnnealing {
+            initial,
+            min_lr,
+            period,
+        })
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+}
+
+impl LrSchedule for CosineAnnealing {
+    fn lr(&self, epoch: u32) -> Float {
+        let t = f64::from(epoch % self.period) / f64::from(self.period);
+        let cosine = (PI * t).cos() as Float;
+        self.min_lr + 0.5 * (self.initial - self.min_lr) * (1.0 + cosine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use super::*;
+
+    #[test]
+    fn gradient_descent() {
+        let mut optimizer = GradientDescent::new(0.5);
+        let mut weights = arr2(&[[1.0, 2.0]]);
+        let gradients = arr2(&[[1.0, -2.0]]);
+        optimizer.begin_update();
+        optimizer.step(&mut weights, &gradients.view());
+        assert_eq!(weights, arr2(&[[0.5, 3.0]]));
+    }
+
+    #[test]
+    fn gradient_descent_momentum() {
+        let mut optimizer = GradientDescent::with_momentum(0.5, 0.5);
+        let (mut weights_1, mut weights_2) = (arr2(&[[1.0]]), arr2(&[[0.0, 0.0]]));
+        let (gradients_1, gradients_2) = (arr2(&[[1.0]]), arr2(&[[2.0, 4.0]]));
+        for _ in 0..2 {
+            optimizer.begin_update();
+            optimizer.step(&mut weights_1, &gradients_1.view());
+            optimizer.step(&mut weights_2, &gradients_2.view());
+        }
+        // velocities: -0.5 then -0.75 ; [-1, -2] then [-1.5, -3]
+        assert_eq!(weights_1, arr2(&[[1.0 - 0.5 - 0.75]]));
+        assert_eq!(weights_2, arr2(&[[-2.5, -5.0]]));
+    }
+
+    #[test]
+    fn adam() {
+        let mut optimizer = Adam::new(0.1);
+        let mut weights = arr2(&[[1.0, 1.0, 1.0]]);
+        let gradients = arr2(&[[0.001, -10.0, 0.0]]);
+        // the bias-corrected first step is (almost) learning_rate * sign(gradient)
+        optimizer.begin_update();
+        optimizer.step(&mut weights, &gradients.view());
+        assert_relative_eq!(weights[[0, 0]], 0.9, epsilon = 1e-4);
+        assert_relative_eq!(weights[[0, 1]], 1.1, epsilon = 1e-4);
+        assert_eq!(weights[[0, 2]], 1.0);
+        // constant gradients keep steps of the same size
+        optimizer.begin_update();
+        optimizer.step(&mut weights, &gradients.view());
+        assert_relative_eq!(weights[[0, 0]], 0.8, epsilon = 1e-4);
+        assert_relative_eq!(weights[[0, 1]], 1.2, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn rms_prop() {
+        let mut optimizer = RmsProp::new(0.01);
+        optimizer.decay = 0.5;
+        let mut weights = arr2(&[[0.0, 0.0]]);
+        let gradients = arr2(&[[2.0, -1.0]]);
+        for _ in 0..3 {
+            optimizer.begin_update();
+            optimizer.step(&mut weights, &gradients.view());
+        }
+        // relative tolerance of a few roundings, whatever the precision
+        let precision = 4.0 * Float::EPSILON;
+        // s = (1 - decay ^ 3) * gradient ^ 2
+        let squared_gradients = optimizer.squared_gradients.states[0].clone();
+        assert_relative_eq!(squared_gradients[[0, 0]], 0.875 * 4.0, max_relative = precision);
+        assert_relative_eq!(squared_gradients[[0, 1]], 0.875 * 1.0, max_relative = precision);
+
+        // without gradients, the accumulator decays geometrically
+        let zeros = arr2(&[[0.0, 0.0]]);
+        for _ in 0..2 {
+            optimizer.begin_update();
+            optimizer.step(&mut weights, &zeros.view());
+        }
+        let decayed = &optimizer.squared_gradients.states[0];
+        assert_relative_eq!(
+            decayed[[0, 0]],
+            0.25 * squared_gradients[[0, 0]],
+            max_relative = precision
+        );
+        assert_relative_eq!(
+            decayed[[0, 1]],
+            0.25 * squared_gradients[[0, 1]],
+            max_relative = precision
+        );
+
+        let expected_step = |s: Float| 0.01 / (s.sqrt() + 1e-8);
+        let expected_weight = -2.0 * (expected_step(2.0) + expected_step(3.0) + expected_step(3.5));
+        assert_relative_eq!(weights[[0, 0]], expected_weight, max_relative = precision);
+    }
+
+    #[test]
+    fn step_decay() {
+        let schedule = StepDecay::new(0.1, 0.5, 10).unwrap();
+        assert_eq!(schedule.step_size(), 10);
+        let lrs: Vec<Float> = [0, 9, 10, 19, 20, 35].iter().map(|&e| schedule.lr(e)).collect();
+        assert_eq!(lrs, vec![0.1, 0.1, 0.05, 0.05, 0.025, 0.0125]);
+        assert!(StepDecay::new(0.1, 0.5, 0).is_none());
+    }
+
+    #[test]
+    fn exponential_decay() {
+        let schedule = ExponentialDecay {
+            initial: 1.0,
+            gamma: 0.9,
+        };
+        assert_eq!(schedule.lr(0), 1.0);
+        assert_relative_eq!(schedule.lr(1), 0.9);
+        assert_relative_eq!(schedule.lr(3), 0.729);
+    }
+
+    #[test]
+    fn cosine_annealing() {
+        let schedule = CosineAnnealing::new(0.1, 0.01, 10).unwrap();
+        assert_eq!(schedule.period(), 10);
+        let lrs: Vec<Float> = (0..10).map(|epoch| schedule.lr(epoch)).collect();
+        assert_relative_eq!(lrs[0], 0.1);
+        // halfway through the period, halfway between initial and min_lr
+        assert_relative_eq!(lrs[5], 0.055, epsilon = 1e-6);
+        assert!(lrs.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(lrs[9] - 0.01 < 0.003);
+        // warm restarts
+        assert_relative_eq!(schedule.lr(10), 0.1);
+        assert_relative_eq!(schedule.lr(25), lrs[5], epsilon = 1e-6);
+        assert!(CosineAnnealing::new(0.1, 0.01, 0).is_none());
+    }
+}
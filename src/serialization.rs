@@ -0,0 +1,36 @@
+//! Serializable representation of a Neural Network, shared by the supported
+//! model formats.
+
+use ndarray::{Array1, Array2};
+
+use super::{Float, NetworkError};
+use activation::ActivationTag;
+use training::TargetScaler;
+
+#[derive(Serialize, Deserialize)]
+pub struct LayerModel {
+    pub activation: ActivationTag,
+    pub inputs_weights: Array2<Float>,
+    pub outputs_weights: Array2<Float>,
+    /// Only present for a layer with biases.
+    pub inputs_bias: Option<Array1<Float>>,
+    /// Only present for a layer with biases.
+    pub outputs_bias: Option<Array1<Float>>,
+    /// Only present for a temperature other than 1.
+    #[serde(default)]
+    pub temperature: Option<Float>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NetworkModel {
+    pub layers: Vec<LayerModel>,
+    /// Scaler of the outputs of the training dataset, if any.
+    #[serde(default)]
+    pub target_scaler: Option<TargetScaler>,
+}
+
+/// Report a Neural Network that cannot be saved or loaded, for the given
+/// `reason`.
+pub fn invalid_model(reason: String) -> NetworkError {
+    NetworkError::InvalidModel { reason }
+}
@@ -0,0 +1,129 @@
+use ndarray::{Array2, ArrayView2};
+
+use super::{Float, NetworkResult};
+use layer::Layer;
+use network::NeuralNetwork;
+
+/// Compare the gradients computed by the backward propagation of `network`
+/// against their finite-difference approximations, and return the maximum
+/// relative error over all the weights.
+///
+/// Each weight w is perturbed in turn by ±`epsilon`, the numerical gradient
+/// being (loss(w + epsilon) - loss(w - epsilon)) / (2 * epsilon), where the
+/// loss is the one of ```NeuralNetwork::loss```.
+///
+/// The weights are restored afterwards. The network should be in inference
+/// mode, since dropout would make the cost random.
+pub fn gradient_check(
+    network: &mut NeuralNetwork,
+    inputs: ArrayView2<Float>,
+    expected_outputs: ArrayView2<Float>,
+    epsilon: Float,
+) -> NetworkResult<Float> {
+    network.backward_propagation(inputs, expected_outputs)?;
+    let gradients: Vec<(Array2<Float>, Array2<Float>)> = network
+        .layers()
+        .iter()
+        .map(|layer| (layer.cost_d_inputs().to_owned(), layer.cost_d_outputs().to_owned()))
+        .collect();
+
+    let cost = |network: &mut NeuralNetwork| network.loss(inputs, expected_outputs);
+    let mut max_error: Float = 0.0;
+    for (l, &(ref d_inputs, ref d_outputs)) in gradients.iter().enumerate() {
+        for &(outputs_weights, analytic_gradients) in &[(false, d_inputs), (true, d_outputs)] {
+            let weights = weights(&network.layers()[l], outputs_weights);
+            for ((i, j), &analytic) in analytic_gradients.indexed_iter() {
+                let mut perturbed = weights.clone();
+                perturbed[[i, j]] = weights[[i, j]] + epsilon;
+                set_weights(&mut network.layers_mut()[l], outputs_weights, perturbed.clone())?;
+                let cost_plus = cost(network)?;
+                perturbed[[i, j]] = weights[[i, j]] - epsilon;
+                set_weights(&mut network.layers_mut()[l], outputs_weights, perturbed)?;
+                let cost_minus = cost(network)?;
+
+                let numerical = (cost_plus - cost_minus) / (2.0 * epsilon);
+                let scale = analytic.abs() + numerical.abs();
+                if scale > 0.0 {
+                    max_error = max_error.max((analytic - numerical).abs() / scale);
+                }
+            }
+            set_weights(&mut network.layers_mut()[l], outputs_weights, weights)?;
+        }
+    }
+    Ok(max_error)
+}
+
+/// Finite-difference step and maximum relative error of the gradient checks
+/// of the tests, for the precision of ```Float```: the losses of single
+/// precision keep too few digits for the small steps of double precision.
+#[cfg(test)]
+pub(crate) fn gradient_check_tolerance() -> (Float, Float) {
+    if cfg!(feature = "f32") {
+        (1e-2, 5e-2)
+    } else {
+        (1e-5, 1e-6)
+    }
+}
+
+fn weights(layer: &Layer<Float>, outputs_weights: bool) -> Array2<Float> {
+    if outputs_weights {
+        layer.outputs_weights().to_owned()
+    } else {
+        layer.inputs_weights().to_owned()
+    }
+}
+
+fn set_weights(
+    layer: &mut Layer<Float>,
+    outputs_weights: bool,
+    weights: Array2<Float>,
+) -> NetworkResult<()> {
+    if outputs_weights {
+        layer.set_outputs_weights(weights)
+    } else {
+        layer.set_inputs_weights(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2};
+
+    use activation::{Sigmoid, TanH};
+    use layer::Layer;
+    use network::NeuralNetwork;
+    use super::{gradient_check, gradient_check_tolerance};
+
+    #[test]
+    fn gradient_check_tiny_network() {
+        let mut neural_network = NeuralNetwork::new(vec![
+            Layer::new_with_bias(
+                TanH,
+                arr2(&[[0.5, -0.3, 0.8], [0.4, 0.7, -0.6]]),
+                arr2(&[[0.4, -0.1], [-0.9, 0.3], [0.6, 0.5]]),
+                arr1(&[0.1, -0.2, 0.05]),
+                arr1(&[0.3, -0.1]),
+            ),
+            Layer::new(Sigmoid, arr2(&[[0.3], [-0.7]]), arr2(&[[1.2]])).with_l2(0.1),
+        ]);
+        let inputs = arr2(&[[0.0, 1.0], [1.0, 0.5], [-1.0, 2.0]]);
+        let expected_outputs = arr2(&[[1.0], [0.0], [0.5]]);
+        let weights = neural_network.layers()[0].inputs_weights().to_owned();
+
+        let (step, tolerance) = gradient_check_tolerance();
+        let error = gradient_check(
+            &mut neural_network,
+            inputs.view(),
+            expected_outputs.view(),
+            step,
+        ).unwrap();
+        assert!(error < tolerance, "max relative error : {}", error);
+        assert_eq!(neural_network.layers()[0].inputs_weights(), weights);
+
+        let wrong_outputs = arr2(&[[1.0, 0.0]]);
+        assert!(
+            gradient_check(&mut neural_network, inputs.view(), wrong_outputs.view(), 1e-6)
+                .is_err()
+        );
+    }
+}
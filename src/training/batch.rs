@@ -0,0 +1,110 @@
+use ndarray::{ArrayView2, Axis};
+
+use utils::check_shape;
+use super::{Float, NetworkError, NetworkResult};
+
+/// Iterates over the mini-batches of a prepared dataset, yielding the
+/// (inputs, outputs) views of `batch_size` consecutive samples (rows) at a
+/// time.
+///
+/// The last batch holds the remaining samples, which may be fewer than
+/// `batch_size` unless ```drop_last``` is set.
+pub struct BatchIter<'a> {
+    inputs: ArrayView2<'a, Float>,
+    outputs: ArrayView2<'a, Float>,
+    batch_size: usize,
+    drop_last: bool,
+}
+
+impl<'a> BatchIter<'a> {
+    /// Iterate over `inputs` and `outputs`, which must have as many rows, by
+    /// batches of `batch_size` (at least 1) samples.
+    pub fn new(
+        inputs: ArrayView2<'a, Float>,
+        outputs: ArrayView2<'a, Float>,
+        batch_size: usize,
+    ) -> NetworkResult<Self> {
+        check_shape((inputs.rows(), outputs.cols()), outputs.dim())?;
+        if batch_size == 0 {
+            return Err(NetworkError::ZeroBatchSize);
+        }
+        Ok(BatchIter {
+            inputs,
+            outputs,
+            batch_size,
+            drop_last: false,
+        })
+    }
+
+    /// Skip the last batch if it holds fewer than `batch_size` samples.
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+}
+
+impl<'a> Iterator for BatchIter<'a> {
+    type Item = (ArrayView2<'a, Float>, ArrayView2<'a, Float>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rows = self.inputs.rows();
+        if rows == 0 || (self.drop_last && rows < self.batch_size) {
+            return None;
+        }
+        let size = self.batch_size.min(rows);
+        let (inputs, remaining_inputs) = self.inputs.clone().split_at(Axis(0), size);
+        let (outputs, remaining_outputs) = self.outputs.clone().split_at(Axis(0), size);
+        self.inputs = remaining_inputs;
+        self.outputs = remaining_outputs;
+        Some((inputs, outputs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array2;
+
+    use super::*;
+
+    #[test]
+    fn batches() {
+        let inputs = Array2::from_shape_fn((7, 2), |(i, j)| (i * 2 + j) as Float);
+        let outputs = Array2::from_shape_fn((7, 1), |(i, _)| i as Float);
+        let batches = |batch_size, drop_last| {
+            BatchIter::new(inputs.view(), outputs.view(), batch_size)
+                .unwrap()
+                .drop_last(drop_last)
+                .collect::<Vec<_>>()
+        };
+
+        let all = batches(3, false);
+        assert_eq!(
+            all.iter().map(|batch| batch.0.rows()).collect::<Vec<_>>(),
+            vec![3, 3, 1]
+        );
+        let rows: Vec<Float> = all.iter().flat_map(|batch| batch.1.iter()).cloned().collect();
+        assert_eq!(rows, (0..7).map(|i| i as Float).collect::<Vec<_>>());
+        for &(ref inputs, ref outputs) in &all {
+            for (input, output) in inputs.genrows().into_iter().zip(outputs.iter()) {
+                assert_eq!(input[0], output * 2.0);
+            }
+        }
+
+        let complete = batches(3, true);
+        assert_eq!(complete.len(), 2);
+        assert!(complete.iter().all(|batch| batch.0.rows() == 3));
+        assert_eq!(batches(7, true).len(), 1);
+        assert_eq!(batches(8, true).len(), 0);
+        assert_eq!(batches(8, false).len(), 1);
+    }
+
+    #[test]
+    fn batches_errors() {
+        let inputs = Array2::zeros((4, 2));
+        assert_eq!(
+            BatchIter::new(inputs.view(), Array2::zeros((4, 1)).view(), 0).err(),
+            Some(NetworkError::ZeroBatchSize)
+        );
+        assert!(BatchIter::new(inputs.view(), Array2::zeros((3, 1)).view(), 2).is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+
+use super::super::{NetworkResult, ResultString};
+use utils::io_error;
+use super::{Float, Sample};
+
+/// A dataset read lazily, row by row, from CSV (comma-separated values)
+/// data, so that it never has to fit in memory as a whole.
+///
+/// The first `num_inputs` columns of each row are the inputs of the
+/// ```Sample```s yielded, the remaining ones being their outputs. Blank lines
+/// are skipped, and every row must have as many columns as the first one.
+pub struct CsvDataset<R: BufRead> {
+    lines: Lines<R>,
+    num_inputs: usize,
+    num_columns: Option<usize>,
+    delimiter: char,
+    has_header: bool,
+    /// Number of the last line read (starting at 1).
+    line_number: usize,
+}
+
+impl CsvDataset<BufReader<File>> {
+    /// Open the CSV file at `path`.
+    pub fn open(path: &Path, num_inputs: usize) -> NetworkResult<Self> {
+        let file = File::open(path).map_err(|why| io_error(path, &why))?;
+        Ok(CsvDataset::from_reader(BufReader::new(file), num_inputs))
+    }
+}
+
+impl<R: BufRead> CsvDataset<R> {
+    /// Read the CSV rows from `reader`, without header and separated by
+    /// commas by default.
+    pub fn from_reader(reader: R, num_inputs: usize) -> Self {
+        CsvDataset {
+            lines: reader.lines(),
+            num_inputs,
+            num_columns: None,
+            delimiter: ',',
+            has_header: false,
+            line_number: 0,
+        }
+    }
+
+    /// Skip the first line of the data, holding the names of the columns.
+    pub fn has_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    /// Set the character separating the columns (',' by default).
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    fn parse_row(&mut self, line: &str) -> ResultString<Sample> {
+        let values = line.split(self.delimiter)
+            .map(|value| {
+                value.trim().parse::<Float>().map_err(|_| {
+                    format!(
+                        "CsvDataset : line {} : invalid value \"{}\"",
+                        self.line_number,
+                        value
+                    )
+                })
+            })
+            .collect::<ResultString<Vec<Float>>>()?;
+        let num_columns = *self.num_columns.get_or_insert(values.len());
+        if values.len() != num_columns {
+            return Err(format!(
+                "CsvDataset : line {} : expected {} columns, got {}",
+                self.line_number,
+                num_columns,
+                values.len()
+            ));
+        }
+        if values.len() <= self.num_inputs {
+            return Err(format!(
+                "CsvDataset : line {} : expected more than {} columns, got {}",
+                self.line_number,
+                self.num_inputs,
+                values.len()
+            ));
+        }
+        let outputs = values[self.num_inputs..].to_vec();
+        let mut inputs = values;
+        inputs.truncate(self.num_inputs);
+        Ok(Sample::dataset(inputs, outputs))
+    }
+}
+
+impl<R: BufRead> Iterator for CsvDataset<R> {
+    type Item = ResultString<Sample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(why) => return Some(Err(format!("CsvDataset : read error ({})", why))),
+            };
+            self.line_number += 1;
+            if self.line_number == 1 && self.has_header {
+                continue;
+            }
+            if !line.trim().is_empty() {
+                return Some(self.parse_row(&line));
+            }
+        }
+    }
+}
+
+/// Write the loss history returned by ```Trainer::train``` to the CSV file at
+/// `path`, as a header followed by one `epoch,loss` row per epoch (starting
+/// at 1), for instance to plot it.
+pub fn write_loss_csv(history: &[Float], path: &Path) -> NetworkResult<()> {
+    let file = File::create(path).map_err(|why| io_error(path, &why))?;
+    let mut writer = BufWriter::new(file);
+    let mut write_rows = || -> io::Result<()> {
+        writeln!(writer, "epoch,loss")?;
+        for (epoch, loss) in history.iter().enumerate() {
+            writeln!(writer, "{},{}", epoch + 1, loss)?;
+        }
+        writer.flush()
+    };
+    write_rows().map_err(|why| io_error(path, &why))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn csv_dataset() {
+        let csv = "sleep;study;grade\n3;5;75\n5 ; 1; 82\n\n10;2;93\n";
+        let samples = CsvDataset::from_reader(Cursor::new(csv), 2)
+            .has_header(true)
+            .delimiter(';')
+            .collect::<ResultString<Vec<Sample>>>()
+            .unwrap();
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[1].inputs().to_vec(), vec![5.0, 1.0]);
+        assert_eq!(samples[1].outputs().unwrap().to_vec(), vec![82.0]);
+        assert_eq!(samples[2].inputs().to_vec(), vec![10.0, 2.0]);
+
+        // without the header flag, the names of the columns are a row
+        let mut dataset = CsvDataset::from_reader(Cursor::new(csv), 2).delimiter(';');
+        assert!(dataset.next().unwrap().is_err());
+        assert!(dataset.next().unwrap().is_ok());
+    }
+
+    #[test]
+    fn csv_dataset_errors() {
+        let errors = |csv: &str, num_inputs| {
+            CsvDataset::from_reader(Cursor::new(csv.to_owned()), num_inputs)
+                .filter_map(Result::err)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            errors("1,2,3\n4,x,6\n", 2),
+            vec!["CsvDataset : line 2 : invalid value \"x\"".to_owned()]
+        );
+        assert_eq!(
+            errors("1,2,3\n4,5\n", 1),
+            vec!["CsvDataset : line 2 : expected 3 columns, got 2".to_owned()]
+        );
+        assert_eq!(errors("1,2\n3,4\n", 2).len(), 2);
+        assert!(errors("1,2\n3,4\n", 1).is_empty());
+    }
+
+    #[test]
+    fn loss_csv_round_trip() {
+        let path = env::temp_dir().join("rust-neuralnet-loss_csv_round_trip.csv");
+        let history = vec![0.5, 0.25, 0.125];
+        write_loss_csv(&history, &path).unwrap();
+        let samples = CsvDataset::open(&path, 1)
+            .unwrap()
+            .has_header(true)
+            .collect::<ResultString<Vec<Sample>>>()
+            .unwrap();
+        let epochs: Vec<Float> = samples.iter().map(|sample| sample.inputs()[0]).collect();
+        let losses: Vec<Float> = samples
+            .iter()
+            .map(|sample| sample.outputs().unwrap()[0])
+            .collect();
+        assert_eq!(epochs, vec![1.0, 2.0, 3.0]);
+        assert_eq!(losses, history);
+
+        write_loss_csv(&[], &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "epoch,loss\n");
+        fs::remove_file(&path).unwrap();
+    }
+}
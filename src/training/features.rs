@@ -0,0 +1,98 @@
+use ndarray::Array2;
+
+use super::Float;
+
+/// Expand the ([samples] * [features]) `inputs` with all the monomials of
+/// their features of degree 2 up to `degree`, appended as new columns after
+/// the original features.
+///
+/// The monomials are ordered by degree, then lexicographically on the
+/// features: for a degree of 2, [a, b] becomes [a, b, a^2, a*b, b^2], and
+/// for a degree of 3, a^3, a^2*b, a*b^2 and b^3 follow.
+///
+/// A degree of at most 1 leaves the inputs unchanged.
+pub fn polynomial_features(inputs: &Array2<Float>, degree: usize) -> Array2<Float> {
+    let mut monomials = Vec::new();
+    for d in 2..degree + 1 {
+        push_monomials(&mut Vec::with_capacity(d), 0, d, inputs.cols(), &mut monomials);
+    }
+    expand(inputs, &monomials)
+}
+
+/// Same as ```polynomial_features```, without the interaction terms: only
+/// the powers of each feature are appended. For a degree of 2, [a, b]
+/// becomes [a, b, a^2, b^2].
+pub fn polynomial_powers(inputs: &Array2<Float>, degree: usize) -> Array2<Float> {
+    let monomials: Vec<Vec<usize>> = (2..degree + 1)
+        .flat_map(|d| (0..inputs.cols()).map(move |feature| vec![feature; d]))
+        .collect();
+    expand(inputs, &monomials)
+}
+
+/// Push to `monomials` every monomial (as the features it multiplies, in
+/// increasing order) extending `prefix` to `degree` features of index at
+/// least `first`.
+fn push_monomials(
+    prefix: &mut Vec<usize>,
+    first: usize,
+    degree: usize,
+    features: usize,
+    monomials: &mut Vec<Vec<usize>>,
+) {
+    if prefix.len() == degree {
+        monomials.push(prefix.clone());
+        return;
+    }
+    for feature in first..features {
+        prefix.push(feature);
+        push_monomials(prefix, feature, degree, features, monomials);
+        prefix.pop();
+    }
+}
+
+/// Append to `inputs` one column per monomial.
+fn expand(inputs: &Array2<Float>, monomials: &[Vec<usize>]) -> Array2<Float> {
+    let features = inputs.cols();
+    Array2::from_shape_fn((inputs.rows(), features + monomials.len()), |(i, j)| {
+        if j < features {
+            inputs[[i, j]]
+        } else {
+            monomials[j - features]
+                .iter()
+                .map(|&feature| inputs[[i, feature]])
+                .product()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::arr2;
+    use super::*;
+
+    #[test]
+    fn polynomial_expansion() {
+        let inputs = arr2(&[[2.0, 3.0], [-1.0, 0.5]]);
+        assert_eq!(
+            polynomial_features(&inputs, 2),
+            arr2(&[
+                [2.0, 3.0, 4.0, 6.0, 9.0],
+                [-1.0, 0.5, 1.0, -0.5, 0.25],
+            ])
+        );
+        assert_eq!(
+            polynomial_features(&inputs, 3).row(0).to_vec(),
+            vec![2.0, 3.0, 4.0, 6.0, 9.0, 8.0, 12.0, 18.0, 27.0]
+        );
+        assert_eq!(
+            polynomial_powers(&inputs, 3),
+            arr2(&[
+                [2.0, 3.0, 4.0, 9.0, 8.0, 27.0],
+                [-1.0, 0.5, 1.0, 0.25, -1.0, 0.125],
+            ])
+        );
+        assert_eq!(polynomial_features(&inputs, 1), inputs);
+        // 3 features: 3 squares and 3 products
+        assert_eq!(polynomial_features(&Array2::zeros((1, 3)), 2).cols(), 3 + 6);
+    }
+}
@@ -1,7 +1,17 @@
-use super::{Float, ResultString};
+use super::{Float, NetworkError, NetworkResult};
 
+mod batch;
+mod csv;
+mod features;
 mod sample;
+mod scaling;
 mod trainer;
 
-pub use self::sample::{Sample, prepare_dataset};
+pub use self::batch::BatchIter;
+pub use self::csv::{CsvDataset, write_loss_csv};
+pub use self::features::{polynomial_features, polynomial_powers};
+pub use self::sample::{Sample, k_fold_split, label_smoothing, one_hot, prepare_dataset,
+                       prepare_dataset_normalized, prepare_inputs, shuffle_dataset,
+                       train_test_split};
+pub use self::scaling::{MinMaxScaler, StandardScaler, TargetScaler};
 pub use self::trainer::{Trainer, TrainerHaltCondition};
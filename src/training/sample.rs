@@ -1,6 +1,7 @@
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, Array2, ArrayView1, Zip};
+use rand::Rng;
 
-use super::{Float, ResultString};
+use super::{Float, NetworkError, NetworkResult, StandardScaler};
 
 /// A Sample contains the vector of the observed values of all the inputs
 /// of an Artificial Neural Network.
@@ -32,61 +33,448 @@ impl Sample {
             outputs: None,
         }
     }
+
+    /// Build a training Sample for a classifier, whose outputs vector is the
+    /// one-hot encoding of `class` among `num_classes` classes.
+    pub fn classification<V>(inputs: V, class: usize, num_classes: usize) -> NetworkResult<Self>
+    where
+        V: Into<Array1<Float>>,
+    {
+        let outputs = one_hot(&[class], num_classes)?;
+        Ok(Sample {
+            inputs: inputs.into(),
+            outputs: Some(outputs.row(0).to_owned()),
+        })
+    }
+
+    /// Get the observed values of the inputs.
+    pub fn inputs(&self) -> ArrayView1<Float> {
+        self.inputs.view()
+    }
+
+    /// Get the observed values of the outputs, if any.
+    pub fn outputs(&self) -> Option<ArrayView1<Float>> {
+        self.outputs.as_ref().map(|outputs| outputs.view())
+    }
+}
+
+/// Encode the class `labels` as one-hot vectors.
+///
+/// one_hot : ([labels] * [num_classes])
+pub fn one_hot(labels: &[usize], num_classes: usize) -> NetworkResult<Array2<Float>> {
+    let mut encoded = Array2::zeros((labels.len(), num_classes));
+    for (i, &label) in labels.iter().enumerate() {
+        if label >= num_classes {
+            return Err(NetworkError::LabelOutOfRange { label, num_classes });
+        }
+        encoded[[i, label]] = 1.0;
+    }
+    Ok(encoded)
+}
+
+/// Smooth the one-hot `targets` of a classifier, so that it does not learn
+/// to be overconfident: each 1 becomes 1 - epsilon, and each 0 becomes
+/// epsilon / (num_classes - 1), every row still summing to 1.
+///
+/// smoothed = targets * (1 - epsilon) + (1 - targets) * epsilon / (num_classes - 1)
+///
+/// Targets of a single class are left unchanged.
+pub fn label_smoothing(targets: &Array2<Float>, epsilon: Float) -> Array2<Float> {
+    if targets.cols() < 2 {
+        return targets.clone();
+    }
+    let spread = epsilon / (targets.cols() - 1) as Float;
+    targets.mapv(|t| t * (1.0 - epsilon) + (1.0 - t) * spread)
+}
+
+/// Shuffle the samples of `dataset` in place with the Fisher-Yates
+/// algorithm, for instance before each training epoch.
+///
+/// The resulting order only depends on the state of `rng`.
+pub fn shuffle_dataset<R: Rng>(dataset: &mut Vec<Sample>, rng: &mut R) {
+    for i in (1..dataset.len()).rev() {
+        let j = rng.gen_range(0, i + 1);
+        dataset.swap(i, j);
+    }
+}
+
+/// Randomly partition `dataset` into a training set and a test set, the
+/// latter holding `test_fraction` (rounded) of the samples.
+///
+/// Fails if `test_fraction` is not within [0, 1].
+pub fn train_test_split<R: Rng>(
+    dataset: &[Sample],
+    test_fraction: f64,
+    rng: &mut R,
+) -> NetworkResult<(Vec<Sample>, Vec<Sample>)> {
+    if !(test_fraction >= 0.0 && test_fraction <= 1.0) {
+        return Err(NetworkError::InvalidTestFraction { test_fraction });
+    }
+    let mut train_set = dataset.to_vec();
+    shuffle_dataset(&mut train_set, rng);
+    let test_size = (dataset.len() as f64 * test_fraction).round() as usize;
+    let train_size = dataset.len() - test_size;
+    let test_set = train_set.split_off(train_size);
+    Ok((train_set, test_set))
 }
 
-// TODO: refactor using ndarray's Zip
-pub fn prepare_dataset(dataset: &Vec<Sample>) -> ResultString<(Array2<Float>, Array2<Float>)> {
-    if dataset.is_empty() {
-        return Err("empty dataset".into());
+/// Randomly partition `dataset` into `k` folds of (almost) equal sizes, and
+/// return for each fold the (training set, validation set) pair where the
+/// fold is the validation set and the remaining folds the training set.
+///
+/// Every sample thus appears in exactly one validation set, so that the
+/// validation losses of models trained on each pair can be averaged to
+/// estimate how well the model generalizes (k-fold cross-validation).
+pub fn k_fold_split<R: Rng>(
+    dataset: &[Sample],
+    k: usize,
+    rng: &mut R,
+) -> NetworkResult<Vec<(Vec<Sample>, Vec<Sample>)>> {
+    if k < 2 || k > dataset.len() {
+        return Err(NetworkError::InvalidFoldCount {
+            folds: k,
+            samples: dataset.len(),
+        });
+    }
+    let mut shuffled = dataset.to_vec();
+    shuffle_dataset(&mut shuffled, rng);
+    // the first (len % k) folds hold one more sample
+    let fold_start = |fold: usize| {
+        let (size, remainder) = (dataset.len() / k, dataset.len() % k);
+        fold * size + fold.min(remainder)
+    };
+    Ok((0..k)
+        .map(|fold| {
+            let (start, end) = (fold_start(fold), fold_start(fold + 1));
+            let mut train_set = shuffled[..start].to_vec();
+            train_set.extend_from_slice(&shuffled[end..]);
+            (train_set, shuffled[start..end].to_vec())
+        })
+        .collect())
+}
+
+/// Build the inputs matrix of a dataset, ignoring any observed outputs.
+///
+/// This is meant for batch predictions on samples built with
+/// ```Sample::predict```.
+pub fn prepare_inputs(dataset: &[Sample]) -> NetworkResult<Array2<Float>> {
+    let inputs_number = match dataset.first() {
+        Some(sample) => sample.inputs.len(),
+        None => return Err(NetworkError::EmptyDataset),
+    };
+    let mut inputs = Array2::zeros((dataset.len(), inputs_number));
+    for (i, (sample, mut row)) in dataset.iter().zip(inputs.genrows_mut()).enumerate() {
+        if sample.inputs.len() != inputs_number {
+            return Err(NetworkError::SampleMismatch {
+                sample_index: i,
+                expected: inputs_number,
+                got: sample.inputs.len(),
+            });
+        }
+        row.assign(&sample.inputs);
     }
-    let maybe_sample = dataset.first().unwrap().clone(); // TODO: avoid clone ?
-    if maybe_sample.outputs.is_none() {
-        return Err("dataset error : no observed output for the sample of index 0".into());
+    Ok(inputs)
+}
+
+pub fn prepare_dataset(dataset: &[Sample]) -> NetworkResult<(Array2<Float>, Array2<Float>)> {
+    let (inputs_number, outputs_number) = match dataset.first() {
+        Some(&Sample {
+            ref inputs,
+            outputs: Some(ref outputs),
+        }) => (inputs.len(), outputs.len()),
+        Some(_) => return Err(NetworkError::MissingOutputs { sample_index: 0 }),
+        None => return Err(NetworkError::EmptyDataset),
+    };
+
+    for (i, sample) in dataset.iter().enumerate() {
+        let sample_outputs = match sample.outputs {
+            Some(ref o) => o,
+            None => return Err(NetworkError::MissingOutputs { sample_index: i }),
+        };
+        if sample.inputs.len() != inputs_number {
+            return Err(NetworkError::SampleMismatch {
+                sample_index: i,
+                expected: inputs_number,
+                got: sample.inputs.len(),
+            });
+        }
+        if sample_outputs.len() != outputs_number {
+            return Err(NetworkError::SampleMismatch {
+                sample_index: i,
+                expected: outputs_number,
+                got: sample_outputs.len(),
+            });
+        }
     }
-    let inputs_number = maybe_sample.inputs.len();
-    let outputs_number = maybe_sample.outputs.unwrap().len();
 
     let mut inputs = Array2::zeros((dataset.len(), inputs_number));
     let mut observed_outputs = Array2::zeros((dataset.len(), outputs_number));
-    for i in 0..dataset.len() {
-        let sample_outputs = match &dataset[i].outputs {
-            &Some(ref o) => o,
-            &None => {
-                return Err(format!(
-                    "dataset error : no observed output for the sample of index {}",
-                    i,
-                ))
+    Zip::from(inputs.genrows_mut())
+        .and(observed_outputs.genrows_mut())
+        .and(&ArrayView1::from(&dataset[..]))
+        .apply(|mut sample_inputs, mut sample_outputs, sample| {
+            sample_inputs.assign(&sample.inputs);
+            if let Some(ref outputs) = sample.outputs {
+                sample_outputs.assign(outputs);
             }
-        };
+        });
+    Ok((inputs, observed_outputs))
+}
+
+/// Same as ```prepare_dataset```, with each output column standardized to a
+/// zero mean and a unit variance, so that the outputs of a multi-output
+/// regression weigh the same in the cost whatever their ranges.
+///
+/// The fitted ```StandardScaler``` is returned along with the matrices, to
+/// map predictions back to the original scale (for instance with
+/// ```NeuralNetwork::set_target_scaler```).
+pub fn prepare_dataset_normalized(
+    dataset: &[Sample],
+) -> NetworkResult<(Array2<Float>, Array2<Float>, StandardScaler)> {
+    let (inputs, outputs) = prepare_dataset(dataset)?;
+    let mut scaler = StandardScaler::default();
+    scaler.fit(&outputs);
+    let normalized_outputs = scaler.transform(&outputs);
+    Ok((inputs, normalized_outputs, scaler))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2};
+    use rand::{SeedableRng, StdRng};
+    use super::*;
 
-        let sample_inputs = &dataset[i].inputs;
-        if sample_inputs.len() != inputs_number {
-            return Err(format!(
-                "dataset error for sample of index {}: inputs count mismatch
-                ({} instead of the expected {})",
-                i,
-                sample_inputs.len(),
-                inputs_number,
-            ));
+    #[test]
+    fn accessors() {
+        let sample = Sample::dataset(vec![0.5, 1.0], vec![2.0]);
+        assert_eq!(sample.inputs(), arr1(&[0.5, 1.0]));
+        assert_eq!(sample.outputs(), Some(arr1(&[2.0]).view()));
+
+        let sample = Sample::predict(vec![3.0]);
+        assert_eq!(sample.inputs(), arr1(&[3.0]));
+        assert!(sample.outputs().is_none());
+    }
+
+    #[test]
+    fn smoothed_labels() {
+        let targets = one_hot(&[0, 2, 1], 3).unwrap();
+        let smoothed = label_smoothing(&targets, 0.1);
+        let expected = arr2(&[[0.9, 0.05, 0.05], [0.05, 0.05, 0.9], [0.05, 0.9, 0.05]]);
+        for (&value, &expected_value) in smoothed.iter().zip(expected.iter()) {
+            assert_relative_eq!(value, expected_value, epsilon = 1e-6);
         }
-        for j in 0..inputs_number {
-            let e = inputs.get_mut((i, j)).unwrap();
-            *e = sample_inputs[j];
+        for row in smoothed.genrows() {
+            assert_relative_eq!(row.scalar_sum(), 1.0, epsilon = 1e-6);
         }
+        assert_eq!(label_smoothing(&targets, 0.0), targets);
+        let single_class = one_hot(&[0, 0], 1).unwrap();
+        assert_eq!(label_smoothing(&single_class, 0.1), single_class);
+    }
 
-        if sample_outputs.len() != outputs_number {
-            return Err(format!(
-                "dataset error for sample of index {}: outputs count mismatch
-                ({} instead of the expected {})",
-                i,
-                sample_outputs.len(),
-                outputs_number,
-            ));
+    #[test]
+    fn normalized_outputs() {
+        let dataset = vec![
+            Sample::dataset(vec![0.0], vec![0.1, 200.0]),
+            Sample::dataset(vec![1.0], vec![0.5, 900.0]),
+            Sample::dataset(vec![2.0], vec![0.9, 100.0]),
+            Sample::dataset(vec![3.0], vec![0.2, 400.0]),
+        ];
+        let (inputs, outputs, scaler) = prepare_dataset_normalized(&dataset).unwrap();
+        assert_eq!(inputs, prepare_dataset(&dataset).unwrap().0);
+        for column in outputs.gencolumns() {
+            let mean = column.scalar_sum() / 4.0;
+            let variance = column.mapv(|v| (v - mean) * (v - mean)).scalar_sum() / 4.0;
+            assert_relative_eq!(mean, 0.0, epsilon = 1e-6);
+            assert_relative_eq!(variance, 1.0, epsilon = 1e-5);
         }
-        for j in 0..outputs_number {
-            let e: &mut Float = observed_outputs.get_mut((i, j)).unwrap();
-            *e = sample_outputs[j];
+        let original_outputs = scaler.inverse_transform(&outputs);
+        assert_relative_eq!(original_outputs[[1, 1]], 900.0, epsilon = 1e-3);
+        assert_eq!(
+            prepare_dataset_normalized(&Vec::new()).err(),
+            Some(NetworkError::EmptyDataset)
+        );
+    }
+
+    #[test]
+    fn one_hot_encoding() {
+        let encoded = one_hot(&[0, 2, 1, 2], 3).unwrap();
+        assert_eq!(
+            encoded,
+            arr2(&[
+                [1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 1.0, 0.0],
+                [0.0, 0.0, 1.0],
+            ])
+        );
+        assert_eq!(
+            one_hot(&[0, 3], 3),
+            Err(NetworkError::LabelOutOfRange {
+                label: 3,
+                num_classes: 3,
+            })
+        );
+
+        let sample = Sample::classification(vec![0.5, 0.1], 1, 3).unwrap();
+        assert_eq!(sample.inputs(), arr1(&[0.5, 0.1]));
+        assert_eq!(sample.outputs(), Some(arr1(&[0.0, 1.0, 0.0]).view()));
+        assert!(Sample::classification(vec![0.5, 0.1], 3, 3).is_err());
+    }
+
+    #[test]
+    fn shuffle_dataset_is_reproducible() {
+        let dataset: Vec<Sample> = (0..8)
+            .map(|i| Sample::predict(vec![i as Float]))
+            .collect();
+        let permutation = |seed: usize| {
+            let mut rng = StdRng::from_seed(&[seed][..]);
+            let mut shuffled = dataset.clone();
+            shuffle_dataset(&mut shuffled, &mut rng);
+            shuffled
+                .iter()
+                .map(|sample| sample.inputs()[0] as usize)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(permutation(42), vec![3, 1, 5, 4, 0, 6, 7, 2]);
+        assert_eq!(permutation(42), permutation(42));
+        assert_ne!(permutation(42), permutation(7));
+    }
+
+    #[test]
+    fn train_test_split_partitions() {
+        let mut rng = StdRng::from_seed(&[35usize][..]);
+        let dataset: Vec<Sample> = (0..10)
+            .map(|i| Sample::dataset(vec![i as Float], vec![0.0]))
+            .collect();
+
+        let (train_set, test_set) = train_test_split(&dataset, 0.3, &mut rng).unwrap();
+        assert_eq!((train_set.len(), test_set.len()), (7, 3));
+        let mut indices: Vec<Float> = train_set
+            .iter()
+            .chain(test_set.iter())
+            .map(|sample| sample.inputs()[0])
+            .collect();
+        indices.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(indices, (0..10).map(|i| i as Float).collect::<Vec<_>>());
+
+        let (train_set, test_set) = train_test_split(&dataset, 0.0, &mut rng).unwrap();
+        assert_eq!((train_set.len(), test_set.len()), (10, 0));
+        let (train_set, test_set) = train_test_split(&dataset, 1.0, &mut rng).unwrap();
+        assert_eq!((train_set.len(), test_set.len()), (0, 10));
+
+        assert_eq!(
+            train_test_split(&dataset, 1.5, &mut rng).err(),
+            Some(NetworkError::InvalidTestFraction { test_fraction: 1.5 })
+        );
+        assert!(train_test_split(&dataset, -0.1, &mut rng).is_err());
+    }
+
+    #[test]
+    fn k_fold_split_partitions() {
+        let mut rng = StdRng::from_seed(&[83usize][..]);
+        let dataset: Vec<Sample> = (0..11)
+            .map(|i| Sample::dataset(vec![i as Float], vec![0.0]))
+            .collect();
+        let folds = k_fold_split(&dataset, 3, &mut rng).unwrap();
+        assert_eq!(folds.len(), 3);
+
+        let mut validation_counts = vec![0; dataset.len()];
+        for &(ref train_set, ref validation_set) in &folds {
+            assert_eq!(train_set.len() + validation_set.len(), dataset.len());
+            assert!(validation_set.len() == 3 || validation_set.len() == 4);
+            for sample in validation_set {
+                validation_counts[sample.inputs()[0] as usize] += 1;
+                assert!(
+                    train_set
+                        .iter()
+                        .all(|other| other.inputs() != sample.inputs())
+                );
+            }
         }
+        assert_eq!(validation_counts, vec![1; dataset.len()]);
+
+        assert_eq!(
+            k_fold_split(&dataset, 1, &mut rng).err(),
+            Some(NetworkError::InvalidFoldCount {
+                folds: 1,
+                samples: 11,
+            })
+        );
+        assert!(k_fold_split(&dataset, 11, &mut rng).is_ok());
+        assert!(k_fold_split(&dataset, 12, &mut rng).is_err());
+    }
+
+    #[test]
+    fn prepare_dataset_stacks_samples() {
+        let dataset = vec![
+            Sample::dataset(vec![0.0, 1.0], vec![1.0]),
+            Sample::dataset(vec![1.0, 1.0], vec![0.0]),
+        ];
+        let (inputs, outputs) = prepare_dataset(&dataset).unwrap();
+        assert_eq!(inputs, arr2(&[[0.0, 1.0], [1.0, 1.0]]));
+        assert_eq!(outputs, arr2(&[[1.0], [0.0]]));
+
+        assert_eq!(prepare_dataset(&vec![]), Err(NetworkError::EmptyDataset));
+        let dataset = vec![
+            Sample::dataset(vec![0.0], vec![1.0]),
+            Sample::predict(vec![0.0]),
+        ];
+        assert_eq!(
+            prepare_dataset(&dataset),
+            Err(NetworkError::MissingOutputs { sample_index: 1 })
+        );
+        let dataset = vec![
+            Sample::dataset(vec![0.0], vec![1.0]),
+            Sample::dataset(vec![0.0], vec![1.0, 0.0]),
+        ];
+        assert_eq!(
+            prepare_dataset(&dataset),
+            Err(NetworkError::SampleMismatch {
+                sample_index: 1,
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn prepare_large_dataset() {
+        let (samples, inputs_number, outputs_number) = (10_000, 8, 3);
+        let expected_inputs =
+            Array2::from_shape_fn((samples, inputs_number), |(i, j)| (i * 31 + j) as Float);
+        let expected_outputs =
+            Array2::from_shape_fn((samples, outputs_number), |(i, j)| (i % 7 + j) as Float);
+        let dataset: Vec<Sample> = expected_inputs
+            .genrows()
+            .into_iter()
+            .zip(expected_outputs.genrows())
+            .map(|(inputs, outputs)| Sample::dataset(inputs.to_vec(), outputs.to_vec()))
+            .collect();
+
+        let (inputs, outputs) = prepare_dataset(&dataset).unwrap();
+        assert_eq!(inputs, expected_inputs);
+        assert_eq!(outputs, expected_outputs);
+    }
+
+    #[test]
+    fn prepare_inputs_ignores_outputs() {
+        let dataset = vec![
+            Sample::predict(vec![0.0, 1.0]),
+            Sample::dataset(vec![1.0, 1.0], vec![0.0]),
+            Sample::predict(vec![2.0, 0.5]),
+        ];
+        let inputs = prepare_inputs(&dataset).unwrap();
+        assert_eq!(inputs, arr2(&[[0.0, 1.0], [1.0, 1.0], [2.0, 0.5]]));
+
+        assert_eq!(prepare_inputs(&[]), Err(NetworkError::EmptyDataset));
+        let dataset = vec![Sample::predict(vec![0.0, 1.0]), Sample::predict(vec![1.0])];
+        assert_eq!(
+            prepare_inputs(&dataset),
+            Err(NetworkError::SampleMismatch {
+                sample_index: 1,
+                expected: 2,
+                got: 1,
+            })
+        );
     }
-    Ok((inputs, observed_outputs))
 }
@@ -0,0 +1,239 @@
+use ndarray::{Array1, Array2, Axis, Zip};
+
+use super::Float;
+
+/// Per-column minimum and maximum of a ([samples] * [features]) matrix.
+fn columns_min_max(data: &Array2<Float>) -> (Array1<Float>, Array1<Float>) {
+    let min = data.gencolumns()
+        .into_iter()
+        .map(|column| column.fold(Float::INFINITY, |m, &v| m.min(v)))
+        .collect::<Vec<_>>();
+    let max = data.gencolumns()
+        .into_iter()
+        .map(|column| column.fold(Float::NEG_INFINITY, |m, &v| m.max(v)))
+        .collect::<Vec<_>>();
+    (Array1::from_vec(min), Array1::from_vec(max))
+}
+
+/// The MinMaxScaler rescales each column (feature) of a dataset to [0, 1].
+///
+/// x' = (x - min) / (max - min)
+///
+/// The minimum and maximum are recorded by ```fit``` on the training data,
+/// so that the same scaling can later be applied to the inputs given at
+/// prediction time. Constant columns are only shifted by their minimum.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MinMaxScaler {
+    min: Array1<Float>,
+    max: Array1<Float>,
+    range: Array1<Float>,
+}
+
+impl MinMaxScaler {
+    /// Record the minimum and maximum of each column of `data`.
+    pub fn fit(&mut self, data: &Array2<Float>) {
+        let (min, max) = columns_min_max(data);
+        self.range = (&max - &min).mapv(|range| if range == 0.0 { 1.0 } else { range });
+        self.min = min;
+        self.max = max;
+    }
+
+    /// Scale `data`, which must have as many columns as the fitted data.
+    pub fn transform(&self, data: &Array2<Float>) -> Array2<Float> {
+        self.check_columns(data);
+        (data - &self.min) / &self.range
+    }
+
+    /// Map scaled data (for instance predictions) back to the original scale.
+    pub fn inverse_transform(&self, data: &Array2<Float>) -> Array2<Float> {
+        self.check_columns(data);
+        data * &self.range + &self.min
+    }
+
+    /// Get the minimum of each column recorded by ```fit```.
+    pub fn min(&self) -> &Array1<Float> {
+        &self.min
+    }
+
+    /// Get the maximum of each column recorded by ```fit```.
+    pub fn max(&self) -> &Array1<Float> {
+        &self.max
+    }
+
+    fn check_columns(&self, data: &Array2<Float>) {
+        assert_eq!(
+            data.len_of(Axis(1)),
+            self.min.len(),
+            "MinMaxScaler : columns count mismatch with the fitted data"
+        );
+    }
+}
+
+/// The StandardScaler standardizes each column (feature) of a dataset to a
+/// zero mean and a unit standard deviation.
+///
+/// x' = (x - mean) / std
+///
+/// As with ```MinMaxScaler```, the means and standard deviations recorded by
+/// ```fit``` are reused for any subsequent data. Columns with a zero
+/// variance are left unchanged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StandardScaler {
+    mean: Array1<Float>,
+    std: Array1<Float>,
+}
+
+impl StandardScaler {
+    /// Record the mean and the (population) standard deviation of each
+    /// column of `data`.
+    pub fn fit(&mut self, data: &Array2<Float>) {
+        let mut mean = data.mean_axis(Axis(0));
+        let std = (data - &mean).mapv(|d| d * d).mean_axis(Axis(0)).mapv(Float::sqrt);
+        // zero variance columns are mapped with the identity transform
+        Zip::from(&mut mean).and(&std).apply(|mean, &std| {
+            if std == 0.0 {
+                *mean = 0.0;
+            }
+        });
+        self.mean = mean;
+        self.std = std.mapv(|std| if std == 0.0 { 1.0 } else { std });
+    }
+
+    /// Standardize `data`, which must have as many columns as the fitted data.
+    pub fn transform(&self, data: &Array2<Float>) -> Array2<Float> {
+        self.check_columns(data);
+        (data - &self.mean) / &self.std
+    }
+
+    /// Map standardized data (for instance predictions) back to the original
+    /// scale.
+    pub fn inverse_transform(&self, data: &Array2<Float>) -> Array2<Float> {
+        self.check_columns(data);
+        data * &self.std + &self.mean
+    }
+
+    fn check_columns(&self, data: &Array2<Float>) {
+        assert_eq!(
+            data.len_of(Axis(1)),
+            self.mean.len(),
+            "StandardScaler : columns count mismatch with the fitted data"
+        );
+    }
+}
+
+/// A scaler fitted on the outputs of a training dataset, which can be
+/// attached to a ```NeuralNetwork``` (see
+/// ```NeuralNetwork::set_target_scaler```) to map its predictions back to
+/// the original scale.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TargetScaler {
+    MinMax(MinMaxScaler),
+    Standard(StandardScaler),
+}
+
+impl TargetScaler {
+    /// Get the number of columns of the fitted data.
+    pub fn columns(&self) -> usize {
+        match *self {
+            TargetScaler::MinMax(ref scaler) => scaler.min.len(),
+            TargetScaler::Standard(ref scaler) => scaler.mean.len(),
+        }
+    }
+
+    /// Map scaled data back to the original scale.
+    pub fn inverse_transform(&self, data: &Array2<Float>) -> Array2<Float> {
+        match *self {
+            TargetScaler::MinMax(ref scaler) => scaler.inverse_transform(data),
+            TargetScaler::Standard(ref scaler) => scaler.inverse_transform(data),
+        }
+    }
+}
+
+impl From<MinMaxScaler> for TargetScaler {
+    fn from(scaler: MinMaxScaler) -> Self {
+        TargetScaler::MinMax(scaler)
+    }
+}
+
+impl From<StandardScaler> for TargetScaler {
+    fn from(scaler: StandardScaler) -> Self {
+        TargetScaler::Standard(scaler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2};
+    use super::*;
+
+    #[test]
+    fn min_max_scaler() {
+        let training_data = arr2(&[[3.0, 5.0], [5.0, 1.0], [10.0, 2.0]]);
+        let mut scaler = MinMaxScaler::default();
+        scaler.fit(&training_data);
+        assert_eq!(scaler.min(), &arr1(&[3.0, 1.0]));
+        assert_eq!(scaler.max(), &arr1(&[10.0, 5.0]));
+
+        let scaled = scaler.transform(&training_data);
+        assert_eq!(scaled, arr2(&[[0.0, 1.0], [2.0 / 7.0, 0.0], [1.0, 0.25]]));
+
+        let held_out = arr2(&[[6.5, 4.0]]);
+        let scaled = scaler.transform(&held_out);
+        assert_eq!(scaled, arr2(&[[0.5, 0.75]]));
+        assert_eq!(scaler.inverse_transform(&scaled), held_out);
+    }
+
+    #[test]
+    fn min_max_scaler_constant_column() {
+        let mut scaler = MinMaxScaler::default();
+        scaler.fit(&arr2(&[[2.0, 0.0], [2.0, 1.0]]));
+        assert_eq!(scaler.min(), &arr1(&[2.0, 0.0]));
+        assert_eq!(scaler.max(), &arr1(&[2.0, 1.0]));
+        let scaled = scaler.transform(&arr2(&[[2.0, 0.5], [3.0, 1.0]]));
+        assert_eq!(scaled, arr2(&[[0.0, 0.5], [1.0, 1.0]]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn min_max_scaler_columns_mismatch() {
+        let mut scaler = MinMaxScaler::default();
+        scaler.fit(&arr2(&[[2.0, 0.0], [2.0, 1.0]]));
+        scaler.transform(&arr2(&[[1.0]]));
+    }
+
+    #[test]
+    fn standard_scaler() {
+        let training_data = arr2(&[[1.0, 10.0], [3.0, 20.0], [5.0, 60.0]]);
+        let mut scaler = StandardScaler::default();
+        scaler.fit(&training_data);
+        let scaled = scaler.transform(&training_data);
+        // the moments are exact up to a few roundings
+        let precision = 4.0 * Float::EPSILON;
+        for column in scaled.gencolumns() {
+            let mean = column.scalar_sum() / 3.0;
+            let variance = column.mapv(|x| (x - mean).powi(2)).scalar_sum() / 3.0;
+            assert_relative_eq!(mean, 0.0, epsilon = precision);
+            assert_relative_eq!(variance, 1.0, epsilon = precision);
+        }
+
+        let held_out = arr2(&[[4.0, 30.0]]);
+        let standardized = scaler.transform(&held_out);
+        assert_relative_eq!(
+            standardized[[0, 0]],
+            1.0 / (8.0 as Float / 3.0).sqrt(),
+            max_relative = precision
+        );
+        let restored = scaler.inverse_transform(&standardized);
+        assert_relative_eq!(restored[[0, 0]], 4.0, max_relative = precision);
+        assert_relative_eq!(restored[[0, 1]], 30.0, max_relative = precision);
+    }
+
+    #[test]
+    fn standard_scaler_zero_variance() {
+        let mut scaler = StandardScaler::default();
+        scaler.fit(&arr2(&[[7.0, 1.0], [7.0, 3.0]]));
+        let scaled = scaler.transform(&arr2(&[[7.0, 2.0], [8.0, 3.0]]));
+        assert!(scaled.iter().all(|x| x.is_finite()));
+        assert_eq!(scaled, arr2(&[[7.0, 0.0], [8.0, 1.0]]));
+    }
+}
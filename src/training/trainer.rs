@@ -1,41 +1,929 @@
-use ndarray::Array2;
+use ndarray::{Array2, ArrayView2, Axis};
+use rand::{thread_rng, Rng, StdRng};
 
-use super::super::{Float, ResultString};
+use super::super::{Float, NetworkError, NetworkResult, ResultString};
+use layer::{LayerGradients, LayerParameters};
 use network::NeuralNetwork;
-use super::{prepare_dataset, Sample};
+use optimizer::{GradientDescent, LrSchedule, Optimizer};
+use utils::{check_shape, seeded_rng, SHUFFLE_STREAM};
+use super::{prepare_dataset, BatchIter, Sample};
 
 pub enum TrainerHaltCondition {
+    /// Train for a fixed number of epochs.
     Epochs(u32),
+    /// Train until the loss over the whole dataset drops below the given
+    /// (strictly positive) threshold, for at most `max_epochs` epochs.
+    TargetLoss { loss: Float, max_epochs: u32 },
+    /// Train until the loss over the validation dataset has not decreased by
+    /// more than `min_delta` for `patience` consecutive epochs, then restore
+    /// the parameters of the best epoch.
+    EarlyStopping { patience: u32, min_delta: Float },
+}
+
+/// Best validation loss so far and matching parameters, tracked for the
+/// ```TrainerHaltCondition::EarlyStopping``` halt condition.
+struct EarlyStoppingMonitor {
+    best_loss: Float,
+    best_parameters: Vec<LayerParameters<Float>>,
+    stale_epochs: u32,
 }
 
 pub struct Trainer {
     inputs: Array2<Float>,
     outputs: Array2<Float>,
+    validation: Option<(Array2<Float>, Array2<Float>)>,
     network: NeuralNetwork,
     halt_condition: TrainerHaltCondition,
+    batch_size: Option<usize>,
+    accumulation_steps: usize,
+    /// Are the samples shuffled before each epoch?
+    shuffle: bool,
+    rng: StdRng,
+    optimizer: Box<Optimizer>,
+    lr_schedule: Option<Box<LrSchedule>>,
+    max_gradients_norm: Option<Float>,
+    /// Are the parameters of the epoch with the lowest validation loss
+    /// restored at the end of the training?
+    restore_best: bool,
+    epoch_callbacks: Vec<Box<FnMut(u32, Float)>>,
 }
 
 impl Trainer {
-    pub fn with_dataset(network: NeuralNetwork, dataset: &Vec<Sample>) -> ResultString<Self> {
-        match prepare_dataset(dataset) {
-            Ok((inputs, outputs)) => Ok(Trainer {
-                inputs,
-                outputs,
-                network,
-                halt_condition: TrainerHaltCondition::Epochs(1),
-            }),
-            Err(why) => Err(why),
+    /// Create a Trainer for `network` on `dataset`, whose inputs and outputs
+    /// must match the dimensions of the network.
+    pub fn with_dataset(network: NeuralNetwork, dataset: &Vec<Sample>) -> NetworkResult<Self> {
+        let (inputs, outputs) = prepare_dataset(dataset)?;
+        if inputs.cols() != network.input_dim() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: network.input_dim(),
+                got: inputs.cols(),
+            });
         }
+        if outputs.cols() != network.output_dim() {
+            return Err(NetworkError::DimensionMismatch {
+                expected: network.output_dim(),
+                got: outputs.cols(),
+            });
+        }
+        Ok(Trainer {
+            inputs,
+            outputs,
+            validation: None,
+            network,
+            halt_condition: TrainerHaltCondition::Epochs(1),
+            batch_size: None,
+            accumulation_steps: 1,
+            shuffle: false,
+            rng: seeded_rng(thread_rng().gen(), SHUFFLE_STREAM),
+            optimizer: Box::new(GradientDescent::new(0.1)),
+            lr_schedule: None,
+            max_gradients_norm: None,
+            restore_best: false,
+            epoch_callbacks: Vec::new(),
+        })
+    }
+
+    /// Create a Trainer holding a separate `validation` dataset, on which the
+    /// ```TrainerHaltCondition::EarlyStopping``` halt condition is evaluated.
+    pub fn with_datasets(
+        network: NeuralNetwork,
+        dataset: &Vec<Sample>,
+        validation: &Vec<Sample>,
+    ) -> NetworkResult<Self> {
+        let mut trainer = Trainer::with_dataset(network, dataset)?;
+        let (inputs, outputs) = prepare_dataset(validation)?;
+        check_shape((inputs.rows(), trainer.inputs.cols()), inputs.dim())?;
+        check_shape((outputs.rows(), trainer.outputs.cols()), outputs.dim())?;
+        trainer.validation = Some((inputs, outputs));
+        Ok(trainer)
     }
 
     pub fn halt_condition(mut self, halt_condition: TrainerHaltCondition) -> Option<Self> {
         use self::TrainerHaltCondition::*;
         match halt_condition {
             Epochs(epochs) => if epochs == 0 {
-                None
-            } else {
-                Some(self)
+                return None;
+            },
+            TargetLoss { loss, max_epochs } => if !(loss > 0.0) || max_epochs == 0 {
+                return None;
+            },
+            EarlyStopping { patience, min_delta } => {
+                if patience == 0 || !(min_delta >= 0.0) || self.validation.is_none() {
+                    return None;
+                }
+            }
+        }
+        self.halt_condition = halt_condition;
+        Some(self)
+    }
+
+    /// Train on mini-batches of (at most) `batch_size` samples instead of
+    /// the whole dataset at once.
+    ///
+    /// Returns ```None``` if `batch_size` is zero.
+    pub fn batch_size(mut self, batch_size: usize) -> Option<Self> {
+        if batch_size == 0 {
+            return None;
+        }
+        self.batch_size = Some(batch_size);
+        Some(self)
+    }
+
+    /// Accumulate the gradients of `accumulation_steps` consecutive batches
+    /// (micro-batches) before each update of the weights, so that the
+    /// effective batch size can exceed what fits in memory at once.
+    ///
+    /// Each update uses the average of the gradients of the micro-batches,
+    /// plus the gradients of the regularization penalties added once: with
+    /// micro-batches of equal sizes, it is the same as a single update with
+    /// a batch of all their samples (dropout and batch normalization aside).
+    ///
+    /// Returns ```None``` if `accumulation_steps` is zero.
+    pub fn accumulation_steps(mut self, accumulation_steps: usize) -> Option<Self> {
+        if accumulation_steps == 0 {
+            return None;
+        }
+        self.accumulation_steps = accumulation_steps;
+        Some(self)
+    }
+
+    /// Shuffle the samples of the dataset before each epoch, so that the
+    /// batches differ from one epoch to the next (the samples are visited
+    /// in order by default).
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Seed every source of randomness of the training from `seed`: the
+    /// shuffling of the samples and the dropout masks of the Neural Network
+    /// (see ```NeuralNetwork::seed```).
+    ///
+    /// Along with a ```builder::SeededNeuralNetworkBuilder``` seeded with
+    /// the same `seed`, two trainings with the same configuration yield
+    /// bitwise identical weights.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = seeded_rng(seed, SHUFFLE_STREAM);
+        self.network.seed(seed);
+        self
+    }
+
+    /// Set the optimizer updating the weights after each batch (by default,
+    /// ```GradientDescent``` with a learning rate of 0.1).
+    pub fn optimizer<O: 'static>(mut self, optimizer: O) -> Self
+    where
+        O: Optimizer,
+    {
+        self.optimizer = Box::new(optimizer);
+        self
+    }
+
+    /// Set the learning rate (0.1 by default) of the optimizer, i.e. the step
+    /// size of each update.
+    ///
+    /// Too large a learning rate makes the training diverge, while too small
+    /// a one slows it down.
+    pub fn learning_rate(mut self, learning_rate: Float) -> Self {
+        self.optimizer.set_learning_rate(learning_rate);
+        self
+    }
+
+    /// Set the learning rate of the optimizer at the beginning of each epoch
+    /// according to `lr_schedule`, overriding any fixed learning rate.
+    pub fn lr_schedule<S: 'static>(mut self, lr_schedule: S) -> Self
+    where
+        S: LrSchedule,
+    {
+        self.lr_schedule = Some(Box::new(lr_schedule));
+        self
+    }
+
+    /// Clip the gradients of each batch to a global L2 norm of at most
+    /// `max_norm` before updating the weights.
+    ///
+    /// Returns ```None``` if `max_norm` is not strictly positive.
+    ///
+    /// See ```NeuralNetwork::clip_gradients```.
+    pub fn clip_gradients(mut self, max_norm: Float) -> Option<Self> {
+        if !(max_norm > 0.0) {
+            return None;
+        }
+        self.max_gradients_norm = Some(max_norm);
+        Some(self)
+    }
+
+    /// Keep a copy of the parameters of the epoch with the lowest validation
+    /// loss, and restore them once the training is over, so that a noisy or
+    /// overfitting last epoch does not end up in the trained network.
+    ///
+    /// Returns ```None``` if `restore_best` is set without a validation
+    /// dataset.
+    pub fn restore_best(mut self, restore_best: bool) -> Option<Self> {
+        if restore_best && self.validation.is_none() {
+            return None;
+        }
+        self.restore_best = restore_best;
+        Some(self)
+    }
+
+    /// Call `callback` after each training epoch with the index of the epoch
+    /// (starting at 0) and the loss over the dataset at its end, for
+    /// instance to log the progress of the training.
+    ///
+    /// Several callbacks can be set, and are called in the order they were.
+    pub fn on_epoch<C: 'static>(mut self, callback: C) -> Self
+    where
+        C: FnMut(u32, Float),
+    {
+        self.epoch_callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Use ```GradientDescent``` with the given momentum (within [0, 1[) and
+    /// the current learning rate as the optimizer.
+    pub fn momentum(self, momentum: Float) -> Self {
+        let learning_rate = self.optimizer.learning_rate();
+        self.optimizer(GradientDescent::with_momentum(learning_rate, momentum))
+    }
+
+    /// Train the Neural Network until the halt condition is met, and return
+    /// the history of the loss over the dataset after each epoch.
+    ///
+    /// Each epoch goes through the dataset once, batch by batch: every batch
+    /// is propagated forward then backward, followed by a gradient descent
+    /// update of the weights. The Neural Network is only in training mode
+    /// during these updates: the losses are computed in inference mode.
+    ///
+    /// With a ```TrainerHaltCondition::TargetLoss```, the training goes on
+    /// for as long as the loss stays above the target, up to the maximum
+    /// number of epochs: check the last loss of the history to know whether
+    /// the target was reached.
+    ///
+    /// Whatever the halt condition, the training stops with a
+    /// ```NetworkError::Diverged``` error as soon as the loss is no longer
+    /// finite (NaN or infinite), typically because of too large a learning
+    /// rate.
+    ///
+    /// See ```restore_best``` to end up with the parameters of the best
+    /// epoch rather than the last one.
+    pub fn train(&mut self) -> NetworkResult<Vec<Float>> {
+        let mut history = Vec::new();
+        let mut monitor = None;
+        let mut best: Option<(Float, Vec<LayerParameters<Float>>)> = None;
+        while !self.halted(&history, &mut monitor)? {
+            if let Some(ref lr_schedule) = self.lr_schedule {
+                let epoch = history.len() as u32;
+                self.optimizer.set_learning_rate(lr_schedule.lr(epoch));
+            }
+            self.network.set_training(true);
+            let epoch = self.train_epoch();
+            self.network.set_training(false);
+            epoch?;
+            let loss = self.loss()?;
+            debug!("Trainer.train : epoch {} loss {}", history.len() + 1, loss);
+            if !loss.is_finite() {
+                return Err(NetworkError::Diverged {
+                    epoch: history.len() + 1,
+                });
+            }
+            for callback in &mut self.epoch_callbacks {
+                callback(history.len() as u32, loss);
+            }
+            history.push(loss);
+            if self.restore_best {
+                let validation_loss = self.validation_loss()?;
+                if best.as_ref().map_or(true, |&(best_loss, _)| validation_loss < best_loss) {
+                    best = Some((validation_loss, self.network.snapshot()));
+                }
+            }
+        }
+        if let Some((_, ref best_parameters)) = best {
+            self.network.restore(best_parameters);
+        }
+        Ok(history)
+    }
+
+    fn halted(
+        &mut self,
+        history: &[Float],
+        monitor: &mut Option<EarlyStoppingMonitor>,
+    ) -> NetworkResult<bool> {
+        match self.halt_condition {
+            TrainerHaltCondition::Epochs(epochs) => Ok(history.len() >= epochs as usize),
+            TrainerHaltCondition::TargetLoss {
+                loss: target_loss,
+                max_epochs,
+            } => {
+                let loss = match history.last() {
+                    Some(&loss) => loss,
+                    None => self.loss()?,
+                };
+                if !loss.is_finite() {
+                    return Err(NetworkError::Diverged {
+                        epoch: history.len(),
+                    });
+                }
+                Ok(loss < target_loss || history.len() >= max_epochs as usize)
+            }
+            TrainerHaltCondition::EarlyStopping { patience, min_delta } => {
+                let loss = self.validation_loss()?;
+                let improved = match *monitor {
+                    Some(ref monitor) => loss < monitor.best_loss - min_delta,
+                    None => true,
+                };
+                if improved {
+                    *monitor = Some(EarlyStoppingMonitor {
+                        best_loss: loss,
+                        best_parameters: self.network.snapshot(),
+                        stale_epochs: 0,
+                    });
+                    return Ok(false);
+                }
+                let monitor = monitor.as_mut().unwrap();
+                monitor.stale_epochs += 1;
+                if monitor.stale_epochs < patience {
+                    return Ok(false);
+                }
+                self.network.restore(&monitor.best_parameters);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Train the Neural Network over a single pass of a stream of samples,
+    /// such as a ```CsvDataset```, collected into batches of the configured
+    /// ```batch_size``` so that the stream is never held in memory as a
+    /// whole. Returns the number of samples trained on.
+    ///
+    /// The halt condition and learning rate schedule are ignored, and the
+    /// dataset of the Trainer is only used to compute its ```loss```. Fails
+    /// on the first invalid sample, or if no batch size was set.
+    pub fn train_stream<I>(&mut self, samples: I) -> NetworkResult<usize>
+    where
+        I: IntoIterator<Item = ResultString<Sample>>,
+    {
+        let batch_size = self.batch_size.ok_or(NetworkError::NoBatchSize)?;
+        let mut samples = samples.into_iter();
+        let mut count = 0;
+        let mut accumulator = GradientAccumulator::new(self.accumulation_steps);
+        self.network.set_training(true);
+        let result = loop {
+            let batch = match samples
+                .by_ref()
+                .take(batch_size)
+                .collect::<ResultString<Vec<Sample>>>()
+            {
+                Ok(batch) => batch,
+                Err(reason) => break Err(NetworkError::InvalidSample { reason }),
+            };
+            if batch.is_empty() {
+                accumulator.flush(&mut self.network, &mut *self.optimizer, self.max_gradients_norm);
+                break Ok(count);
+            }
+            count += batch.len();
+            let step = prepare_dataset(&batch).and_then(|(inputs, outputs)| {
+                accumulator.train_batch(
+                    &mut self.network,
+                    &mut *self.optimizer,
+                    self.max_gradients_norm,
+                    inputs.view(),
+                    outputs.view(),
+                )
+            });
+            if let Err(why) = step {
+                break Err(why);
+            }
+        };
+        self.network.set_training(false);
+        result
+    }
+
+    fn train_epoch(&mut self) -> NetworkResult<()> {
+        let batch_size = self.batch_size.unwrap_or_else(|| self.inputs.rows());
+        let shuffled = if self.shuffle {
+            let mut permutation: Vec<usize> = (0..self.inputs.rows()).collect();
+            self.rng.shuffle(&mut permutation);
+            Some((
+                self.inputs.select(Axis(0), &permutation),
+                self.outputs.select(Axis(0), &permutation),
+            ))
+        } else {
+            None
+        };
+        let Trainer {
+            ref inputs,
+            ref outputs,
+            ref mut network,
+            ref mut optimizer,
+            max_gradients_norm,
+            accumulation_steps,
+            ..
+        } = *self;
+        let (inputs, outputs) = match shuffled {
+            Some((ref inputs, ref outputs)) => (inputs, outputs),
+            None => (inputs, outputs),
+        };
+        let mut accumulator = GradientAccumulator::new(accumulation_steps);
+        for (inputs, outputs) in BatchIter::new(inputs.view(), outputs.view(), batch_size)? {
+            accumulator.train_batch(
+                network,
+                &mut **optimizer,
+                max_gradients_norm,
+                inputs,
+                outputs,
+            )?;
+        }
+        accumulator.flush(network, &mut **optimizer, max_gradients_norm);
+        Ok(())
+    }
+
+    /// Compute the loss of the Neural Network over the whole dataset.
+    pub fn loss(&mut self) -> NetworkResult<Float> {
+        self.network.loss(self.inputs.view(), self.outputs.view())
+    }
+
+    /// Compute the loss of the Neural Network over the validation dataset.
+    pub fn validation_loss(&mut self) -> NetworkResult<Float> {
+        match self.validation {
+            Some((ref inputs, ref outputs)) => self.network.loss(inputs.view(), outputs.view()),
+            None => Err(NetworkError::NoValidationDataset),
+        }
+    }
+
+    pub fn network(&self) -> &NeuralNetwork {
+        &self.network
+    }
+
+    pub fn network_mut(&mut self) -> &mut NeuralNetwork {
+        &mut self.network
+    }
+
+    pub fn into_network(self) -> NeuralNetwork {
+        self.network
+    }
+}
+
+/// Gradients of the cost (without the regularization penalties) summed over
+/// the batches since the last update, which happens every `steps` batches.
+struct GradientAccumulator {
+    steps: usize,
+    batches: usize,
+    gradients: Vec<LayerGradients<Float>>,
+}
+
+impl GradientAccumulator {
+    fn new(steps: usize) -> Self {
+        GradientAccumulator {
+            steps,
+            batches: 0,
+            gradients: Vec::new(),
+        }
+    }
+
+    /// Accumulate the gradients of `network` over the batch, and update its
+    /// weights once `steps` batches were accumulated.
+    fn train_batch(
+        &mut self,
+        network: &mut NeuralNetwork,
+        optimizer: &mut Optimizer,
+        max_gradients_norm: Option<Float>,
+        inputs: ArrayView2<Float>,
+        outputs: ArrayView2<Float>,
+    ) -> NetworkResult<()> {
+        if self.steps == 1 {
+            network.backward_propagation(inputs, outputs)?;
+            update(network, optimizer, max_gradients_norm);
+            return Ok(());
+        }
+        network.unregularized_backward_propagation(inputs, outputs)?;
+        if self.batches == 0 {
+            self.gradients = network.gradients();
+        } else {
+            for (gradients, batch_gradients) in self.gradients.iter_mut().zip(network.gradients()) {
+                gradients.add(&batch_gradients);
+            }
+        }
+        self.batches += 1;
+        if self.batches == self.steps {
+            self.flush(network, optimizer, max_gradients_norm);
+        }
+        Ok(())
+    }
+
+    /// Update the weights of `network` with the average of the accumulated
+    /// gradients, if any, plus the gradients of the regularization
+    /// penalties, then reset them.
+    fn flush(
+        &mut self,
+        network: &mut NeuralNetwork,
+        optimizer: &mut Optimizer,
+        max_gradients_norm: Option<Float>,
+    ) {
+        if self.batches == 0 {
+            return;
+        }
+        let batches = self.batches as Float;
+        for gradients in &mut self.gradients {
+            gradients.scale(1.0 / batches);
+        }
+        network.set_gradients(&self.gradients);
+        network.regularize_gradients();
+        update(network, optimizer, max_gradients_norm);
+        self.batches = 0;
+        self.gradients.clear();
+    }
+}
+
+/// Update the weights of `network` with a single step of `optimizer`, using
+/// its current gradients.
+fn update(
+    network: &mut NeuralNetwork,
+    optimizer: &mut Optimizer,
+    max_gradients_norm: Option<Float>,
+) {
+    if let Some(max_norm) = max_gradients_norm {
+        network.clip_gradients(max_norm);
+    }
+    optimizer.begin_update();
+    let learning_rate = optimizer.learning_rate();
+    network.update_with(learning_rate, |_, weights, gradients| {
+        optimizer.step(weights, gradients)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use ndarray::arr2;
+    use rand::{SeedableRng, StdRng};
+
+    use activation::{Identity, TanH};
+    use builder::NeuralNetworkBuilder;
+    use init::WeightInit;
+    use layer::Layer;
+    use optimizer::StepDecay;
+    use training::CsvDataset;
+    use super::*;
+
+    fn linear_trainer(learning_rate: Float) -> Trainer {
+        linear_trainer_until(TrainerHaltCondition::Epochs(50)).learning_rate(learning_rate)
+    }
+
+    fn linear_network() -> NeuralNetwork {
+        let mut rng = StdRng::from_seed(&[3usize][..]);
+        NeuralNetworkBuilder::with_inputs(1).output(1, 1, Identity, &mut rng)
+    }
+
+    fn linear_dataset(slope: Float) -> Vec<Sample> {
+        (0..10)
+            .map(|i| i as Float / 10.0)
+            .map(|x| Sample::dataset(vec![x], vec![slope * x]))
+            .collect()
+    }
+
+    fn linear_trainer_until(halt_condition: TrainerHaltCondition) -> Trainer {
+        Trainer::with_dataset(linear_network(), &linear_dataset(2.0))
+            .unwrap()
+            .halt_condition(halt_condition)
+            .unwrap()
+    }
+
+    #[test]
+    fn learning_rate() {
+        let mut trainer = linear_trainer(0.5);
+        let initial_loss = trainer.loss().unwrap();
+        trainer.train().unwrap();
+        let final_loss = trainer.loss().unwrap();
+        assert!(final_loss < 0.1 * initial_loss);
+
+        // too large a step overshoots the minimum further at each update
+        let mut trainer = linear_trainer(10.0);
+        let initial_loss = trainer.loss().unwrap();
+        match trainer.train() {
+            Ok(_) => assert!(trainer.loss().unwrap() > initial_loss),
+            Err(NetworkError::Diverged { .. }) => {}
+            Err(error) => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn target_loss() {
+        let mut trainer = linear_trainer_until(TrainerHaltCondition::TargetLoss {
+            loss: 1e-3,
+            max_epochs: 1000,
+        }).learning_rate(0.5);
+        let epochs = trainer.train().unwrap().len();
+        assert!(epochs > 0 && epochs < 50, "{} epochs", epochs);
+        assert!(trainer.loss().unwrap() < 1e-3);
+
+        // already below the target: no training at all
+        let inputs = arr2(&[[0.5]]);
+        let outputs = trainer.network_mut().predict(inputs.view()).unwrap();
+        assert!(trainer.train().unwrap().is_empty());
+        assert_eq!(trainer.network_mut().predict(inputs.view()).unwrap(), outputs);
+
+        // out of epochs before reaching the target
+        let mut trainer = linear_trainer_until(TrainerHaltCondition::TargetLoss {
+            loss: 1e-3,
+            max_epochs: 3,
+        }).learning_rate(1e-3);
+        let history = trainer.train().unwrap();
+        assert_eq!(history.len(), 3);
+        assert!(history[2] > 1e-3);
+
+        let mut trainer = linear_trainer_until(TrainerHaltCondition::TargetLoss {
+            loss: 1e-3,
+            max_epochs: 1000,
+        });
+        trainer = trainer.learning_rate(10.0);
+        match trainer.train() {
+            Err(NetworkError::Diverged { epoch }) => assert!(epoch > 0),
+            _ => panic!("the training should diverge"),
+        }
+        assert_eq!(trainer.validation_loss(), Err(NetworkError::NoValidationDataset));
+    }
+
+    #[test]
+    fn dataset_network_mismatch() {
+        let dataset = vec![Sample::dataset(vec![0.0, 1.0, 2.0], vec![1.0])];
+        let error = Trainer::with_dataset(linear_network(), &dataset).err();
+        assert_eq!(
+            error,
+            Some(NetworkError::DimensionMismatch {
+                expected: 1,
+                got: 3,
+            })
+        );
+
+        let dataset = vec![Sample::dataset(vec![0.0], vec![1.0, 0.0])];
+        let error = Trainer::with_dataset(linear_network(), &dataset).err();
+        assert_eq!(
+            error,
+            Some(NetworkError::DimensionMismatch {
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_halt_conditions() {
+        for halt_condition in vec![
+            TrainerHaltCondition::Epochs(0),
+            TrainerHaltCondition::TargetLoss {
+                loss: 0.0,
+                max_epochs: 10,
+            },
+            TrainerHaltCondition::TargetLoss {
+                loss: -1.0,
+                max_epochs: 10,
+            },
+            TrainerHaltCondition::TargetLoss {
+                loss: Float::NAN,
+                max_epochs: 10,
             },
+            TrainerHaltCondition::TargetLoss {
+                loss: 1e-3,
+                max_epochs: 0,
+            },
+            TrainerHaltCondition::EarlyStopping {
+                patience: 5,
+                min_delta: 0.0,
+            },
+        ] {
+            let trainer = linear_trainer(0.1);
+            assert!(trainer.halt_condition(halt_condition).is_none());
+        }
+    }
+
+    #[test]
+    fn early_stopping() {
+        // the validation targets contradict the training ones, so that the
+        // validation loss only increases: the best epoch is the initial one
+        let mut trainer =
+            Trainer::with_datasets(linear_network(), &linear_dataset(2.0), &linear_dataset(-2.0))
+                .unwrap()
+                .halt_condition(TrainerHaltCondition::EarlyStopping {
+                    patience: 5,
+                    min_delta: 0.0,
+                })
+                .unwrap();
+        let initial_loss = trainer.validation_loss().unwrap();
+        assert_eq!(trainer.train().unwrap().len(), 5);
+        assert_eq!(trainer.validation_loss().unwrap(), initial_loss);
+
+        // validation targets matching the training ones: training carries on
+        // until it stops improving
+        let mut trainer =
+            Trainer::with_datasets(linear_network(), &linear_dataset(2.0), &linear_dataset(2.0))
+                .unwrap()
+                .halt_condition(TrainerHaltCondition::EarlyStopping {
+                    patience: 3,
+                    min_delta: 1e-6,
+                })
+                .unwrap();
+        let initial_loss = trainer.validation_loss().unwrap();
+        assert!(trainer.train().unwrap().len() > 3);
+        assert!(trainer.validation_loss().unwrap() < initial_loss);
+    }
+
+    #[test]
+    fn restore_best() {
+        // the training targets overshoot the validation ones, so that the
+        // validation loss decreases then increases again
+        let trainer = || {
+            Trainer::with_datasets(linear_network(), &linear_dataset(2.0), &linear_dataset(1.0))
+                .unwrap()
+                .learning_rate(0.2)
+        };
+        let mut epoch_trainer = trainer();
+        let mut validation_losses = Vec::new();
+        let mut parameters = Vec::new();
+        for _ in 0..30 {
+            epoch_trainer.train().unwrap();
+            validation_losses.push(epoch_trainer.validation_loss().unwrap());
+            parameters.push(epoch_trainer.network().get_parameters());
         }
+        let best_epoch = (0..validation_losses.len())
+            .min_by(|&a, &b| validation_losses[a].partial_cmp(&validation_losses[b]).unwrap())
+            .unwrap();
+        assert!(best_epoch > 0 && best_epoch < validation_losses.len() - 1);
+
+        let mut best_trainer = trainer()
+            .halt_condition(TrainerHaltCondition::Epochs(30))
+            .unwrap()
+            .restore_best(true)
+            .unwrap();
+        best_trainer.train().unwrap();
+        assert_eq!(
+            best_trainer.validation_loss().unwrap(),
+            validation_losses[best_epoch]
+        );
+        assert_eq!(best_trainer.into_network().get_parameters(), parameters[best_epoch]);
+
+        let no_validation = Trainer::with_dataset(linear_network(), &linear_dataset(2.0)).unwrap();
+        assert!(no_validation.restore_best(true).is_none());
+    }
+
+    #[test]
+    fn momentum() {
+        let target_loss = || TrainerHaltCondition::TargetLoss {
+            loss: 1e-4,
+            max_epochs: 10_000,
+        };
+        let mut plain_trainer = linear_trainer_until(target_loss()).learning_rate(0.01);
+        let plain_history = plain_trainer.train().unwrap();
+        let mut momentum_trainer = linear_trainer_until(target_loss())
+            .learning_rate(0.01)
+            .momentum(0.9);
+        let momentum_history = momentum_trainer.train().unwrap();
+        assert!(*plain_history.last().unwrap() < 1e-4);
+        assert!(*momentum_history.last().unwrap() < 1e-4);
+        assert!(momentum_history.len() < plain_history.len());
+    }
+
+    #[test]
+    fn lr_schedule() {
+        let mut trainer = linear_trainer(1.0).lr_schedule(StepDecay::new(0.1, 0.5, 20).unwrap());
+        trainer.train().unwrap();
+        assert_eq!(trainer.optimizer.learning_rate(), 0.025);
+    }
+
+    #[test]
+    fn train_stream() {
+        let csv: String = linear_dataset(2.0)
+            .iter()
+            .map(|sample| format!("{},{}\n", sample.inputs()[0], sample.outputs().unwrap()[0]))
+            .collect();
+        let stream = || CsvDataset::from_reader(Cursor::new(csv.clone()), 1);
+
+        let mut trainer = linear_trainer(0.5);
+        assert_eq!(trainer.train_stream(stream()), Err(NetworkError::NoBatchSize));
+        let mut trainer = trainer.batch_size(3).unwrap();
+        let initial_loss = trainer.loss().unwrap();
+        for _ in 0..10 {
+            assert_eq!(trainer.train_stream(stream()), Ok(10));
+        }
+        assert!(trainer.loss().unwrap() < 0.1 * initial_loss);
+
+        let invalid = CsvDataset::from_reader(Cursor::new("0.5,1.0\n0.5,x\n"), 1);
+        assert_eq!(
+            trainer.train_stream(invalid),
+            Err(NetworkError::InvalidSample {
+                reason: "CsvDataset : line 2 : invalid value \"x\"".to_owned(),
+            })
+        );
+        // dimensions are checked against the network
+        let mismatch = CsvDataset::from_reader(Cursor::new("0.5,1.0,1.0\n"), 1);
+        assert!(trainer.train_stream(mismatch).is_err());
+        assert!(!trainer.network().layers()[0].training());
+    }
+
+    #[test]
+    fn on_epoch() {
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let mut trainer = {
+            let reported = Rc::clone(&reported);
+            linear_trainer(0.1).on_epoch(move |epoch, loss| {
+                assert_eq!(epoch as usize, reported.borrow().len());
+                reported.borrow_mut().push(loss);
+            })
+        };
+        let history = trainer.train().unwrap();
+        assert_eq!(history.len(), 50);
+        assert_eq!(*reported.borrow(), history);
+    }
+
+    #[test]
+    fn divergence() {
+        let mut trainer = linear_trainer(1e6);
+        let epoch = match trainer.train() {
+            Err(NetworkError::Diverged { epoch }) => epoch,
+            result => panic!("the training should diverge: {:?}", result),
+        };
+        assert!(epoch > 0 && epoch < 50, "epoch {}", epoch);
+        assert!(!trainer.loss().unwrap().is_finite());
+        assert!(!trainer.network().layers()[0].training());
+    }
+
+    #[test]
+    fn gradient_accumulation() {
+        let network = |l2| {
+            let mut rng = StdRng::from_seed(&[86usize][..]);
+            let init = WeightInit::XavierUniform;
+            NeuralNetwork::new(vec![
+                Layer::with_weight_init(TanH, init, 2, 3, 3, &mut rng).with_l2(l2),
+                Layer::with_weight_init(Identity, init, 3, 3, 1, &mut rng).with_l2(l2),
+            ])
+        };
+        let dataset: Vec<Sample> = (0..8)
+            .map(|i| i as Float / 8.0)
+            .map(|x| Sample::dataset(vec![x, 1.0 - x * x], vec![2.0 * x - 0.5]))
+            .collect();
+        let train = |l2, batch_size, accumulation_steps| {
+            let mut trainer = Trainer::with_dataset(network(l2), &dataset)
+                .unwrap()
+                .optimizer(GradientDescent::new(0.1))
+                .halt_condition(TrainerHaltCondition::Epochs(3))
+                .unwrap()
+                .batch_size(batch_size)
+                .unwrap()
+                .accumulation_steps(accumulation_steps)
+                .unwrap();
+            trainer.train().unwrap();
+            trainer.into_network().get_parameters()
+        };
+
+        for &l2 in &[0.0, 0.1] {
+            let accumulated = train(l2, 2, 4);
+            let concatenated = train(l2, 8, 1);
+            assert!(accumulated != network(l2).get_parameters());
+            for (&a, &c) in accumulated.iter().zip(concatenated.iter()) {
+                assert_relative_eq!(a, c, epsilon = 1e-6);
+            }
+            // without accumulation, the weights are updated after each batch
+            assert!(train(l2, 2, 1) != concatenated);
+        }
+
+        let trainer = Trainer::with_dataset(network(0.0), &dataset).unwrap();
+        assert!(trainer.accumulation_steps(0).is_none());
+    }
+
+    #[test]
+    fn seeded_trainings_are_reproducible() {
+        let dataset: Vec<Sample> = (0..12)
+            .map(|i| i as Float / 12.0)
+            .map(|x| Sample::dataset(vec![x, 1.0 - x * x], vec![2.0 * x - 0.5]))
+            .collect();
+        let train = |seed| {
+            let hidden_layer = Layer::with_init_fn(TanH, 2, 6, 4, |(i, j)| {
+                ((i * 5 + j * 3) % 7) as Float / 7.0 - 0.4
+            }).with_dropout(0.3);
+            let network = NeuralNetworkBuilder::with_seed(2, seed)
+                .add_layer(hidden_layer)
+                .unwrap()
+                .output(3, 1, Identity);
+            let mut trainer = Trainer::with_dataset(network, &dataset)
+                .unwrap()
+                .halt_condition(TrainerHaltCondition::Epochs(5))
+                .unwrap()
+                .batch_size(4)
+                .unwrap()
+                .shuffle(true)
+                .seed(seed);
+            trainer.train().unwrap();
+            trainer.into_network().get_parameters()
+        };
+        let (first, second) = (train(87), train(87));
+        assert!(
+            first
+                .iter()
+                .zip(second.iter())
+                .all(|(a, b)| a.to_bits() == b.to_bits())
+        );
+        assert!(train(88) != first);
     }
 }
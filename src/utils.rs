@@ -1,6 +1,11 @@
-use rand::Rng;
+use std::io;
+use std::path::Path;
+
+use rand::{Rng, SeedableRng, StdRng};
 use rand::distributions::IndependentSample;
-use ndarray::{Array2, ArrayBase, DataOwned, Dimension, NdFloat, ShapeBuilder};
+use ndarray::{Array1, Array2, ArrayBase, DataOwned, Dimension, NdFloat, ShapeBuilder, Zip};
+
+use super::{NetworkError, NetworkResult};
 
 /// Extends ndarray's ```ArrayBase``` to provide random arrays of any shape
 /// and any data type.
@@ -40,3 +45,80 @@ where
         Self::from_shape_fn(shape, |_| distribution.ind_sample(rng))
     }
 }
+
+/// Create the RNG of one `stream` of randomness (weights initialization,
+/// shuffling, dropout of a given layer...) derived from a master `seed`, so
+/// that all of them can be seeded at once while remaining independent.
+pub fn seeded_rng(seed: u64, stream: usize) -> StdRng {
+    StdRng::from_seed(&[seed as usize, (seed >> 32) as usize, stream][..])
+}
+
+/// Stream of the weights initialization of a ```SeededNeuralNetworkBuilder```.
+pub const INIT_STREAM: usize = 0;
+/// Stream of the shuffling of the samples by a ```Trainer```.
+pub const SHUFFLE_STREAM: usize = 1;
+/// First stream of the dropout masks, one stream per layer.
+pub const DROPOUT_STREAM: usize = 2;
+
+/// Check that a ([rows] * [columns]) shape matches the `expected` one,
+/// reporting the first mismatching dimension.
+pub fn check_shape(expected: (usize, usize), got: (usize, usize)) -> NetworkResult<()> {
+    if got.0 != expected.0 {
+        Err(NetworkError::DimensionMismatch {
+            expected: expected.0,
+            got: got.0,
+        })
+    } else if got.1 != expected.1 {
+        Err(NetworkError::DimensionMismatch {
+            expected: expected.1,
+            got: got.1,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Wrap an I/O `error` on the file at `path`.
+pub fn io_error(path: &Path, error: &io::Error) -> NetworkError {
+    NetworkError::Io {
+        path: path.to_path_buf(),
+        reason: error.to_string(),
+    }
+}
+
+/// Sum each column of a ([rows] * [columns]) matrix with Neumaier's
+/// compensated summation, whose rounding error does not grow with the number
+/// of rows as the one of a naive summation does.
+pub fn stable_column_sums<F: NdFloat>(values: &Array2<F>) -> Array1<F> {
+    let mut sums = Array1::zeros(values.cols());
+    let mut compensations = Array1::zeros(values.cols());
+    for row in values.genrows() {
+        Zip::from(&mut sums)
+            .and(&mut compensations)
+            .and(&row)
+            .apply(|sum: &mut F, compensation: &mut F, &value| {
+                let total = *sum + value;
+                // recover the low-order bits lost by the smaller operand
+                *compensation = *compensation + if sum.abs() >= value.abs() {
+                    (*sum - total) + value
+                } else {
+                    (value - total) + *sum
+                };
+                *sum = total;
+            });
+    }
+    sums + compensations
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr1, arr2};
+
+    use super::*;
+
+    #[test]
+    fn stable_column_sums() {
+        let values = arr2(&[[1.0, -2.0], [3.0, 4.0], [0.5, 0.0]]);
+        assert_eq!(super::stable_column_sums(&values), arr1(&[4.5, 2.0]));
+    }
+}
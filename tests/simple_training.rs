@@ -2,12 +2,41 @@ extern crate ndarray;
 extern crate rand;
 extern crate rust_neuralnet;
 
-use ndarray::arr2;
-use rand::thread_rng;
+use ndarray::{arr2, Array2};
+use rand::{thread_rng, SeedableRng, StdRng};
 
+use rust_neuralnet::Float;
 use rust_neuralnet::activation::Sigmoid;
 use rust_neuralnet::builder::NeuralNetworkBuilder;
-use rust_neuralnet::training::Sample;
+use rust_neuralnet::init::WeightInit;
+use rust_neuralnet::network::NeuralNetwork;
+use rust_neuralnet::optimizer::Adam;
+use rust_neuralnet::training::{Sample, Trainer, TrainerHaltCondition};
+
+fn xor_samples() -> Vec<Sample> {
+    let (t, f) = (1.0, 0.0);
+    vec![
+        Sample::dataset(vec![f, f], vec![f]),
+        Sample::dataset(vec![f, t], vec![t]),
+        Sample::dataset(vec![t, f], vec![t]),
+        Sample::dataset(vec![t, t], vec![f]),
+    ]
+}
+
+fn xor_dataset() -> (Array2<Float>, Array2<Float>) {
+    let inputs = arr2(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]);
+    let outputs = arr2(&[[0.0], [1.0], [1.0], [0.0]]);
+    (inputs, outputs)
+}
+
+fn mse(
+    neural_network: &mut NeuralNetwork,
+    inputs: &Array2<Float>,
+    expected: &Array2<Float>,
+) -> Float {
+    let outputs = neural_network.run_forward(inputs.view()).unwrap();
+    (&outputs - expected).map(|d| d * d).scalar_sum() / outputs.len() as Float
+}
 
 /// Train a Neural Network to replicate the XOR (exclusive) function with
 /// a single hidden layer.
@@ -38,3 +67,114 @@ fn xor() {
     let result = neural_network.run_forward(inputs.view());
     assert!(result.is_ok());
 }
+
+#[test]
+fn xor_gradient_descent() {
+    let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+    let mut neural_network = NeuralNetworkBuilder::with_inputs(2)
+        .layer(3, Sigmoid, &mut rng)
+        .output(3, 1, Sigmoid, &mut rng);
+    let (inputs, expected_outputs) = xor_dataset();
+
+    let initial_mse = mse(&mut neural_network, &inputs, &expected_outputs);
+    for _ in 0..100 {
+        neural_network
+            .backward_propagation(inputs.view(), expected_outputs.view())
+            .unwrap();
+        neural_network.update(0.5);
+    }
+    let final_mse = mse(&mut neural_network, &inputs, &expected_outputs);
+    assert!(
+        final_mse < initial_mse,
+        "MSE did not decrease: {} -> {}",
+        initial_mse,
+        final_mse
+    );
+}
+
+#[test]
+fn xor_mini_batches() {
+    let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+    let neural_network = NeuralNetworkBuilder::with_inputs(2)
+        .layer(3, Sigmoid, &mut rng)
+        .output(3, 1, Sigmoid, &mut rng);
+    let mut trainer = Trainer::with_dataset(neural_network, &xor_samples())
+        .unwrap()
+        .halt_condition(TrainerHaltCondition::Epochs(200))
+        .and_then(|trainer| trainer.batch_size(2))
+        .unwrap();
+
+    let initial_loss = trainer.loss().unwrap();
+    trainer.train().unwrap();
+    let final_loss = trainer.loss().unwrap();
+    assert!(
+        final_loss < initial_loss,
+        "loss did not decrease: {} -> {}",
+        initial_loss,
+        final_loss
+    );
+}
+
+#[test]
+fn xor_loss_history() {
+    let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+    let neural_network = NeuralNetworkBuilder::with_inputs(2)
+        .layer(3, Sigmoid, &mut rng)
+        .output(3, 1, Sigmoid, &mut rng);
+    let mut trainer = Trainer::with_dataset(neural_network, &xor_samples())
+        .unwrap()
+        .halt_condition(TrainerHaltCondition::Epochs(100))
+        .unwrap()
+        .learning_rate(0.5);
+
+    let history = trainer.train().unwrap();
+    assert_eq!(history.len(), 100);
+    let mean = |losses: &[Float]| losses.iter().sum::<Float>() / losses.len() as Float;
+    for window in history.chunks(25).collect::<Vec<_>>().windows(2) {
+        assert!(mean(window[1]) < mean(window[0]));
+    }
+}
+
+#[test]
+fn xor_adam() {
+    let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+    let neural_network = NeuralNetworkBuilder::with_inputs(2)
+        .init(WeightInit::XavierUniform)
+        .layer(6, Sigmoid, &mut rng)
+        .output(6, 1, Sigmoid, &mut rng);
+    let mut trainer = Trainer::with_dataset(neural_network, &xor_samples())
+        .unwrap()
+        .halt_condition(TrainerHaltCondition::Epochs(300))
+        .unwrap()
+        .optimizer(Adam::new(0.05));
+
+    let final_loss = *trainer.train().unwrap().last().unwrap();
+    assert!(final_loss < 0.01, "final loss: {}", final_loss);
+}
+
+/// Train the XOR Neural Network with single-precision weights.
+#[cfg(feature = "f32")]
+#[test]
+fn xor_single_precision() {
+    let mut rng = StdRng::from_seed(&[1, 2, 3, 4][..]);
+    let neural_network = NeuralNetworkBuilder::with_inputs(2)
+        .init(WeightInit::XavierUniform)
+        .layer(6, Sigmoid, &mut rng)
+        .output(6, 1, Sigmoid, &mut rng);
+    let mut trainer = Trainer::with_dataset(neural_network, &xor_samples())
+        .unwrap()
+        .halt_condition(TrainerHaltCondition::TargetLoss {
+            loss: 0.01,
+            max_epochs: 5000,
+        })
+        .unwrap()
+        .optimizer(Adam::new(0.05));
+    let final_loss = *trainer.train().unwrap().last().unwrap();
+    assert!(final_loss < 0.01, "final loss: {}", final_loss);
+
+    let (inputs, expected) = xor_dataset();
+    let outputs: Array2<f32> = trainer.network_mut().predict(inputs.view()).unwrap();
+    for (output, expected) in outputs.iter().zip(expected.iter()) {
+        assert!((output - expected).abs() < 0.2, "{} != {}", output, expected);
+    }
+}